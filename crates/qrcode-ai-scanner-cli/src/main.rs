@@ -1,6 +1,15 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use qrcode_ai_scanner_core::{decode_only, validate, validate_fast, ValidationResult, DecodeResult};
+use clap::{Parser, Subcommand};
+use qrcode_ai_scanner_core::print::{self, CameraModel, PrintOptions, PrintRecommendation};
+use qrcode_ai_scanner_core::expect::{check_content, MatchMode};
+use qrcode_ai_scanner_core::fidelity::ModuleFidelity;
+use qrcode_ai_scanner_core::micro;
+use qrcode_ai_scanner_core::structure::{FinderPosition, StructureHealth};
+use qrcode_ai_scanner_core::{
+    decode_consensus_with, decode_only_with, decode_structured_append_with, decode_tiled,
+    extract_grid_with, validate_fast_with, validate_with, AppendedMessage, Consensus, DecodeOptions,
+    DecodeResult, DecodeTrace, Detection, ModuleGrid, Payload, QrMetadata, ResourceLimits, RiskLevel, Symbology, ValidationResult,
+};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
@@ -10,9 +19,14 @@ use std::time::Instant;
 #[command(author = "Thibaut @ SuperNovae Studio")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "Validate QR codes and compute scannability score")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Image file to validate (PNG, JPEG, etc.)
-    image: PathBuf,
+    #[arg(required = true)]
+    image: Option<PathBuf>,
 
     /// Output only the score (0-100), useful for scripts
     #[arg(long, short = 's')]
@@ -37,6 +51,169 @@ struct Cli {
     /// Quiet mode: minimal output
     #[arg(long, short = 'q')]
     quiet: bool,
+
+    /// Printer resolution for the print size recommendation
+    #[arg(long, value_name = "DPI")]
+    dpi: Option<u32>,
+
+    /// Expected scan distance in millimetres
+    #[arg(long, value_name = "MM")]
+    scan_distance: Option<f32>,
+
+    /// Planned printed width of the code (without quiet zone) in millimetres
+    #[arg(long, value_name = "MM")]
+    print_width: Option<f32>,
+
+    /// Phone camera horizontal field of view in degrees
+    #[arg(long, value_name = "DEGREES")]
+    camera_fov: Option<f32>,
+
+    /// Width in pixels of the camera frame analysed by the scanner
+    #[arg(long, value_name = "PX")]
+    camera_resolution: Option<u32>,
+
+    /// Fail unless the code encodes exactly this content
+    #[arg(long, value_name = "CONTENT")]
+    expect: Option<String>,
+
+    /// With --expect: compare as URLs (host case, default ports, trailing slash ignored)
+    #[arg(long, requires = "expect")]
+    normalize_url: bool,
+
+    /// Symbologies to accept, comma-separated (qr, microqr, rmqr, datamatrix, aztec, pdf417)
+    #[arg(long, value_name = "LIST", value_delimiter = ',', global = true)]
+    formats: Vec<String>,
+
+    /// Reject images wider or taller than this many pixels (default 10000)
+    #[arg(long, value_name = "PX", global = true)]
+    max_dimension: Option<u32>,
+
+    /// Reject images with more pixels than this (default 100000000)
+    #[arg(long, value_name = "N", global = true)]
+    max_pixels: Option<u64>,
+
+    /// Reject image files larger than this many bytes (default 64 MiB)
+    #[arg(long, value_name = "BYTES", global = true)]
+    max_input_bytes: Option<usize>,
+
+    /// Cap the memory the image decoder may allocate, in bytes (default 512 MiB)
+    #[arg(long, value_name = "BYTES", global = true)]
+    max_memory: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show the sampled module grid as block art (for debugging stylized codes)
+    Grid {
+        /// Image file containing the QR code
+        image: PathBuf,
+
+        /// JSON output (modules, per-module confidence, location)
+        #[arg(long, short = 'j')]
+        json: bool,
+
+        /// Compact text output: one row per line, '#' dark and '.' light
+        #[arg(long, conflicts_with = "json")]
+        text: bool,
+
+        /// Highlight modules sampled with less confidence than this (0.0-1.0)
+        #[arg(long, value_name = "RATIO", default_value_t = 0.5)]
+        min_confidence: f32,
+    },
+
+    /// Decode with every decoder and variant; fail if they read different content
+    Consensus {
+        /// Image file containing the QR code
+        image: PathBuf,
+
+        /// JSON output (every distinct content, decoders and variants)
+        #[arg(long, short = 'j')]
+        json: bool,
+    },
+
+    /// Find every code in a large scene by scanning overlapping tiles
+    Tiles {
+        /// Image file to scan
+        image: PathBuf,
+
+        /// JSON output (content, metadata, corners and tile per code)
+        #[arg(long, short = 'j')]
+        json: bool,
+    },
+
+    /// Reassemble a Structured Append message split across several QR codes
+    Append {
+        /// Image files holding the parts, in any order
+        #[arg(required = true)]
+        images: Vec<PathBuf>,
+
+        /// JSON output (message, part count, parity, charset)
+        #[arg(long, short = 'j')]
+        json: bool,
+    },
+}
+
+impl Cli {
+    /// Check decoded content against --expect, if given
+    fn check_expected(&self, raw_bytes: Option<&[u8]>, content: &str) -> Result<()> {
+        let Some(expected) = &self.expect else {
+            return Ok(());
+        };
+        let mode = if self.normalize_url { MatchMode::Url } else { MatchMode::Exact };
+        check_content(raw_bytes, content, expected.as_bytes(), mode)
+            .context("QR code does not encode the expected content")
+    }
+
+    /// Decode options from --formats (the QR family when none are given) and the limits
+    fn decode_options(&self) -> Result<DecodeOptions> {
+        let mut options = DecodeOptions {
+            limits: self.limits(),
+            ..DecodeOptions::default()
+        };
+        if !self.formats.is_empty() {
+            options.formats = self
+                .formats
+                .iter()
+                .map(|name| Symbology::from_name(name).with_context(|| format!("Unknown format: {}", name)))
+                .collect::<Result<Vec<_>>>()?;
+        }
+        Ok(options)
+    }
+
+    /// Resource limits from the --max-* flags, defaults for the rest
+    fn limits(&self) -> ResourceLimits {
+        let defaults = ResourceLimits::default();
+        ResourceLimits {
+            max_width: self.max_dimension.unwrap_or(defaults.max_width),
+            max_height: self.max_dimension.unwrap_or(defaults.max_height),
+            max_pixels: self.max_pixels.unwrap_or(defaults.max_pixels),
+            max_input_bytes: self.max_input_bytes.unwrap_or(defaults.max_input_bytes),
+            max_decode_memory: self.max_memory.unwrap_or(defaults.max_decode_memory),
+        }
+    }
+
+    /// Print options from the command line, or None when all defaults apply
+    fn print_options(&self) -> Option<PrintOptions> {
+        if self.dpi.is_none()
+            && self.scan_distance.is_none()
+            && self.print_width.is_none()
+            && self.camera_fov.is_none()
+            && self.camera_resolution.is_none()
+        {
+            return None;
+        }
+
+        let defaults = PrintOptions::default();
+        Some(PrintOptions {
+            dpi: self.dpi.unwrap_or(defaults.dpi),
+            scan_distance_mm: self.scan_distance.unwrap_or(defaults.scan_distance_mm),
+            printed_width_mm: self.print_width,
+            camera: CameraModel {
+                horizontal_fov_deg: self.camera_fov.unwrap_or(defaults.camera.horizontal_fov_deg),
+                resolution_px: self.camera_resolution.unwrap_or(defaults.camera.resolution_px),
+            },
+        })
+    }
 }
 
 // ANSI color codes
@@ -57,6 +234,22 @@ mod colors {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+
+    if let Some(Command::Grid { image, json, text, min_confidence }) = &cli.command {
+        return run_grid(image, *json, *text, *min_confidence, &cli.limits());
+    }
+    if let Some(Command::Consensus { image, json }) = &cli.command {
+        return run_consensus(image, *json, &cli.limits());
+    }
+    if let Some(Command::Tiles { image, json }) = &cli.command {
+        return run_tiles(image, *json, &cli.decode_options()?);
+    }
+    if let Some(Command::Append { images, json }) = &cli.command {
+        return run_append(images, *json, &cli.limits());
+    }
+
+    let image = cli.image.clone().context("No image file given")?;
+    let options = cli.decode_options()?;
     let start = Instant::now();
 
     // Show banner unless quiet or json mode
@@ -64,31 +257,32 @@ fn main() -> Result<()> {
         print_banner();
     }
 
-    let image_bytes = std::fs::read(&cli.image)
-        .with_context(|| format!("Failed to read image file: {:?}", cli.image))?;
+    let image_bytes = std::fs::read(&image)
+        .with_context(|| format!("Failed to read image file: {:?}", image))?;
 
     let read_time = start.elapsed();
 
     if cli.decode_only {
-        let result = decode_only(&image_bytes)
+        let result = decode_only_with(&image_bytes, &options)
             .with_context(|| "Failed to decode QR code")?;
         let total_time = start.elapsed();
 
         if cli.json {
             println!("{}", serde_json::to_string_pretty(&result)?);
         } else if !cli.quiet {
-            print_decode_result(&result, &cli.image, total_time.as_millis() as u64);
+            print_decode_result(&result, &image, total_time.as_millis() as u64);
         }
 
         if cli.timing {
             eprintln!("{}⏱  Read: {:?}, Decode: {:?}, Total: {:?}{}",
                 colors::DIM, read_time, total_time - read_time, total_time, colors::RESET);
         }
+        cli.check_expected(result.raw_bytes.as_deref(), &result.content)?;
     } else if cli.score_only {
         let result = if cli.fast {
-            validate_fast(&image_bytes)
+            validate_fast_with(&image_bytes, &options)
         } else {
-            validate(&image_bytes)
+            validate_with(&image_bytes, &options)
         }.with_context(|| "Failed to validate QR code")?;
 
         println!("{}", result.score);
@@ -98,13 +292,21 @@ fn main() -> Result<()> {
             eprintln!("{}⏱  Total: {:?}{}",
                 colors::DIM, total_time, colors::RESET);
         }
+        cli.check_expected(result.raw_bytes.as_deref(), result.content.as_deref().unwrap_or_default())?;
     } else {
-        let result = if cli.fast {
-            validate_fast(&image_bytes)
+        let mut result = if cli.fast {
+            validate_fast_with(&image_bytes, &options)
         } else {
-            validate(&image_bytes)
+            validate_with(&image_bytes, &options)
         }.with_context(|| "Failed to validate QR code")?;
 
+        // Re-run the print recommendation with the user's print conditions
+        if let Some(options) = cli.print_options() {
+            result.print = result.print.map(|p| {
+                print::recommend(p.modules, p.required_pixels_per_module, &options)
+            });
+        }
+
         let total_time = start.elapsed();
 
         if cli.json {
@@ -112,18 +314,192 @@ fn main() -> Result<()> {
         } else if cli.quiet {
             println!("{}", result.score);
         } else {
-            print_validation_result(&result, &cli.image, total_time.as_millis() as u64, cli.fast);
+            print_validation_result(&result, &image, total_time.as_millis() as u64, cli.fast);
         }
 
         if cli.timing && !cli.json {
             eprintln!("\n{}⏱  Read: {:?}, Validate: {:?}, Total: {:?}{}",
                 colors::DIM, read_time, total_time - read_time, total_time, colors::RESET);
         }
+        cli.check_expected(result.raw_bytes.as_deref(), result.content.as_deref().unwrap_or_default())?;
     }
 
     Ok(())
 }
 
+/// `grid` subcommand: extract the module matrix and print it
+fn run_grid(image: &Path, json: bool, text: bool, min_confidence: f32, limits: &ResourceLimits) -> Result<()> {
+    let image_bytes = std::fs::read(image)
+        .with_context(|| format!("Failed to read image file: {:?}", image))?;
+    let grid = extract_grid_with(&image_bytes, limits)
+        .with_context(|| "Failed to locate a QR code")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&grid)?);
+    } else if text {
+        println!("{}", grid.to_text());
+    } else {
+        print_grid_art(&grid, min_confidence);
+    }
+
+    Ok(())
+}
+
+/// Render the grid as terminal block art, light modules drawn solid
+///
+/// Drawn this way round so the art scans on a dark terminal background.
+/// Modules below `min_confidence` are highlighted in red.
+fn print_grid_art(grid: &ModuleGrid, min_confidence: f32) {
+    const QUIET: isize = 2;
+    let size = grid.size as isize;
+
+    println!();
+    for y in -QUIET..size + QUIET {
+        let mut line = String::from("  ");
+        for x in -QUIET..size + QUIET {
+            let inside = (0..size).contains(&x) && (0..size).contains(&y);
+            if !inside {
+                line.push_str("██");
+                continue;
+            }
+            let (xu, yu) = (x as usize, y as usize);
+            let dark = grid.is_dark(xu, yu);
+            if grid.confidence[yu][xu] < min_confidence {
+                line.push_str(colors::RED);
+                line.push_str(if dark { "░░" } else { "▓▓" });
+                line.push_str(colors::RESET);
+            } else {
+                line.push_str(if dark { "  " } else { "██" });
+            }
+        }
+        println!("{}", line);
+    }
+    println!();
+
+    let low = grid.low_confidence_count(min_confidence);
+    println!("  {}Modules:{}          {}x{}", colors::DIM, colors::RESET, grid.size, grid.size);
+    println!("  {}Mean confidence:{}  {:.0}%", colors::DIM, colors::RESET, grid.mean_confidence() * 100.0);
+    println!("  {}Low confidence:{}   {}{}{}  {}(below {:.0}%, ░░ read dark / ▓▓ read light){}",
+        colors::DIM, colors::RESET,
+        if low > 0 { colors::RED } else { colors::GREEN }, low, colors::RESET,
+        colors::DIM, min_confidence * 100.0, colors::RESET);
+    match grid.decode() {
+        Ok(content) => println!("  {}Grid decodes:{}     {}✓{} {}",
+            colors::DIM, colors::RESET, colors::GREEN, colors::RESET, content),
+        Err(_) => println!("  {}Grid decodes:{}     {}✗ sampled grid does not decode on its own{}",
+            colors::DIM, colors::RESET, colors::RED, colors::RESET),
+    }
+    println!();
+}
+
+/// `consensus` subcommand: report every content read, fail when ambiguous
+fn run_consensus(image: &Path, json: bool, limits: &ResourceLimits) -> Result<()> {
+    let image_bytes = std::fs::read(image)
+        .with_context(|| format!("Failed to read image file: {:?}", image))?;
+    let consensus = decode_consensus_with(&image_bytes, limits)
+        .with_context(|| "Failed to decode QR code")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&consensus)?);
+    } else {
+        print_consensus(&consensus);
+    }
+
+    if consensus.ambiguous {
+        anyhow::bail!("Ambiguous QR code: {} different contents read", consensus.readings.len());
+    }
+    Ok(())
+}
+
+fn print_consensus(consensus: &Consensus) {
+    println!();
+    if consensus.ambiguous {
+        println!("  {}{}⚠ AMBIGUOUS{}  {}decoders disagree across {} variants{}",
+            colors::BOLD, colors::RED, colors::RESET,
+            colors::DIM, consensus.variants_tried, colors::RESET);
+    } else {
+        println!("  {}{}✓ CONSENSUS{}  {}all decoders agree across {} variants{}",
+            colors::BOLD, colors::GREEN, colors::RESET,
+            colors::DIM, consensus.variants_tried, colors::RESET);
+    }
+    println!();
+    for reading in &consensus.readings {
+        println!("  {}{}{}", colors::WHITE, reading.content, colors::RESET);
+        println!("    {}Reads:{}     {}", colors::DIM, colors::RESET, reading.reads);
+        println!("    {}Decoders:{}  {}", colors::DIM, colors::RESET, reading.decoders.join(", "));
+        println!("    {}Variants:{}  {}", colors::DIM, colors::RESET, reading.variants.join(", "));
+    }
+    println!();
+}
+
+fn run_append(images: &[PathBuf], json: bool, limits: &ResourceLimits) -> Result<()> {
+    let image_bytes = images
+        .iter()
+        .map(|image| std::fs::read(image).with_context(|| format!("Failed to read image file: {:?}", image)))
+        .collect::<Result<Vec<_>>>()?;
+    let slices: Vec<&[u8]> = image_bytes.iter().map(Vec::as_slice).collect();
+    let message = decode_structured_append_with(&slices, limits)
+        .with_context(|| "Failed to reassemble Structured Append message")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&message)?);
+    } else {
+        print_appended(&message);
+    }
+    Ok(())
+}
+
+/// `tiles` subcommand: every code found tile by tile, in image coordinates
+fn run_tiles(image: &Path, json: bool, options: &DecodeOptions) -> Result<()> {
+    let image_bytes = std::fs::read(image)
+        .with_context(|| format!("Failed to read image file: {:?}", image))?;
+    let detections = decode_tiled(&image_bytes, options)
+        .with_context(|| "Failed to find a QR code in any tile")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&detections)?);
+    } else {
+        print_detections(&detections);
+    }
+    Ok(())
+}
+
+fn print_detections(detections: &[Detection]) {
+    println!();
+    println!("  {}{}✓ FOUND {}{}", colors::BOLD, colors::GREEN, detections.len(), colors::RESET);
+    println!();
+    for detection in detections {
+        println!("  {}{}{}", colors::WHITE, detection.content, colors::RESET);
+        if let Some(meta) = &detection.metadata {
+            println!("    {}Symbol:{}   {}", colors::DIM, colors::RESET, meta.symbology);
+        }
+        match detection.corners {
+            Some(corners) => {
+                let points: Vec<String> = corners.iter().map(|(x, y)| format!("({:.0}, {:.0})", x, y)).collect();
+                println!("    {}Corners:{}  {}", colors::DIM, colors::RESET, points.join(" "));
+            }
+            None => {
+                let tile = detection.tile;
+                println!("    {}Tile:{}     {}x{} at ({}, {})",
+                    colors::DIM, colors::RESET, tile.width, tile.height, tile.x, tile.y);
+            }
+        }
+    }
+    println!();
+}
+
+fn print_appended(message: &AppendedMessage) {
+    println!();
+    println!("  {}{}✓ REASSEMBLED{}  {}{} parts, parity {:#04x} verified{}",
+        colors::BOLD, colors::GREEN, colors::RESET,
+        colors::DIM, message.total, message.parity, colors::RESET);
+    println!();
+    println!("  {}{}{}", colors::WHITE, message.content, colors::RESET);
+    println!("    {}Bytes:{}    {}", colors::DIM, colors::RESET, message.raw_bytes.len());
+    println!("    {}Charset:{}  {:?}", colors::DIM, colors::RESET, message.charset);
+    println!();
+}
+
 fn print_banner() {
     println!(r#"
 {}{}   ___  ____      _    ___      {}
@@ -187,12 +563,23 @@ fn print_decode_result(result: &DecodeResult, path: &Path, time_ms: u64) {
     if let Some(ref meta) = result.metadata {
         println!();
         println!("  {}📊 METADATA{}", colors::DIM, colors::RESET);
-        println!("  {}├── Version:    {}v{}{}",
-            colors::DIM, colors::WHITE, meta.version, colors::RESET);
+        println!("  {}├── Symbology:  {}{}{}",
+            colors::DIM, colors::WHITE, meta.symbology, colors::RESET);
+        println!("  {}├── Version:    {}{}{}",
+            colors::DIM, colors::WHITE, version_label(meta), colors::RESET);
         println!("  {}├── EC Level:   {}{}{}",
             colors::DIM, colors::WHITE, meta.error_correction, colors::RESET);
-        println!("  {}└── Modules:    {}{}x{}{}",
-            colors::DIM, colors::WHITE, meta.modules, meta.modules, colors::RESET);
+        if let Some(orientation) = orientation_label(meta) {
+            println!("  {}├── Orientation:{} {}{}",
+                colors::DIM, colors::YELLOW, orientation, colors::RESET);
+        }
+        let corner = if meta.trace.is_some() { "├" } else { "└" };
+        println!("  {}{}── Modules:    {}{}{}",
+            colors::DIM, corner, colors::WHITE, size_label(meta), colors::RESET);
+        if let Some(ref trace) = meta.trace {
+            println!("  {}└── Stage:      {}{}{}",
+                colors::DIM, colors::WHITE, trace_label(trace), colors::RESET);
+        }
     }
 
     println!();
@@ -251,6 +638,35 @@ fn print_validation_result(result: &ValidationResult, path: &Path, time_ms: u64,
             }
         }
 
+        if let Some(ref parsed) = result.payload {
+            println!("  {}├─────────────────────────────────────────────────────────────────┤{}",
+                colors::BLUE, colors::RESET);
+            println!("  {}│{}  Type:             {}{}{}",
+                colors::BLUE, colors::RESET, colors::BOLD, parsed.payload.kind(), colors::RESET);
+            if !matches!(parsed.payload, Payload::Text { .. } | Payload::Url { .. }) {
+                for (name, value) in parsed.payload.fields() {
+                    println!("  {}│{}  {:<17} {}", colors::BLUE, colors::RESET, format!("{}:", name), value);
+                }
+            }
+            for error in &parsed.errors {
+                println!("  {}│{}  {}✗ {}: {}{}",
+                    colors::BLUE, colors::RESET, colors::RED, error.field, error.message, colors::RESET);
+            }
+        }
+
+        if let Some(ref risk) = result.url_risk {
+            let color = match risk.level {
+                RiskLevel::Safe => colors::GREEN,
+                RiskLevel::Low => colors::YELLOW,
+                RiskLevel::Medium | RiskLevel::High => colors::RED,
+            };
+            println!("  {}│{}  Link Risk:        {}{}{}{}",
+                colors::BLUE, colors::RESET, colors::BOLD, color, risk.level, colors::RESET);
+            for reason in &risk.reasons {
+                println!("  {}│{}  {}⚠ {}{}", colors::BLUE, colors::RESET, color, reason.detail, colors::RESET);
+            }
+        }
+
         println!("  {}╰─────────────────────────────────────────────────────────────────╯{}",
             colors::BLUE, colors::RESET);
         println!();
@@ -283,24 +699,157 @@ fn print_validation_result(result: &ValidationResult, path: &Path, time_ms: u64,
             colors::CYAN, colors::RESET, colors::CYAN, colors::RESET);
         println!("  {}├─────────────────────────────────────────────────────────────────┤{}",
             colors::CYAN, colors::RESET);
-        println!("  {}│{}  Version:          {}v{:<3}{}  (size complexity)                    {}│{}",
-            colors::CYAN, colors::RESET, colors::BOLD, meta.version, colors::RESET, colors::CYAN, colors::RESET);
+        if meta.symbology != Symbology::Qr {
+            println!("  {}│{}  Symbology:        {}{:<11}{}  ({}-module quiet zone)            {}│{}",
+                colors::CYAN, colors::RESET, colors::BOLD, meta.symbology.to_string(), colors::RESET,
+                meta.symbology.quiet_zone_modules(), colors::CYAN, colors::RESET);
+        }
+        println!("  {}│{}  Version:          {}{:<4}{}  (size complexity)                    {}│{}",
+            colors::CYAN, colors::RESET, colors::BOLD, version_label(meta), colors::RESET, colors::CYAN, colors::RESET);
         println!("  {}│{}  Error Correction: {}{}{}    ({})                              {}│{}",
             colors::CYAN, colors::RESET, colors::BOLD, meta.error_correction, colors::RESET,
             get_ec_description(meta.error_correction), colors::CYAN, colors::RESET);
-        println!("  {}│{}  Modules:          {}{}x{}{}  (grid size)                         {}│{}",
-            colors::CYAN, colors::RESET, colors::BOLD, meta.modules, meta.modules, colors::RESET,
+        println!("  {}│{}  Modules:          {}{}{}  (grid size)                         {}│{}",
+            colors::CYAN, colors::RESET, colors::BOLD, size_label(meta), colors::RESET,
             colors::CYAN, colors::RESET);
         println!("  {}│{}  Decoders:         {}{}{}                                     {}│{}",
             colors::CYAN, colors::RESET, colors::GREEN,
             meta.decoders_success.join(", "), colors::RESET, colors::CYAN, colors::RESET);
+        if let Some(ref trace) = meta.trace {
+            println!("  {}│{}  Stage:            {}",
+                colors::CYAN, colors::RESET, trace_label(trace));
+        }
+        if let Some(orientation) = orientation_label(meta) {
+            println!("  {}│{}  Orientation:      {}{}{}  (many phone scanners fail)",
+                colors::CYAN, colors::RESET, colors::YELLOW, orientation, colors::RESET);
+        }
+        if let (Some(ratio), Some(usage)) = (meta.ec_used_ratio, meta.ec_usage) {
+            println!("  {}│{}  EC Budget Used:   {}{:.0}%{}  ({}/{} codewords, {}/{} blocks corrected)",
+                colors::CYAN, colors::RESET, colors::BOLD, ratio * 100.0, colors::RESET,
+                usage.codewords_corrected, usage.codewords_capacity,
+                usage.blocks_corrected, usage.blocks);
+        }
+        if let Some(charset) = meta.charset {
+            let source = match meta.eci {
+                Some(eci) => format!("ECI {}", eci),
+                None => "detected".to_string(),
+            };
+            println!("  {}│{}  Charset:          {}{}{}  ({})",
+                colors::CYAN, colors::RESET, colors::BOLD, charset, colors::RESET, source);
+        }
+        if !meta.segments.is_empty() {
+            let segments: Vec<String> = meta.segments.iter()
+                .map(|s| format!("{}({})", s.mode, s.char_count))
+                .collect();
+            println!("  {}│{}  Segments:         {}",
+                colors::CYAN, colors::RESET, segments.join(" "));
+        }
         println!("  {}╰─────────────────────────────────────────────────────────────────╯{}",
             colors::CYAN, colors::RESET);
     }
 
+    if let Some(ref structure) = result.structure {
+        print_structure_health(structure);
+    }
+
+    if let Some(ref fidelity) = result.fidelity {
+        print_module_fidelity(fidelity);
+    }
+
+    if let Some(ref print) = result.print {
+        print_print_recommendation(print);
+    }
+
     println!();
 }
 
+fn print_structure_health(structure: &StructureHealth) {
+    let (score_color, _, _) = get_score_style(structure.score);
+    println!();
+    println!("  {}╭─────────────────────────────────────────────────────────────────╮{}",
+        colors::WHITE, colors::RESET);
+    println!("  {}│{} 🎯 PATTERN HEALTH                                               {}│{}",
+        colors::WHITE, colors::RESET, colors::WHITE, colors::RESET);
+    println!("  {}├─────────────────────────────────────────────────────────────────┤{}",
+        colors::WHITE, colors::RESET);
+    println!("  {}│{}  Overall:          {}{}{:>3}/100{}",
+        colors::WHITE, colors::RESET, colors::BOLD, score_color, structure.score, colors::RESET);
+    for finder in &structure.finders {
+        let name = match finder.position {
+            FinderPosition::TopLeft => "Finder (TL):",
+            FinderPosition::TopRight => "Finder (TR):",
+            FinderPosition::BottomLeft => "Finder (BL):",
+        };
+        println!("  {}│{}  {:<18}{}{:>3}/100{}  {}(ratio {}, shape {}){}",
+            colors::WHITE, colors::RESET, name, colors::BOLD, finder.score, colors::RESET,
+            colors::DIM, finder.ratio_score, finder.shape_score, colors::RESET);
+    }
+    println!("  {}│{}  Timing:           {}{:>3}/100{}  {}(row {}, column {}){}",
+        colors::WHITE, colors::RESET, colors::BOLD,
+        (structure.timing_horizontal as u16 + structure.timing_vertical as u16) / 2, colors::RESET,
+        colors::DIM, structure.timing_horizontal, structure.timing_vertical, colors::RESET);
+    if let Some(worst) = structure.alignment.iter().min_by_key(|a| a.score) {
+        println!("  {}│{}  Alignment:        {}{:>3}/100{}  {}(weakest of {}, at {},{}){}",
+            colors::WHITE, colors::RESET, colors::BOLD, worst.score, colors::RESET,
+            colors::DIM, structure.alignment.len(), worst.x, worst.y, colors::RESET);
+    }
+    println!("  {}╰─────────────────────────────────────────────────────────────────╯{}",
+        colors::WHITE, colors::RESET);
+}
+
+fn print_module_fidelity(fidelity: &ModuleFidelity) {
+    let budget_color = if fidelity.ec_budget_used >= 0.75 {
+        colors::RED
+    } else if fidelity.ec_budget_used >= 0.4 {
+        colors::YELLOW
+    } else {
+        colors::GREEN
+    };
+    println!();
+    println!("  {}╭─────────────────────────────────────────────────────────────────╮{}",
+        colors::WHITE, colors::RESET);
+    println!("  {}│{} 🔬 MODULE FIDELITY                                              {}│{}",
+        colors::WHITE, colors::RESET, colors::WHITE, colors::RESET);
+    println!("  {}├─────────────────────────────────────────────────────────────────┤{}",
+        colors::WHITE, colors::RESET);
+    println!("  {}│{}  Flipped modules:  {}{}{}  ({:.1}% of {}x{})",
+        colors::WHITE, colors::RESET, colors::BOLD, fidelity.flipped_modules, colors::RESET,
+        fidelity.flipped_ratio * 100.0, fidelity.modules, fidelity.modules);
+    println!("  {}│{}  EC budget used:   {}{}{:.0}%{}  (worst block, {}/{} codewords corrected)",
+        colors::WHITE, colors::RESET, colors::BOLD, budget_color, fidelity.ec_budget_used * 100.0,
+        colors::RESET, fidelity.codewords_corrected, fidelity.codewords_correctable);
+    if let Some(worst) = fidelity.error_map.iter().filter(|r| r.flipped > 0).max_by_key(|r| r.flipped) {
+        println!("  {}│{}  Worst region:     {}{} flipped{}  {}(modules {},{} to {},{}){}",
+            colors::WHITE, colors::RESET, colors::BOLD, worst.flipped, colors::RESET, colors::DIM,
+            worst.x, worst.y, worst.x + worst.width - 1, worst.y + worst.height - 1, colors::RESET);
+    }
+    println!("  {}╰─────────────────────────────────────────────────────────────────╯{}",
+        colors::WHITE, colors::RESET);
+}
+
+fn print_print_recommendation(print: &PrintRecommendation) {
+    println!();
+    println!("  {}╭─────────────────────────────────────────────────────────────────╮{}",
+        colors::YELLOW, colors::RESET);
+    println!("  {}│{} 🖨  PRINT SIZE                                                    {}│{}",
+        colors::YELLOW, colors::RESET, colors::YELLOW, colors::RESET);
+    println!("  {}├─────────────────────────────────────────────────────────────────┤{}",
+        colors::YELLOW, colors::RESET);
+    println!("  {}│{}  Min width:        {}{:.1} mm{}  at {} dpi, {:.0} cm away ({} dots/module)",
+        colors::YELLOW, colors::RESET, colors::BOLD, print.min_printed_width_mm, colors::RESET,
+        print.dpi, print.scan_distance_mm / 10.0, print.dots_per_module);
+    println!("  {}│{}  Quiet zone:       {}{:.1} mm{}  on each side",
+        colors::YELLOW, colors::RESET, colors::BOLD, print.quiet_zone_mm, colors::RESET);
+    println!("  {}│{}  Max distance:     {}{:.0} cm{}  when printed {:.1} mm wide",
+        colors::YELLOW, colors::RESET, colors::BOLD, print.max_scan_distance_mm / 10.0, colors::RESET,
+        print.printed_width_mm);
+    println!("  {}│{}  Camera:           {}{:.0}° FOV, {} px{}  ({:.1} px/module needed)",
+        colors::YELLOW, colors::RESET, colors::DIM, print.camera.horizontal_fov_deg,
+        print.camera.resolution_px, colors::RESET, print.required_pixels_per_module);
+    println!("  {}╰─────────────────────────────────────────────────────────────────╯{}",
+        colors::YELLOW, colors::RESET);
+}
+
 fn print_score_bar(score: u8) {
     let bar_width = 60;
     let filled = (score as usize * bar_width) / 100;
@@ -405,6 +954,50 @@ fn get_score_style(score: u8) -> (&'static str, &'static str, &'static str) {
     }
 }
 
+/// Version as printed on the symbol: `v5`, `M3` or `R7x43` (`-` outside the QR family)
+fn version_label(meta: &QrMetadata) -> String {
+    match (meta.symbology, micro::symbol_size(meta.symbology, meta.version)) {
+        (Symbology::MicroQr, _) => format!("M{}", meta.version),
+        (Symbology::RectangularMicroQr, Some((columns, rows))) => format!("R{}x{}", rows, columns),
+        (Symbology::DataMatrix | Symbology::Aztec | Symbology::Pdf417, _) => "-".to_string(),
+        _ => format!("v{}", meta.version),
+    }
+}
+
+/// Module grid as columns x rows (`-` when unknown)
+fn size_label(meta: &QrMetadata) -> String {
+    match micro::symbol_size(meta.symbology, meta.version) {
+        Some((columns, rows)) => format!("{}x{}", columns, rows),
+        None if meta.modules == 0 => "-".to_string(),
+        None => format!("{}x{}", meta.modules, meta.modules),
+    }
+}
+
+/// Decode stage, plus the crop it ran on: `parallel pool (texture crop 792x628 at 108,0)`
+/// or the upscaling it needed: `upscale (bicubic x6)`
+fn trace_label(trace: &DecodeTrace) -> String {
+    let label = match (trace.roi, trace.upscale) {
+        (Some(roi), _) => format!("{} ({} crop {}x{} at {},{})",
+            trace.stage, roi.source, roi.region.width, roi.region.height, roi.region.x, roi.region.y),
+        (None, Some(upscale)) => format!("{} ({} x{})", trace.stage, upscale.filter, upscale.factor),
+        (None, None) => trace.stage.to_string(),
+    };
+    if trace.format_recovered {
+        format!("{label}, format recovered")
+    } else {
+        label
+    }
+}
+
+fn orientation_label(meta: &QrMetadata) -> Option<&'static str> {
+    match (meta.inverted, meta.mirrored) {
+        (false, false) => None,
+        (true, false) => Some("inverted"),
+        (false, true) => Some("mirrored"),
+        (true, true) => Some("inverted, mirrored"),
+    }
+}
+
 fn get_ec_description(ec: qrcode_ai_scanner_core::ErrorCorrectionLevel) -> &'static str {
     match ec {
         qrcode_ai_scanner_core::ErrorCorrectionLevel::L => "~7% recovery",
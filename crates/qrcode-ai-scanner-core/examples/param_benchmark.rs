@@ -3,6 +3,7 @@
 //! Run with: cargo run --release -p qrcode-ai-scanner-core --example param_benchmark
 
 use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+use qrcode_ai_scanner_core::adaptive::adaptive_variants;
 use std::fs;
 use std::time::Instant;
 
@@ -51,11 +52,10 @@ fn apply_preprocessing(img: &DynamicImage, params: &PreprocessParams) -> Dynamic
 
     for (x, y, pixel) in rgb.enumerate_pixels() {
         let mut new_pixel = [0u8; 3];
-        for c in 0..3 {
-            let v = pixel.0[c] as f32;
-            let brightened = v * params.brightness;
+        for (out, &v) in new_pixel.iter_mut().zip(pixel.0.iter()) {
+            let brightened = v as f32 * params.brightness;
             let contrasted = ((brightened - 128.0) * params.contrast) + 128.0;
-            new_pixel[c] = contrasted.clamp(0.0, 255.0) as u8;
+            *out = contrasted.clamp(0.0, 255.0) as u8;
         }
         adjusted.put_pixel(x, y, image::Rgb(new_pixel));
     }
@@ -192,6 +192,47 @@ struct TestResult {
     duration_ms: u128,
 }
 
+/// Result of decoding one adaptive preprocessing variant
+#[derive(Debug)]
+struct AdaptiveResult {
+    method: &'static str,
+    success: bool,
+    duration_ms: u128,
+}
+
+/// Decode each adaptive variant (Sauvola, Niblack, local mean, CLAHE) of an image
+fn benchmark_adaptive(img: &DynamicImage) -> Vec<AdaptiveResult> {
+    adaptive_variants(img)
+        .into_iter()
+        .map(|(method, variant)| {
+            let start = Instant::now();
+            let success = try_decode(&variant).is_some();
+            AdaptiveResult {
+                method,
+                success,
+                duration_ms: start.elapsed().as_millis(),
+            }
+        })
+        .collect()
+}
+
+/// PNG files in a directory and its immediate subdirectories
+fn png_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let entries = |dir: &std::path::Path| -> Vec<std::path::PathBuf> {
+        fs::read_dir(dir)
+            .expect("Failed to read test-images directory")
+            .map(|entry| entry.expect("Failed to read entry").path())
+            .collect()
+    };
+    let mut files: Vec<_> = entries(dir)
+        .into_iter()
+        .flat_map(|path| if path.is_dir() { entries(&path) } else { vec![path] })
+        .filter(|path| path.extension().is_some_and(|e| e == "png"))
+        .collect();
+    files.sort();
+    files
+}
+
 /// Test all parameter combinations for an image
 fn benchmark_image(img: &DynamicImage, sizes: &[u32], contrasts: &[f32], brightnesses: &[f32], blurs: &[f32]) -> Vec<TestResult> {
     let mut results = Vec::new();
@@ -268,19 +309,16 @@ fn main() {
 
     println!("Looking for images in: {:?}\n", test_dir);
 
-    let entries = fs::read_dir(&test_dir).expect("Failed to read test-images directory");
-
     let mut image_results: Vec<(String, Vec<TestResult>)> = Vec::new();
+    let mut adaptive_results: Vec<AdaptiveResult> = Vec::new();
 
-    for entry in entries {
-        let entry = entry.expect("Failed to read entry");
-        let path = entry.path();
-
-        if !path.extension().map_or(false, |e| e == "png") {
-            continue;
-        }
-
+    for path in png_files(&test_dir) {
         let filename = path.file_name().unwrap().to_string_lossy();
+        let image_data = fs::read(&path).expect("Failed to read image");
+        let img = image::load_from_memory(&image_data).expect("Failed to load image");
+
+        // Adaptive preprocessing is cheap enough to run on every image
+        adaptive_results.extend(benchmark_adaptive(&img));
 
         // Check if this is one of our target images
         let is_target = target_ids.iter().any(|id| filename.contains(id));
@@ -290,9 +328,6 @@ fn main() {
 
         println!("Processing: {}", filename);
 
-        let image_data = fs::read(&path).expect("Failed to read image");
-        let img = image::load_from_memory(&image_data).expect("Failed to load image");
-
         let (width, height) = img.dimensions();
         println!("  Dimensions: {}x{}", width, height);
 
@@ -324,6 +359,24 @@ fn main() {
         println!();
     }
 
+    // Adaptive preprocessing success rates across every image
+    println!("\n=== ADAPTIVE PREPROCESSING ===\n");
+    println!("{:<12} {:>10} {:>10} {:>10}", "Method", "Success", "Rate", "Avg(ms)");
+    println!("{}", "-".repeat(46));
+    let mut methods: Vec<&str> = Vec::new();
+    for result in &adaptive_results {
+        if !methods.contains(&result.method) {
+            methods.push(result.method);
+        }
+    }
+    for method in methods {
+        let runs: Vec<_> = adaptive_results.iter().filter(|r| r.method == method).collect();
+        let success = runs.iter().filter(|r| r.success).count();
+        let avg_time = runs.iter().map(|r| r.duration_ms).sum::<u128>() / runs.len() as u128;
+        println!("{:<12} {:>6}/{:<3} {:>9.1}% {:>10}",
+            method, success, runs.len(), success as f64 / runs.len() as f64 * 100.0, avg_time);
+    }
+
     // Print summary table
     println!("\n=== OPTIMAL PARAMETERS SUMMARY ===\n");
     println!("{:<20} {:>10} {:>10} {:>10} {:>6} {:>6} {:>8}",
@@ -0,0 +1,261 @@
+//! Adaptive (local) binarisation and contrast
+//!
+//! Otsu and the fixed high-contrast threshold pick one level for the whole
+//! image, so artwork whose colours shift from region to region ends up with
+//! whole areas of the symbol on the wrong side of it. The methods here decide
+//! each pixel against its own neighbourhood: window means and deviations come
+//! from integral images in constant time per pixel, and CLAHE equalises each
+//! tile's histogram separately.
+
+use image::{DynamicImage, GrayImage, Luma};
+use rayon::prelude::*;
+
+/// Sauvola sensitivity to local deviation
+const SAUVOLA_K: f32 = 0.2;
+
+/// Dynamic range of the standard deviation for 8-bit images
+const SAUVOLA_R: f32 = 128.0;
+
+/// Niblack offset, in standard deviations below the local mean
+const NIBLACK_K: f32 = -0.2;
+
+/// Local mean threshold offset, in grey levels
+const LOCAL_MEAN_OFFSET: f32 = 7.0;
+
+/// CLAHE tile grid (tiles per side) and clip limit (multiple of the mean bin count)
+const CLAHE_GRID: u32 = 8;
+const CLAHE_CLIP: f32 = 2.0;
+
+/// Sums and squared sums over every top-left rectangle of an image
+pub(crate) struct Integral {
+    width: u32,
+    height: u32,
+    sum: Vec<u64>,
+    squares: Vec<u64>,
+}
+
+impl Integral {
+    pub(crate) fn new(gray: &GrayImage) -> Self {
+        let (width, height) = gray.dimensions();
+        let stride = width as usize + 1;
+        let mut sum = vec![0u64; stride * (height as usize + 1)];
+        let mut squares = sum.clone();
+        for y in 0..height as usize {
+            let (mut row, mut row_sq) = (0u64, 0u64);
+            for x in 0..width as usize {
+                let v = u64::from(gray.as_raw()[y * width as usize + x]);
+                row += v;
+                row_sq += v * v;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                squares[(y + 1) * stride + x + 1] = squares[y * stride + x + 1] + row_sq;
+            }
+        }
+        Self { width, height, sum, squares }
+    }
+
+    /// Mean and standard deviation of the window of `radius` around a pixel,
+    /// clipped to the image
+    pub(crate) fn stats(&self, x: u32, y: u32, radius: u32) -> (f32, f32) {
+        let stride = self.width as usize + 1;
+        let (x0, y0) = (x.saturating_sub(radius) as usize, y.saturating_sub(radius) as usize);
+        let (x1, y1) = ((x + radius + 1).min(self.width) as usize, (y + radius + 1).min(self.height) as usize);
+        let area = ((x1 - x0) * (y1 - y0)) as f64;
+        let rect = |table: &[u64]| {
+            (table[y1 * stride + x1] + table[y0 * stride + x0]) as f64
+                - (table[y0 * stride + x1] + table[y1 * stride + x0]) as f64
+        };
+        let mean = rect(&self.sum) / area;
+        let variance = (rect(&self.squares) / area - mean * mean).max(0.0);
+        (mean as f32, variance.sqrt() as f32)
+    }
+}
+
+/// Window radius for an image: a sixteenth of the shorter side, at least 7 px,
+/// so each window spans an eighth of it, several modules of a symbol filling the frame
+pub fn window_radius(width: u32, height: u32) -> u32 {
+    (width.min(height) / 16).max(7)
+}
+
+/// Binarise against a per-pixel threshold computed from window statistics
+fn threshold_by(gray: &GrayImage, radius: u32, threshold: impl Fn(f32, f32) -> f32) -> GrayImage {
+    let integral = Integral::new(gray);
+    GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        let (mean, deviation) = integral.stats(x, y, radius);
+        Luma([if f32::from(gray.get_pixel(x, y).0[0]) > threshold(mean, deviation) { 255 } else { 0 }])
+    })
+}
+
+/// Sauvola threshold: `mean * (1 + k * (deviation / R - 1))`
+///
+/// Flat areas fall below the local mean and turn light, so uneven
+/// backgrounds stay clean.
+pub fn sauvola(gray: &GrayImage, radius: u32) -> GrayImage {
+    threshold_by(gray, radius, |mean, deviation| mean * (1.0 + SAUVOLA_K * (deviation / SAUVOLA_R - 1.0)))
+}
+
+/// Niblack threshold: `mean + k * deviation`
+pub fn niblack(gray: &GrayImage, radius: u32) -> GrayImage {
+    threshold_by(gray, radius, |mean, deviation| mean + NIBLACK_K * deviation)
+}
+
+/// Local mean threshold: `mean - offset`
+pub fn local_mean(gray: &GrayImage, radius: u32) -> GrayImage {
+    threshold_by(gray, radius, |mean, _| mean - LOCAL_MEAN_OFFSET)
+}
+
+/// Contrast-limited adaptive histogram equalisation
+///
+/// Each tile of a `grid` x `grid` split gets its own equalisation curve,
+/// with histogram bins clipped at `clip` times the mean bin count so noise
+/// in flat tiles is not amplified. Pixels blend the curves of the four
+/// nearest tiles, so tile borders do not show.
+pub fn clahe(gray: &GrayImage, grid: u32, clip: f32) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return gray.clone();
+    }
+    let (tile_w, tile_h) = (width.div_ceil(grid).max(1), height.div_ceil(grid).max(1));
+    let (cols, rows) = (width.div_ceil(tile_w), height.div_ceil(tile_h));
+
+    let mut curves = vec![[0u8; 256]; (cols * rows) as usize];
+    for row in 0..rows {
+        for col in 0..cols {
+            let (x0, y0) = (col * tile_w, row * tile_h);
+            let (x1, y1) = ((x0 + tile_w).min(width), (y0 + tile_h).min(height));
+            let mut histogram = [0u32; 256];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    histogram[gray.get_pixel(x, y).0[0] as usize] += 1;
+                }
+            }
+            let pixels = (x1 - x0) * (y1 - y0);
+            curves[(row * cols + col) as usize] = clipped_curve(histogram, pixels, clip);
+        }
+    }
+
+    // Position between tile centres along one axis: (lower tile, upper tile, weight of upper)
+    let blend = |pos: u32, tile: u32, count: u32| {
+        let t = (pos as f32 + 0.5) / tile as f32 - 0.5;
+        let lower = t.floor().clamp(0.0, (count - 1) as f32);
+        (lower as u32, (lower as u32 + 1).min(count - 1), (t - lower).clamp(0.0, 1.0))
+    };
+    GrayImage::from_fn(width, height, |x, y| {
+        let v = gray.get_pixel(x, y).0[0] as usize;
+        let (c0, c1, ax) = blend(x, tile_w, cols);
+        let (r0, r1, ay) = blend(y, tile_h, rows);
+        let at = |c: u32, r: u32| f32::from(curves[(r * cols + c) as usize][v]);
+        let top = at(c0, r0) * (1.0 - ax) + at(c1, r0) * ax;
+        let bottom = at(c0, r1) * (1.0 - ax) + at(c1, r1) * ax;
+        Luma([(top * (1.0 - ay) + bottom * ay).round() as u8])
+    })
+}
+
+/// Equalisation curve of a tile histogram clipped at `clip` times the mean bin
+fn clipped_curve(mut histogram: [u32; 256], pixels: u32, clip: f32) -> [u8; 256] {
+    let limit = ((clip * pixels as f32 / 256.0) as u32).max(1);
+    let excess: u32 = histogram.iter().map(|&count| count.saturating_sub(limit)).sum();
+    // Clipped counts are spread evenly over every bin, the remainder at even steps
+    let residual = excess % 256;
+    let step = 256u32.checked_div(residual);
+    for (i, count) in histogram.iter_mut().enumerate() {
+        let i = i as u32;
+        let extra = step.is_some_and(|step| i.is_multiple_of(step) && i / step < residual);
+        *count = (*count).min(limit) + excess / 256 + u32::from(extra);
+    }
+
+    let mut curve = [0u8; 256];
+    let mut cumulative = 0u32;
+    for (value, &count) in curve.iter_mut().zip(histogram.iter()) {
+        cumulative += count;
+        *value = (u64::from(cumulative) * 255 / u64::from(pixels.max(1))) as u8;
+    }
+    curve
+}
+
+/// A named adaptive method taking a grayscale image and window radius
+type Method = (&'static str, fn(&GrayImage, u32) -> GrayImage);
+
+/// Methods in the decoder's preprocessing pool
+const METHODS: [Method; 4] = [
+    ("sauvola", sauvola),
+    ("niblack", niblack),
+    ("local_mean", local_mean),
+    ("clahe", |gray, _| clahe(gray, CLAHE_GRID, CLAHE_CLIP)),
+];
+
+/// Adaptive variants of an image for the decoder's preprocessing pool, built in parallel
+pub fn adaptive_variants(img: &DynamicImage) -> Vec<(&'static str, DynamicImage)> {
+    let gray = img.to_luma8();
+    let radius = window_radius(gray.width(), gray.height());
+    METHODS
+        .par_iter()
+        .map(|&(name, method)| (name, DynamicImage::ImageLuma8(method(&gray, radius))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{apply_otsu_threshold, try_decode_with_both, DecodeOptions};
+
+    /// QR whose left half is printed light-on-pale and right half dark-on-dim
+    fn unevenly_lit() -> GrayImage {
+        let code = qrcode::QrCode::new(b"https://example.com/mural").unwrap();
+        let symbol = code.render::<Luma<u8>>().module_dimensions(6, 6).build();
+        let half = symbol.width() / 2;
+        GrayImage::from_fn(symbol.width(), symbol.height(), |x, y| {
+            let dark = symbol.get_pixel(x, y).0[0] < 128;
+            Luma([match (x < half, dark) {
+                (true, true) => 140,
+                (true, false) => 250,
+                (false, true) => 10,
+                (false, false) => 120,
+            }])
+        })
+    }
+
+    #[test]
+    fn window_stats_come_from_the_integral_image() {
+        let gray = GrayImage::from_fn(4, 4, |x, _| Luma([if x < 2 { 0 } else { 100 }]));
+        let integral = Integral::new(&gray);
+        assert_eq!(integral.stats(1, 1, 1), (100.0 / 3.0, (2.0f32 / 9.0 * 10_000.0).sqrt()));
+        assert_eq!(integral.stats(3, 3, 0), (100.0, 0.0));
+        // Clipped at the corner: a 2 x 2 window of zeros
+        assert_eq!(integral.stats(0, 0, 1), (0.0, 0.0));
+    }
+
+    #[test]
+    fn local_thresholds_survive_regional_lighting() {
+        let lit = unevenly_lit();
+        let options = DecodeOptions::default();
+        let otsu = apply_otsu_threshold(&DynamicImage::ImageLuma8(lit.clone())).unwrap();
+        assert!(try_decode_with_both(&otsu, &options).is_err());
+
+        let radius = window_radius(lit.width(), lit.height());
+        for binary in [sauvola(&lit, radius), local_mean(&lit, radius)] {
+            let result = try_decode_with_both(&DynamicImage::ImageLuma8(binary), &options).unwrap();
+            assert_eq!(result.content, "https://example.com/mural");
+        }
+    }
+
+    #[test]
+    fn clahe_stretch_is_bounded_by_the_clip_limit() {
+        // 32 grey levels, equally common; a single tile isolates the curve
+        let ramp = GrayImage::from_fn(256, 256, |x, _| Luma([100 + (x / 8) as u8]));
+        let range = |img: &GrayImage| {
+            let (lo, hi) = img.pixels().fold((255, 0), |(lo, hi), p| (p.0[0].min(lo), p.0[0].max(hi)));
+            f32::from(hi - lo) / 31.0
+        };
+        // Unclipped it is plain equalisation over the full range
+        assert!(range(&clahe(&ramp, 1, 256.0)) > 7.5);
+        let stretch = range(&clahe(&ramp, 1, CLAHE_CLIP));
+        assert!((2.0..=CLAHE_CLIP + 1.0).contains(&stretch), "stretch {stretch}");
+    }
+
+    #[test]
+    fn variants_are_named() {
+        let img = DynamicImage::ImageLuma8(unevenly_lit());
+        let names: Vec<_> = adaptive_variants(&img).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["sauvola", "niblack", "local_mean", "clahe"]);
+    }
+}
@@ -0,0 +1,250 @@
+//! Structured Append reassembly
+//!
+//! Structured Append splits one message across up to 16 QR symbols. Each
+//! symbol starts with a header giving its position, the symbol count and a
+//! parity byte (the XOR of every byte of the complete message). This module
+//! gathers the parts from one or more images, puts them in order, and checks
+//! that none are missing and that the parity matches.
+
+use crate::decoder::read_all_encodings;
+use crate::error::{QraiError, Result};
+use crate::segments::{decode_text, Charset, PayloadEncoding, StructuredAppend};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// A message reassembled from its Structured Append parts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendedMessage {
+    /// Full message as text
+    pub content: String,
+    /// Full message bytes, parts concatenated in order
+    pub raw_bytes: Vec<u8>,
+    /// Number of symbols the message was split across
+    pub total: u8,
+    /// Parity byte from the headers, verified against `raw_bytes`
+    pub parity: u8,
+    /// ECI designator declared by the first part
+    pub eci: Option<u32>,
+    /// Declared (ECI) or detected character set of the message
+    pub charset: Charset,
+}
+
+/// Reassemble the Structured Append message spread over `images`
+///
+/// Symbols may be split across the images in any way and in any order.
+///
+/// # Errors
+/// * `QraiError::DecodeFailed` if no symbol carries a Structured Append header
+/// * `QraiError::MissingAppendParts` listing the indices that were not found
+/// * `QraiError::AppendParityMismatch` if the message fails its parity check
+/// * `QraiError::InconsistentAppendParts` if the parts disagree on the total
+///   or parity, or two symbols claim the same index with different data
+pub fn reassemble_images(images: &[DynamicImage]) -> Result<AppendedMessage> {
+    reassemble(images.iter().flat_map(read_all_encodings))
+}
+
+/// Reassemble a message from the bitstreams of its symbols
+///
+/// Symbols without a Structured Append header are ignored; the same part
+/// may appear more than once.
+pub fn reassemble(symbols: impl IntoIterator<Item = PayloadEncoding>) -> Result<AppendedMessage> {
+    let mut parts = symbols
+        .into_iter()
+        .filter_map(|encoding| encoding.structured_append.map(|header| (header, encoding)));
+    let (first, first_encoding) = parts.next().ok_or(QraiError::DecodeFailed)?;
+
+    let mut slots: Vec<Option<PayloadEncoding>> = vec![None; first.total as usize];
+    for (header, encoding) in std::iter::once((first, first_encoding)).chain(parts) {
+        check_header(&first, &header)?;
+        match &slots[header.index as usize] {
+            Some(existing) if existing.raw_bytes != encoding.raw_bytes => {
+                return Err(QraiError::InconsistentAppendParts(format!(
+                    "two different symbols claim index {}",
+                    header.index
+                )));
+            }
+            Some(_) => {}
+            None => slots[header.index as usize] = Some(encoding),
+        }
+    }
+
+    let missing: Vec<u8> = (0..first.total).filter(|&i| slots[i as usize].is_none()).collect();
+    if !missing.is_empty() {
+        return Err(QraiError::MissingAppendParts {
+            total: first.total,
+            missing,
+        });
+    }
+
+    let parts: Vec<PayloadEncoding> = slots.into_iter().flatten().collect();
+    let raw_bytes: Vec<u8> = parts.iter().flat_map(|p| p.raw_bytes.iter().copied()).collect();
+    let actual = raw_bytes.iter().fold(0, |parity, byte| parity ^ byte);
+    if actual != first.parity {
+        return Err(QraiError::AppendParityMismatch {
+            expected: first.parity,
+            actual,
+        });
+    }
+
+    let eci = parts[0].eci;
+    let charset = match eci {
+        Some(designator) => Charset::from_eci(designator),
+        None => {
+            let segments: Vec<_> = parts.iter().flat_map(|p| p.segments.iter().copied()).collect();
+            Charset::detect(&raw_bytes, &segments)
+        }
+    };
+
+    Ok(AppendedMessage {
+        content: decode_text(&raw_bytes, eci, charset),
+        raw_bytes,
+        total: first.total,
+        parity: first.parity,
+        eci,
+        charset,
+    })
+}
+
+/// Check that a part belongs to the same message as the first one found
+fn check_header(first: &StructuredAppend, header: &StructuredAppend) -> Result<()> {
+    if header.total != first.total || header.parity != first.parity {
+        return Err(QraiError::InconsistentAppendParts(format!(
+            "part {} of {} (parity {:#04x}) does not match part {} of {} (parity {:#04x})",
+            header.index, header.total, header.parity, first.index, first.total, first.parity
+        )));
+    }
+    if header.index >= header.total {
+        return Err(QraiError::InconsistentAppendParts(format!(
+            "index {} is out of range for {} parts",
+            header.index, header.total
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+    use rxing::common::BitArray;
+    use rxing::qrcode::decoder::{ErrorCorrectionLevel, Version};
+    use rxing::qrcode::encoder::{matrix_util, qrcode_encoder, ByteMatrix};
+
+    /// Render one Structured Append symbol (version 3-M) carrying `chunk` in byte mode
+    pub(crate) fn append_symbol(index: u8, total: u8, parity: u8, chunk: &[u8]) -> GrayImage {
+        let version = Version::getVersionForNumber(3).unwrap();
+        let ec_level = ErrorCorrectionLevel::M;
+        let ec_blocks = version.getECBlocksForLevel(ec_level);
+        let total_bytes = version.getTotalCodewords();
+        let data_bytes = total_bytes - ec_blocks.getTotalECCodewords();
+
+        let mut bits = BitArray::new();
+        bits.appendBits(0b0011, 4).unwrap();
+        bits.appendBits(((index << 4) | (total - 1)) as usize, 8).unwrap();
+        bits.appendBits(parity as usize, 8).unwrap();
+        bits.appendBits(0b0100, 4).unwrap();
+        bits.appendBits(chunk.len(), 8).unwrap();
+        for &byte in chunk {
+            bits.appendBits(byte as usize, 8).unwrap();
+        }
+        qrcode_encoder::terminateBits(data_bytes, &mut bits).unwrap();
+        let interleaved =
+            qrcode_encoder::interleaveWithECBytes(&bits, total_bytes, data_bytes, ec_blocks.getNumBlocks()).unwrap();
+
+        let size = version.getDimensionForVersion();
+        let mut matrix = ByteMatrix::new(size, size);
+        matrix_util::buildMatrix(&interleaved, &ec_level, version, 2, &mut matrix).unwrap();
+
+        let (scale, quiet) = (6, 4);
+        let side = (size + 2 * quiet) * scale;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (mx, my) = ((x / scale).checked_sub(quiet), (y / scale).checked_sub(quiet));
+            let dark = match (mx, my) {
+                (Some(mx), Some(my)) if mx < size && my < size => matrix.get(mx, my) == 1,
+                _ => false,
+            };
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    /// Split `message` into `total` symbols with the right parity
+    pub(crate) fn append_set(message: &[u8], total: u8) -> Vec<GrayImage> {
+        let parity = message.iter().fold(0, |p, b| p ^ b);
+        message
+            .chunks(message.len().div_ceil(total as usize))
+            .enumerate()
+            .map(|(i, chunk)| append_symbol(i as u8, total, parity, chunk))
+            .collect()
+    }
+
+    /// Place images side by side on one white canvas
+    fn side_by_side(images: &[GrayImage]) -> DynamicImage {
+        let width = images.iter().map(|i| i.width()).sum();
+        let height = images.iter().map(|i| i.height()).max().unwrap_or(0);
+        let mut canvas = GrayImage::from_pixel(width, height, Luma([255]));
+        let mut x = 0;
+        for img in images {
+            image::imageops::overlay(&mut canvas, img, x, 0);
+            x += img.width() as i64;
+        }
+        DynamicImage::ImageLuma8(canvas)
+    }
+
+    fn part(index: u8, total: u8, parity: u8, bytes: &[u8]) -> PayloadEncoding {
+        PayloadEncoding {
+            structured_append: Some(StructuredAppend { index, total, parity }),
+            ..PayloadEncoding::from_bytes(bytes.to_vec())
+        }
+    }
+
+    #[test]
+    fn reassembles_parts_in_any_order() {
+        let parity = b"hello world".iter().fold(0, |p, b| p ^ b);
+        let message = reassemble([
+            part(1, 3, parity, b"o w"),
+            part(2, 3, parity, b"orld"),
+            part(0, 3, parity, b"hell"),
+            part(1, 3, parity, b"o w"),
+        ])
+        .unwrap();
+
+        assert_eq!(message.content, "hello world");
+        assert_eq!((message.total, message.parity), (3, parity));
+        assert_eq!(message.charset, Charset::Ascii);
+    }
+
+    #[test]
+    fn reports_missing_parts() {
+        let err = reassemble([part(0, 4, 0, b"a"), part(2, 4, 0, b"c")]).unwrap_err();
+        assert!(matches!(err, QraiError::MissingAppendParts { total: 4, ref missing } if missing == &[1, 3]));
+    }
+
+    #[test]
+    fn rejects_bad_parity_and_mixed_sets() {
+        let err = reassemble([part(0, 2, 0x00, b"a"), part(1, 2, 0x00, b"b")]).unwrap_err();
+        assert!(matches!(err, QraiError::AppendParityMismatch { expected: 0x00, actual: 0x03 }));
+
+        let err = reassemble([part(0, 2, 0x03, b"a"), part(1, 3, 0x03, b"b")]).unwrap_err();
+        assert!(matches!(err, QraiError::InconsistentAppendParts(_)));
+
+        let err = reassemble([part(0, 2, 0x03, b"a"), part(0, 2, 0x03, b"z")]).unwrap_err();
+        assert!(matches!(err, QraiError::InconsistentAppendParts(_)));
+
+        assert!(matches!(reassemble([PayloadEncoding::from_bytes(b"x".to_vec())]), Err(QraiError::DecodeFailed)));
+    }
+
+    #[test]
+    fn reads_parts_from_one_or_several_images() {
+        let message = b"https://example.com/catalogue/2026/spring?sku=4711";
+        let symbols = append_set(message, 3);
+
+        let separate: Vec<DynamicImage> = symbols.iter().cloned().map(DynamicImage::ImageLuma8).collect();
+        assert_eq!(reassemble_images(&separate).unwrap().raw_bytes, message);
+
+        let together = side_by_side(&symbols);
+        assert_eq!(reassemble_images(&[together]).unwrap().raw_bytes, message);
+
+        let err = reassemble_images(&separate[..2]).unwrap_err();
+        assert!(matches!(err, QraiError::MissingAppendParts { total: 3, ref missing } if missing == &[2]));
+    }
+}
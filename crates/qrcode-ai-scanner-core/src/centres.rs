@@ -0,0 +1,327 @@
+//! Module-centre sampling for artistic symbols
+//!
+//! ControlNet-style generators paint a scene over the symbol that is only
+//! right at module centres; everything between them is arbitrary texture, so
+//! binarising the whole image reads the texture instead of the modules. This
+//! decoder locates the grid from the finder and alignment patterns that rqrr
+//! or rxing detect, votes on the pixels of a small window at each module
+//! centre against a threshold taken from the neighbouring modules, and hands
+//! the module matrix straight to Reed-Solomon correction. Stylised format
+//! information is no obstacle: when no sampling corrects as read, every EC
+//! level and mask is tried in its place.
+
+use crate::adaptive::{sauvola, window_radius};
+use crate::decoder::DecodeOptions;
+use crate::error::{QraiError, Result};
+use crate::fidelity::{correct_symbol, recover_format};
+use crate::grid::ModuleGrid;
+use crate::locate::{locate_in_luma, locate_with_rxing, otsu_level, SymbolLocation};
+use crate::micro::parse_ec_level;
+use crate::morphology::binarize;
+use crate::segments::parse_segments;
+use crate::types::{DecodeStage, DecodeTrace, MultiDecodeResult, QrMetadata, Symbology};
+use image::{DynamicImage, GrayImage};
+use rayon::prelude::*;
+
+/// Name reported in `decoders_success`
+pub const DECODER: &str = "module_centres";
+
+/// Centre window radii tried, as fractions of the module pitch
+const WINDOW_SCALES: [f32; 3] = [0.1, 0.2, 0.3];
+
+/// Threshold neighbourhoods tried, in modules either side; `None` is one
+/// level for the whole symbol
+const NEIGHBOURHOODS: [Option<usize>; 3] = [Some(3), Some(6), None];
+
+/// Neighbourhoods whose centre means spread less than this share of the
+/// symbol's spread hold only one colour and use the symbol-wide level
+const MIN_LOCAL_SPREAD: f32 = 0.25;
+
+/// How the modules of a located symbol are sampled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CentreSampling {
+    /// Centre window radius, as a fraction of the module pitch
+    pub window: f32,
+    /// Threshold neighbourhood in modules either side, or the whole symbol
+    pub neighbourhood: Option<usize>,
+}
+
+/// Every sampling tried, narrow windows and local thresholds first
+pub fn samplings() -> Vec<CentreSampling> {
+    WINDOW_SCALES
+        .iter()
+        .flat_map(|&window| NEIGHBOURHOODS.iter().map(move |&neighbourhood| CentreSampling { window, neighbourhood }))
+        .collect()
+}
+
+/// Grids located in the image and in its Otsu and Sauvola binarisations,
+/// by rqrr and by rxing, without duplicates
+pub fn candidate_locations(gray: &GrayImage) -> Vec<SymbolLocation> {
+    let local = sauvola(gray, window_radius(gray.width(), gray.height()));
+    let found = [
+        locate_in_luma(gray),
+        locate_with_rxing(gray),
+        locate_in_luma(&binarize(gray)),
+        locate_in_luma(&local),
+        locate_with_rxing(&local),
+    ];
+    let mut locations: Vec<SymbolLocation> = Vec::new();
+    for location in found.into_iter().flatten() {
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
+    }
+    locations
+}
+
+/// Module darkness by majority vote over each centre window, row-major
+///
+/// Every pixel in a module's window votes against the Otsu level of the
+/// window means in the surrounding neighbourhood; the module is dark when
+/// most of its pixels are.
+pub fn sample_centres(gray: &GrayImage, location: &SymbolLocation, sampling: CentreSampling) -> Vec<bool> {
+    let size = location.size;
+    let radius = (location.module_pitch() * sampling.window).round().max(0.0) as i64;
+    let (width, height) = (gray.width() as i64, gray.height() as i64);
+
+    let windows: Vec<Vec<u8>> = (0..size * size)
+        .map(|i| {
+            let (cx, cy) = location.module_center(i % size, i / size);
+            let (cx, cy) = (cx.round() as i64, cy.round() as i64);
+            let mut pixels = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+            for y in (cy - radius).max(0)..=(cy + radius).min(height - 1) {
+                for x in (cx - radius).max(0)..=(cx + radius).min(width - 1) {
+                    pixels.push(gray.get_pixel(x as u32, y as u32).0[0]);
+                }
+            }
+            pixels
+        })
+        .collect();
+    let means: Vec<f32> = windows
+        .iter()
+        .map(|w| if w.is_empty() { 255.0 } else { w.iter().map(|&v| f32::from(v)).sum::<f32>() / w.len() as f32 })
+        .collect();
+
+    let spread = |values: &[f32]| {
+        let (lo, hi) = values.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        hi - lo
+    };
+    let global = otsu_level(&means);
+    let global_spread = spread(&means);
+    let level = |x: usize, y: usize| {
+        let Some(reach) = sampling.neighbourhood else {
+            return global;
+        };
+        let near: Vec<f32> = (y.saturating_sub(reach)..(y + reach + 1).min(size))
+            .flat_map(|ny| (x.saturating_sub(reach)..(x + reach + 1).min(size)).map(move |nx| ny * size + nx))
+            .map(|i| means[i])
+            .collect();
+        if spread(&near) < global_spread * MIN_LOCAL_SPREAD {
+            global
+        } else {
+            otsu_level(&near)
+        }
+    };
+
+    (0..size * size)
+        .map(|i| {
+            let level = level(i % size, i / size);
+            let dark = windows[i].iter().filter(|&&v| f32::from(v) < level).count();
+            dark * 2 > windows[i].len()
+        })
+        .collect()
+}
+
+/// Decode a located symbol from one sampling of its module centres,
+/// optionally ignoring the format information it carries
+fn read_at(
+    gray: &GrayImage,
+    location: &SymbolLocation,
+    sampling: CentreSampling,
+    format_recovered: bool,
+) -> Option<MultiDecodeResult> {
+    let dark = sample_centres(gray, location, sampling);
+    let symbol = if format_recovered {
+        recover_format(&dark, location.size)?
+    } else {
+        correct_symbol(&dark, location.size)?
+    };
+    let version = symbol.version.getVersionNumber() as u8;
+    let encoding = parse_segments(&symbol.data_codewords(), version)?;
+    let usage = symbol.ec_usage();
+
+    Some(MultiDecodeResult {
+        content: encoding.text(),
+        raw_bytes: Some(encoding.raw_bytes.clone()),
+        metadata: Some(QrMetadata {
+            symbology: Symbology::Qr,
+            version,
+            error_correction: parse_ec_level(&symbol.ec_level.to_string()).unwrap_or_default(),
+            modules: location.size as u8,
+            decoders_success: vec![DECODER.to_string()],
+            ec_used_ratio: Some(symbol.worst_block_usage()),
+            ec_usage: Some(usage),
+            eci: encoding.eci,
+            charset: Some(encoding.charset),
+            structured_append: encoding.structured_append,
+            fnc1: encoding.fnc1,
+            trace: Some(DecodeTrace {
+                stage: DecodeStage::ModuleCentres,
+                format_recovered,
+                ..Default::default()
+            }),
+            segments: encoding.segments,
+            inverted: false,
+            mirrored: false,
+        }),
+        decoders_success: vec![DECODER.to_string()],
+        grid: Some(ModuleGrid::from_dark(&dark, location.size)),
+    })
+}
+
+/// Decode an image by sampling module centres of every located grid
+///
+/// Each candidate location is tried with every sampling in parallel; the
+/// first matrix Reed-Solomon can correct wins. If none does, the same
+/// matrices are tried again with the format information recovered.
+///
+/// # Errors
+/// `QraiError::DecodeFailed` if standard QR is not among `options.formats`,
+/// no grid is located, or no sampling corrects.
+pub(crate) fn decode_module_centres(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
+    if !options.accepts(Symbology::Qr) {
+        return Err(QraiError::DecodeFailed);
+    }
+    let gray = img.to_luma8();
+    let samplings = samplings();
+    let attempts: Vec<_> = candidate_locations(&gray)
+        .iter()
+        .flat_map(|&location| samplings.iter().map(move |&sampling| (location, sampling)))
+        .collect();
+    [false, true]
+        .into_iter()
+        .find_map(|format_recovered| {
+            attempts
+                .par_iter()
+                .find_map_first(|(location, sampling)| read_at(&gray, location, *sampling, format_recovered))
+        })
+        .ok_or(QraiError::DecodeFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{multi_decode_image, try_decode_with_both};
+    use image::Luma;
+
+    /// Symbol whose module centres are right but noisy, with random texture
+    /// everywhere else; finder patterns are left solid
+    fn painted(text: &str, pitch: u32) -> GrayImage {
+        let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+        let modules = code.width() as u32;
+        let colors = code.to_colors();
+        let corner = |m: u32| m < 8 || m >= modules - 8;
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut noise = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 100) as u32
+        };
+        let side = (modules + 8) * pitch;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (mx, my) = ((x / pitch).wrapping_sub(4), (y / pitch).wrapping_sub(4));
+            if mx >= modules || my >= modules {
+                return Luma([255]);
+            }
+            let dark = colors[(my * modules + mx) as usize] == qrcode::Color::Dark;
+            let colour = |dark: bool| Luma([if dark { 20 } else { 235 }]);
+            if corner(mx) && corner(my) && (mx < 8 || my < 8) {
+                return colour(dark);
+            }
+            let (fx, fy) = (x % pitch, y % pitch);
+            let centre = |f: u32| (pitch * 3 / 10..=pitch * 7 / 10).contains(&f);
+            match (centre(fx) && centre(fy), noise()) {
+                // A quarter of the centre pixels are flipped
+                (true, n) => colour(dark ^ (n < 25)),
+                (false, n) => colour(n < 50),
+            }
+        })
+    }
+
+    #[test]
+    fn majority_votes_recover_noisy_centres() {
+        let gray = painted("https://example.com/painted", 10);
+        let code = qrcode::QrCode::new(b"https://example.com/painted").unwrap();
+        let location = candidate_locations(&gray).into_iter().find(|l| l.size == code.width()).unwrap();
+        let truth: Vec<bool> = code.to_colors().iter().map(|&c| c == qrcode::Color::Dark).collect();
+
+        let sampling = CentreSampling { window: 0.2, neighbourhood: Some(3) };
+        let read = sample_centres(&gray, &location, sampling);
+        let wrong = read.iter().zip(&truth).filter(|(a, b)| a != b).count();
+        assert!(wrong * 50 < truth.len(), "{wrong} of {} modules wrong", truth.len());
+    }
+
+    #[test]
+    fn decodes_what_whole_image_binarisation_cannot() {
+        let img = DynamicImage::ImageLuma8(painted("https://example.com/painted", 10));
+        let options = DecodeOptions::default();
+        assert!(try_decode_with_both(&img, &options).is_err());
+
+        let result = decode_module_centres(&img, &options).unwrap();
+        assert_eq!(result.content, "https://example.com/painted");
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta.decoders_success, [DECODER]);
+        assert!(meta.ec_usage.is_some());
+
+        let staged = multi_decode_image(&img).unwrap();
+        assert_eq!(staged.metadata.unwrap().trace.unwrap().stage, DecodeStage::ModuleCentres);
+    }
+
+    #[test]
+    fn stylised_format_information_is_recovered() {
+        let code = qrcode::QrCode::new(b"https://example.com/format").unwrap();
+        let size = code.width() as u32;
+        let mut gray = code.render::<Luma<u8>>().module_dimensions(6, 6).build();
+        // An ornament painted over both format information copies
+        let mut paint = |mx: u32, my: u32| {
+            let (x0, y0) = (24 + mx * 6, 24 + my * 6);
+            for y in y0..y0 + 6 {
+                for x in x0..x0 + 6 {
+                    gray.put_pixel(x, y, Luma([if (mx + my).is_multiple_of(3) { 255 } else { 0 }]));
+                }
+            }
+        };
+        for i in (0..9).filter(|&i| i != 6).chain(size - 8..size) {
+            paint(i, 8);
+            paint(8, i);
+        }
+        let img = DynamicImage::ImageLuma8(gray);
+        assert!(try_decode_with_both(&img, &DecodeOptions::default()).is_err());
+
+        let result = multi_decode_image(&img).unwrap();
+        assert_eq!(result.content, "https://example.com/format");
+        let trace = result.metadata.unwrap().trace.unwrap();
+        assert_eq!(trace.stage, DecodeStage::ModuleCentres);
+        assert!(trace.format_recovered);
+    }
+
+    #[test]
+    fn local_levels_follow_regional_lighting() {
+        // Lighting adds up to 195 grey levels from left to right, so dark
+        // modules on the right are lighter than light ones on the left
+        let code = qrcode::QrCode::new(b"https://example.com/lit").unwrap();
+        let symbol = code.render::<Luma<u8>>().module_dimensions(6, 6).build();
+        let width = symbol.width();
+        let lit = GrayImage::from_fn(width, symbol.height(), |x, y| {
+            let base = if symbol.get_pixel(x, y).0[0] < 128 { 0 } else { 60 };
+            Luma([base + (195 * x / width) as u8])
+        });
+        // Too uneven for the detectors; the grid is where the unlit symbol's is
+        let location = candidate_locations(&symbol).into_iter().next().unwrap();
+        let global = CentreSampling { window: 0.2, neighbourhood: None };
+        let local = CentreSampling { window: 0.2, neighbourhood: Some(3) };
+        assert!(correct_symbol(&sample_centres(&lit, &location, global), location.size).is_none());
+        assert!(correct_symbol(&sample_centres(&lit, &location, local), location.size).is_some());
+    }
+}
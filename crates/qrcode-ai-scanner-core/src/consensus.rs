@@ -0,0 +1,158 @@
+//! Cross-decoder consensus
+//!
+//! The normal decode path stops at the first decoder and preprocessing
+//! variant that succeeds, so it never notices when rxing and rqrr disagree.
+//! Consensus mode runs every decoder over a fixed set of variants and keeps
+//! every distinct content read. More than one content means the image reads
+//! differently depending on the scanner — a red flag for user-uploaded codes.
+
+use crate::decoder::{consensus_variants, decode_all_with_both, multi_decode_image};
+use crate::error::Result;
+use image::DynamicImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Variant name reported when only the tiered decoder could read the code
+pub const TIERED_VARIANT: &str = "tiered";
+
+/// One distinct content read from the image
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentReading {
+    /// Decoded content
+    pub content: String,
+    /// Decoders that read it
+    pub decoders: Vec<String>,
+    /// Preprocessing variants it was read from
+    pub variants: Vec<String>,
+    /// Number of (decoder, variant) reads that produced it
+    pub reads: u32,
+}
+
+/// Every content read by every decoder across preprocessing variants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consensus {
+    /// Distinct contents, most-read first
+    pub readings: Vec<ContentReading>,
+    /// More than one distinct content was read
+    pub ambiguous: bool,
+    /// Variants decoded (each by every decoder)
+    pub variants_tried: u32,
+}
+
+impl Consensus {
+    /// The content every decoder agreed on, or `None` if ambiguous
+    pub fn agreed_content(&self) -> Option<&str> {
+        match self.readings.as_slice() {
+            [only] => Some(&only.content),
+            _ => None,
+        }
+    }
+}
+
+/// Run every decoder over every consensus variant and collect what they read
+///
+/// Falls back to the tiered decoder when no variant decodes.
+///
+/// # Errors
+/// * `QraiError::DecodeFailed` if nothing decodes at all
+pub fn consensus_decode_image(img: &DynamicImage) -> Result<Consensus> {
+    let variants = consensus_variants(img);
+    let reads: Vec<(&str, &str, String)> = variants
+        .par_iter()
+        .flat_map_iter(|(name, variant)| {
+            decode_all_with_both(variant)
+                .into_iter()
+                .map(move |(decoder, content)| (*name, decoder, content))
+        })
+        .collect();
+
+    let mut readings: Vec<ContentReading> = Vec::new();
+    for (variant, decoder, content) in reads {
+        let index = match readings.iter().position(|r| r.content == content) {
+            Some(index) => index,
+            None => {
+                readings.push(ContentReading {
+                    content,
+                    decoders: Vec::new(),
+                    variants: Vec::new(),
+                    reads: 0,
+                });
+                readings.len() - 1
+            }
+        };
+        let reading = &mut readings[index];
+        reading.reads += 1;
+        if !reading.decoders.iter().any(|d| d == decoder) {
+            reading.decoders.push(decoder.to_string());
+        }
+        if !reading.variants.iter().any(|v| v == variant) {
+            reading.variants.push(variant.to_string());
+        }
+    }
+    // Heavily stylized codes may only decode in the deeper tiers; report
+    // that single reading rather than failing outright
+    if readings.is_empty() {
+        let result = multi_decode_image(img)?;
+        readings.push(ContentReading {
+            content: result.content,
+            reads: result.decoders_success.len() as u32,
+            decoders: result.decoders_success,
+            variants: vec![TIERED_VARIANT.to_string()],
+        });
+    }
+
+    for reading in &mut readings {
+        reading.decoders.sort();
+    }
+    readings.sort_by(|a, b| b.reads.cmp(&a.reads).then_with(|| a.content.cmp(&b.content)));
+
+    Ok(Consensus {
+        ambiguous: readings.len() > 1,
+        readings,
+        variants_tried: variants.len() as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::QraiError;
+    use image::{GrayImage, Luma};
+
+    fn render(content: &str) -> GrayImage {
+        qrcode::QrCode::new(content.as_bytes()).unwrap().render::<Luma<u8>>().build()
+    }
+
+    #[test]
+    fn clean_code_is_unanimous() {
+        let img = DynamicImage::ImageLuma8(render("https://example.com"));
+        let consensus = consensus_decode_image(&img).unwrap();
+
+        assert!(!consensus.ambiguous);
+        assert_eq!(consensus.agreed_content(), Some("https://example.com"));
+        assert_eq!(consensus.readings[0].decoders, ["rqrr", "rxing"]);
+        assert!(consensus.readings[0].variants.iter().any(|v| v == "original"));
+    }
+
+    #[test]
+    fn two_different_codes_are_ambiguous() {
+        let (left, right) = (render("https://example.com"), render("https://evil.example"));
+        let mut canvas = GrayImage::from_pixel(left.width() + right.width(), left.height().max(right.height()), Luma([255]));
+        image::imageops::replace(&mut canvas, &left, 0, 0);
+        image::imageops::replace(&mut canvas, &right, left.width() as i64, 0);
+
+        let consensus = consensus_decode_image(&DynamicImage::ImageLuma8(canvas)).unwrap();
+
+        assert!(consensus.ambiguous);
+        assert_eq!(consensus.agreed_content(), None);
+        let contents: Vec<&str> = consensus.readings.iter().map(|r| r.content.as_str()).collect();
+        assert!(contents.contains(&"https://example.com"), "{contents:?}");
+        assert!(contents.contains(&"https://evil.example"), "{contents:?}");
+    }
+
+    #[test]
+    fn blank_image_fails() {
+        let blank = DynamicImage::new_luma8(100, 100);
+        assert!(matches!(consensus_decode_image(&blank), Err(QraiError::DecodeFailed)));
+    }
+}
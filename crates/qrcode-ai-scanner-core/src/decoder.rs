@@ -1,6 +1,21 @@
+use crate::adaptive;
+use crate::centres;
 use crate::error::{QraiError, Result};
-use crate::types::{ErrorCorrectionLevel, MultiDecodeResult, QrMetadata};
-use image::{DynamicImage, GenericImageView, GrayImage, RgbImage};
+use crate::grid::ModuleGrid;
+use crate::fidelity::{correct_symbol, read_symbol, CorrectedSymbol};
+use crate::limits::ResourceLimits;
+use crate::locate::{catch_rqrr_panic, locate_in_luma, locate_with_rxing};
+use crate::micro;
+use crate::morphology;
+use crate::pyramid;
+use crate::roi;
+use crate::upscale;
+use crate::segments::{parse_segments, Fnc1Mode, PayloadEncoding};
+use crate::types::{
+    DecodeStage, DecodeTrace, EcUsage, ErrorCorrectionLevel, MultiDecodeResult, PixelBuffer, PixelFormat, QrMetadata, Region,
+    RegionOfInterest, RoiSource, Symbology,
+};
+use image::{DynamicImage, GenericImageView, GrayImage, RgbImage, RgbaImage};
 use rayon::prelude::*;
 
 // ============================================================================
@@ -8,15 +23,11 @@ use rayon::prelude::*;
 // These replace .unwrap() calls with proper error handling
 // ============================================================================
 
-/// Maximum allowed image dimension (65536 x 65536 = 4GB max memory)
-const MAX_DIMENSION: u32 = 65536;
+// Image size limits are enforced once at load time by `ResourceLimits`;
+// these helpers only guard against arithmetic overflow and length mismatches.
 
-/// Safely create a GrayImage from raw data with dimension validation
+/// Safely create a GrayImage from raw data with length validation
 fn safe_gray_from_raw(width: u32, height: u32, data: Vec<u8>) -> Option<GrayImage> {
-    // Validate dimensions to prevent overflow
-    if width > MAX_DIMENSION || height > MAX_DIMENSION {
-        return None;
-    }
     let expected = (width as usize).checked_mul(height as usize)?;
     if data.len() != expected {
         return None;
@@ -24,12 +35,8 @@ fn safe_gray_from_raw(width: u32, height: u32, data: Vec<u8>) -> Option<GrayImag
     GrayImage::from_raw(width, height, data)
 }
 
-/// Safely create an RgbImage from raw data with dimension validation
+/// Safely create an RgbImage from raw data with length validation
 fn safe_rgb_from_raw(width: u32, height: u32, data: Vec<u8>) -> Option<RgbImage> {
-    // Validate dimensions to prevent overflow
-    if width > MAX_DIMENSION || height > MAX_DIMENSION {
-        return None;
-    }
     let expected = (width as usize).checked_mul(height as usize)?.checked_mul(3)?;
     if data.len() != expected {
         return None;
@@ -37,11 +44,60 @@ fn safe_rgb_from_raw(width: u32, height: u32, data: Vec<u8>) -> Option<RgbImage>
     RgbImage::from_raw(width, height, data)
 }
 
-/// Safe pixel count calculation with overflow protection
-fn safe_pixel_count(width: u32, height: u32) -> Option<usize> {
-    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+/// Safely create an RgbaImage from raw data with length validation
+fn safe_rgba_from_raw(width: u32, height: u32, data: Vec<u8>) -> Option<RgbaImage> {
+    let expected = (width as usize).checked_mul(height as usize)?.checked_mul(4)?;
+    if data.len() != expected {
         return None;
     }
+    RgbaImage::from_raw(width, height, data)
+}
+
+/// Copy a raw pixel buffer into an image, dropping row padding
+///
+/// # Errors
+/// * `QraiError::ImageLoad` if the image is empty
+/// * `QraiError::InvalidStride` if rows are shorter than the image width
+/// * `QraiError::BufferMismatch` if the buffer ends before the last row
+/// * `QraiError::DimensionOverflow` if the size overflows
+pub fn image_from_pixels(pixels: &PixelBuffer) -> Result<DynamicImage> {
+    let PixelBuffer { data, width, height, stride, format } = *pixels;
+    if width == 0 || height == 0 {
+        return Err(QraiError::ImageLoad(format!("empty {width}x{height} pixel buffer")));
+    }
+    let overflow = || QraiError::DimensionOverflow { width, height };
+    let row_bytes = (width as usize).checked_mul(format.bytes_per_pixel()).ok_or_else(overflow)?;
+    if stride < row_bytes {
+        return Err(QraiError::InvalidStride { stride, row_bytes });
+    }
+    let expected = stride
+        .checked_mul(height as usize - 1)
+        .and_then(|n| n.checked_add(row_bytes))
+        .ok_or_else(overflow)?;
+    if data.len() < expected {
+        return Err(QraiError::BufferMismatch { expected, actual: data.len() });
+    }
+
+    let mut packed = Vec::with_capacity(row_bytes * height as usize);
+    for row in data.chunks(stride).take(height as usize) {
+        packed.extend_from_slice(&row[..row_bytes]);
+    }
+    if format == PixelFormat::Bgra8 {
+        packed.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+    }
+
+    let image = match format {
+        PixelFormat::Luma8 => safe_gray_from_raw(width, height, packed).map(DynamicImage::ImageLuma8),
+        PixelFormat::Rgb8 => safe_rgb_from_raw(width, height, packed).map(DynamicImage::ImageRgb8),
+        PixelFormat::Rgba8 | PixelFormat::Bgra8 => {
+            safe_rgba_from_raw(width, height, packed).map(DynamicImage::ImageRgba8)
+        }
+    };
+    image.ok_or_else(overflow)
+}
+
+/// Safe pixel count calculation with overflow protection
+fn safe_pixel_count(width: u32, height: u32) -> Option<usize> {
     (width as usize).checked_mul(height as usize)
 }
 
@@ -55,24 +111,93 @@ struct PreprocessParams {
     grayscale: bool,   // Convert to grayscale first
 }
 
+/// Options for the decode and validate entry points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Symbologies to look for. Defaults to the QR family; add Data Matrix,
+    /// Aztec or PDF417 to opt in to them.
+    pub formats: Vec<Symbology>,
+    /// Size caps checked before an image is decoded
+    pub limits: ResourceLimits,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            formats: Symbology::QR_FAMILY.to_vec(),
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Options accepting exactly the given symbologies
+    pub fn with_formats(formats: &[Symbology]) -> Self {
+        Self {
+            formats: formats.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether `symbology` is one of the requested formats
+    pub fn accepts(&self, symbology: Symbology) -> bool {
+        self.formats.contains(&symbology)
+    }
+
+    /// rxing hints restricting its readers to the requested formats
+    pub(crate) fn rxing_hints(&self) -> rxing::DecodeHints {
+        let formats = self.formats.iter().map(|&s| rxing_format(s)).collect();
+        rxing::DecodeHints::default().with(rxing::DecodeHintValue::PossibleFormats(formats))
+    }
+}
+
 /// Decode result from a single decoder
 #[derive(Debug, Clone)]
 pub struct SingleDecodeResult {
+    pub symbology: Symbology,
     pub content: String,
     pub version: Option<u8>,
     pub error_correction: Option<ErrorCorrectionLevel>,
+    /// Raw payload bytes and segment breakdown, when the bitstream could be read
+    pub encoding: Option<PayloadEncoding>,
+    /// The symbol was only read from a mirrored copy of the image
+    pub mirrored: bool,
+    /// Share of the EC budget consumed by the worst block, when the decoded
+    /// codewords could be re-read
+    pub ec_used_ratio: Option<f32>,
+    /// Codewords and blocks Reed-Solomon had to correct in that read
+    pub ec_usage: Option<EcUsage>,
+    /// Module grid of that read
+    pub grid: Option<ModuleGrid>,
+}
+
+/// A decoded symbol re-read from its sampled modules
+struct Reread {
+    encoding: PayloadEncoding,
+    symbol: CorrectedSymbol,
+    grid: ModuleGrid,
 }
 
 /// Decode QR code using rxing (ZXing port) - most robust decoder
 pub fn decode_with_rxing(img: &DynamicImage) -> Result<SingleDecodeResult> {
     let luma = img.to_luma8();
     let (width, height) = luma.dimensions();
-    decode_with_rxing_raw(&luma.into_raw(), width, height)
+    decode_with_rxing_raw(&luma.into_raw(), width, height, &DecodeOptions::default())
 }
 
 /// Internal rxing decoder using pre-converted luma data
-fn decode_with_rxing_raw(luma_data: &[u8], width: u32, height: u32) -> Result<SingleDecodeResult> {
-    let results = rxing::helpers::detect_multiple_in_luma(luma_data.to_vec(), width, height);
+fn decode_with_rxing_raw(
+    luma_data: &[u8],
+    width: u32,
+    height: u32,
+    options: &DecodeOptions,
+) -> Result<SingleDecodeResult> {
+    let results = rxing::helpers::detect_multiple_in_luma_with_hints(
+        luma_data.to_vec(),
+        width,
+        height,
+        &mut options.rxing_hints(),
+    );
 
     // Debug output
     if std::env::var("QRAI_DEBUG").is_ok() {
@@ -84,15 +209,51 @@ fn decode_with_rxing_raw(luma_data: &[u8], width: u32, height: u32) -> Result<Si
 
     let results = results.map_err(|_| QraiError::DecodeFailed)?;
 
-    let first = results.first().ok_or(QraiError::DecodeFailed)?;
-
-    let version = extract_version_from_rxing(first);
+    let (first, symbology) = results
+        .iter()
+        .find_map(|r| symbology_of(r.getBarcodeFormat()).filter(|&s| options.accepts(s)).map(|s| (r, s)))
+        .ok_or(QraiError::DecodeFailed)?;
     let error_correction = extract_ec_from_rxing(first);
 
+    if symbology != Symbology::Qr {
+        // rxing doesn't report the size; measure it natively when the symbol is pure
+        let version = GrayImage::from_raw(width, height, luma_data.to_vec())
+            .filter(|_| symbology.is_micro())
+            .and_then(|gray| micro::read_pure(&gray))
+            .filter(|read| read.symbology == symbology && read.content == first.getText())
+            .map(|read| read.version);
+        return Ok(SingleDecodeResult {
+            symbology,
+            content: first.getText().to_string(),
+            version,
+            error_correction,
+            encoding: Some(PayloadEncoding {
+                fnc1: extract_fnc1_from_rxing(first),
+                ..PayloadEncoding::from_bytes(first.getRawBytes().to_vec())
+            }),
+            mirrored: false,
+            ec_used_ratio: None,
+            ec_usage: None,
+            grid: None,
+        });
+    }
+
+    let reread = reread_rxing(first, luma_data, width, height);
+    let mut encoding = reread.as_ref().map_or_else(
+        || PayloadEncoding::from_bytes(first.getRawBytes().to_vec()),
+        |r| r.encoding.clone(),
+    );
+    encoding.fnc1 = encoding.fnc1.or_else(|| extract_fnc1_from_rxing(first));
     Ok(SingleDecodeResult {
+        symbology,
         content: first.getText().to_string(),
-        version,
+        version: extract_version_from_rxing(first),
         error_correction,
+        encoding: Some(encoding),
+        mirrored: false,
+        ec_used_ratio: reread.as_ref().map(|r| r.symbol.worst_block_usage()),
+        ec_usage: reread.as_ref().map(|r| r.symbol.ec_usage()),
+        grid: reread.map(|r| r.grid),
     })
 }
 
@@ -109,64 +270,156 @@ fn decode_with_rqrr_raw(luma_data: &[u8], width: u32, height: u32) -> Result<Sin
     let luma = GrayImage::from_raw(width, height, luma_data.to_vec())
         .ok_or(QraiError::DecodeFailed)?;
 
-    let mut prepared = rqrr::PreparedImage::prepare(luma);
-    let grids = prepared.detect_grids();
+    let (meta, content, reread) = catch_rqrr_panic(|| {
+        let mut prepared = rqrr::PreparedImage::prepare(luma);
+        let grids = prepared.detect_grids();
 
-    // Debug: show how many grids were found
-    if std::env::var("QRAI_DEBUG").is_ok() {
-        eprintln!("[DEBUG] rqrr found {} grids", grids.len());
-    }
+        // Debug: show how many grids were found
+        if std::env::var("QRAI_DEBUG").is_ok() {
+            eprintln!("[DEBUG] rqrr found {} grids", grids.len());
+        }
+
+        let grid = grids.first().ok_or(QraiError::DecodeFailed)?;
+        let (meta, content) = grid.decode().map_err(|_| QraiError::DecodeFailed)?;
 
-    let grid = grids.first().ok_or(QraiError::DecodeFailed)?;
-    let (meta, content) = grid.decode().map_err(|_| QraiError::DecodeFailed)?;
+        Ok::<_, QraiError>((meta, content, reread_grid(&grid.grid)))
+    })
+    .ok_or(QraiError::DecodeFailed)??;
+    let encoding = reread.as_ref().map_or_else(
+        || PayloadEncoding::from_bytes(content.clone().into_bytes()),
+        |r| r.encoding.clone(),
+    );
 
     Ok(SingleDecodeResult {
+        symbology: Symbology::Qr,
         content,
         version: Some(meta.version.0 as u8),
         error_correction: Some(convert_rqrr_ec(meta.ecc_level)),
+        encoding: Some(encoding),
+        mirrored: false,
+        ec_used_ratio: reread.as_ref().map(|r| r.symbol.worst_block_usage()),
+        ec_usage: reread.as_ref().map(|r| r.symbol.ec_usage()),
+        grid: reread.map(|r| r.grid),
+    })
+}
+
+/// rqrr on a luma buffer, retried on a mirrored copy
+///
+/// rqrr never tries mirror images by itself, so the retry is what marks the
+/// result `mirrored`.
+fn decode_with_rqrr_either_way(luma_data: &[u8], width: u32, height: u32) -> Result<SingleDecodeResult> {
+    decode_with_rqrr_raw(luma_data, width, height).or_else(|_| {
+        let luma = GrayImage::from_raw(width, height, luma_data.to_vec()).ok_or(QraiError::DecodeFailed)?;
+        let flipped = image::imageops::flip_horizontal(&luma);
+        let result = decode_with_rqrr_raw(flipped.as_raw(), width, height)?;
+        Ok(SingleDecodeResult { mirrored: true, ..result })
     })
 }
 
 /// Multi-decoder that tries multiple decoders and combines results
 pub fn multi_decode(image_bytes: &[u8]) -> Result<MultiDecodeResult> {
-    let img = image::load_from_memory(image_bytes)
-        .map_err(|e| QraiError::ImageLoad(e.to_string()))?;
-
-    multi_decode_image(&img)
+    let options = DecodeOptions::default();
+    let img = options.limits.load_image(image_bytes)?;
+    multi_decode_image_with(&img, &options)
 }
 
 /// Multi-decoder for already-loaded image
 /// Phase 7: TIERED STRATEGY - prioritize known-good params over random exploration
 /// Tier 1: Original (instant) → Tier 2: Quick trio → Tier 3: Known-good + channels → Tier 4: Brute force
 pub fn multi_decode_image(img: &DynamicImage) -> Result<MultiDecodeResult> {
+    multi_decode_image_with(img, &DecodeOptions::default())
+}
+
+/// Multi-decoder for already-loaded image, restricted to `options.formats`
+///
+/// Images larger than [`pyramid::PYRAMID_THRESHOLD`] are scanned coarse to
+/// fine through an image pyramid rather than at full resolution.
+pub fn multi_decode_image_with(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
+    decode_at_any_size(img, options, true)
+}
+
+/// Every tier except upscaling
+///
+/// Stress tests decode their degraded variants this way, since upscaling
+/// would restore the resolution a downscaled variant gave up.
+pub(crate) fn decode_without_upscale(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
+    decode_at_any_size(img, options, false)
+}
+
+fn decode_at_any_size(img: &DynamicImage, options: &DecodeOptions, upscale: bool) -> Result<MultiDecodeResult> {
+    if pyramid::needs_pyramid(img) {
+        return pyramid::scan(img, options, upscale);
+    }
+    decode_tiers(img, options, upscale)
+}
+
+/// The tiered decode strategy, run on the image at its own resolution
+pub(crate) fn decode_tiers(img: &DynamicImage, options: &DecodeOptions, upscale: bool) -> Result<MultiDecodeResult> {
+    decode_staged(img, options, true, upscale)
+}
+
+/// Tiers 1-3: the original, the quick trio and the unified parallel pool
+pub(crate) fn decode_without_brute_force(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
+    decode_staged(img, options, false, true)
+}
+
+fn decode_staged(img: &DynamicImage, options: &DecodeOptions, brute_force: bool, upscale: bool) -> Result<MultiDecodeResult> {
+    if let Some(result) = decode_quick(img, options) {
+        return Ok(result);
+    }
+
     // ========================================================================
-    // TIER 1: Original image (instant for clean QRs) - ~80ms
+    // Upscale: the tiers below only shrink, so tiny images whose modules
+    // are one or two pixels wide are enlarged and re-binarised first
     // ========================================================================
-    if let Ok(result) = try_decode_with_both(img) {
-        return Ok(result);
+    if upscale {
+        if let Ok(result) = upscale::decode_upscaled(img, options) {
+            return Ok(result);
+        }
     }
 
     // ========================================================================
-    // TIER 2: Quick preprocessing trio (parallel) - ~100ms
-    // These catch many artistic QRs without heavy processing
-    // SECURITY: Filter out None values from safe image processing
+    // Region of interest: crop to the likely symbol so the heavy tiers spend
+    // their variants on it rather than the background. Grid and finder
+    // regions outline a symbol and are tried first; a texture region is only
+    // a guess and is tried after the whole image. Brute force runs once, on
+    // the crop when the region is confirmed.
     // ========================================================================
-    let quick_variants: Vec<DynamicImage> = [
-        apply_otsu_threshold(img),
-        apply_otsu_threshold(img).and_then(|otsu| invert_image(&otsu)),
-        apply_high_contrast_threshold(img),
-    ].into_iter().flatten().collect();
+    let roi = roi::locate_roi(img);
+    let confirmed = roi.filter(|r| r.source != RoiSource::Texture);
+    let crop = |roi: &RegionOfInterest| {
+        let Region { x, y, width, height } = roi.region;
+        img.crop_imm(x, y, width, height)
+    };
 
-    if let Some(result) = quick_variants.par_iter().find_map_any(|v| try_decode_with_both(v).ok()) {
-        return Ok(result);
+    if let Some(roi) = confirmed {
+        if let Ok(result) = try_unified_parallel_pool(&crop(&roi), options) {
+            return Ok(traced(result, DecodeStage::ParallelPool, Some(roi)));
+        }
     }
 
     // ========================================================================
     // TIER 3: ALL known strategies in ONE parallel pool
-    // Known-good params (16) + Color channels (12) + HSV (6) = 34 strategies
+    // Known-good params + colour and HSV channels + adaptive thresholds + morphology
     // First success wins instantly via find_map_any
     // ========================================================================
-    if let Ok(result) = try_unified_parallel_pool(img) {
+    if let Ok(result) = try_unified_parallel_pool(img, options) {
+        return Ok(traced(result, DecodeStage::ParallelPool, None));
+    }
+
+    if let Some(roi) = roi.filter(|r| r.source == RoiSource::Texture) {
+        if let Ok(result) = try_unified_parallel_pool(&crop(&roi), options) {
+            return Ok(traced(result, DecodeStage::ParallelPool, Some(roi)));
+        }
+    }
+
+    // ========================================================================
+    // Module centres: artwork that is only right at module centres defeats
+    // whole-image binarisation, so located grids are read by majority votes
+    // over small centre windows, straight into Reed-Solomon. It locates and
+    // samples the symbol on every call, so it runs only once the pools fail
+    // ========================================================================
+    if let Ok(result) = centres::decode_module_centres(img, options) {
         return Ok(result);
     }
 
@@ -174,17 +427,80 @@ pub fn multi_decode_image(img: &DynamicImage) -> Result<MultiDecodeResult> {
     // TIER 4: Full brute force (256 random combos) - last resort
     // Some images like 3eb25154 need many tries to find winning params
     // ========================================================================
-    if let Ok(result) = try_mini_brute_force(img, 256) {
-        return Ok(result);
+    if !brute_force {
+        return Err(QraiError::DecodeFailed);
     }
+    match confirmed {
+        Some(roi) => try_mini_brute_force(&crop(&roi), 256, options).map(|r| traced(r, DecodeStage::BruteForce, Some(roi))),
+        None => try_mini_brute_force(img, 256, options).map(|r| traced(r, DecodeStage::BruteForce, None)),
+    }
+}
 
-    Err(QraiError::DecodeFailed)
+/// Tiers 1-2: the original image, then quick thresholds and orientations
+fn decode_quick(img: &DynamicImage, options: &DecodeOptions) -> Option<MultiDecodeResult> {
+    // ========================================================================
+    // TIER 1: Original image (instant for clean QRs) - ~80ms
+    // ========================================================================
+    if let Ok(result) = try_decode_with_both(img, options) {
+        return Some(traced(result, DecodeStage::Original, None));
+    }
+
+    // ========================================================================
+    // TIER 2: Quick preprocessing trio (parallel) - ~100ms
+    // These catch many artistic QRs without heavy processing, alongside an
+    // explicit inverted copy, tagged (inverted, mirrored) so the result can
+    // report it. Mirrored copies are only tried once every other variant
+    // has failed, so a symbol that reads either way round is never reported
+    // by whichever copy happened to finish first.
+    // SECURITY: Filter out None values from safe image processing
+    // ========================================================================
+    let otsu = apply_otsu_threshold(img);
+    let inverted = invert_image(img);
+    let quick_variants: Vec<(DynamicImage, bool, bool)> = [
+        (otsu.clone(), false, false),
+        (otsu.and_then(|otsu| invert_image(&otsu)), true, false),
+        (apply_high_contrast_threshold(img), false, false),
+        (inverted.clone(), true, false),
+    ].into_iter().filter_map(|(v, inverted, mirrored)| Some((v?, inverted, mirrored))).collect();
+    let flipped_variants: Vec<(DynamicImage, bool, bool)> = [
+        (Some(img.fliph()), false, true),
+        (inverted.map(|inv| inv.fliph()), true, true),
+    ].into_iter().filter_map(|(v, inverted, mirrored)| Some((v?, inverted, mirrored))).collect();
+
+    [quick_variants, flipped_variants]
+        .iter()
+        .find_map(|variants| {
+            variants.par_iter().find_map_first(|(v, inverted, mirrored)| {
+                try_decode_with_both(v, options).ok().map(|r| oriented(r, *inverted, *mirrored))
+            })
+        })
+        .map(|result| traced(result, DecodeStage::QuickThreshold, None))
 }
 
-/// Unified parallel pool: known-good params + color channels + HSV
+/// Record the decode stage and region of interest in the result's metadata
+fn traced(mut result: MultiDecodeResult, stage: DecodeStage, roi: Option<RegionOfInterest>) -> MultiDecodeResult {
+    if let Some(meta) = result.metadata.as_mut() {
+        meta.trace = Some(DecodeTrace { stage, roi, ..Default::default() });
+    }
+    result
+}
+
+/// Record that a result was read from an inverted and/or mirrored copy
+///
+/// Flipping the copy flips whatever the read reported, so a flipped copy
+/// that itself read as mirrored holds a normal symbol.
+fn oriented(mut result: MultiDecodeResult, inverted: bool, mirrored: bool) -> MultiDecodeResult {
+    if let Some(meta) = result.metadata.as_mut() {
+        meta.inverted |= inverted;
+        meta.mirrored ^= mirrored;
+    }
+    result
+}
+
+/// Unified parallel pool: known-good params + color channels + HSV + adaptive thresholds + morphology
 /// All 34+ strategies run simultaneously, first success exits instantly
 /// SECURITY: Gracefully handles image processing failures by filtering them out
-fn try_unified_parallel_pool(img: &DynamicImage) -> Result<MultiDecodeResult> {
+fn try_unified_parallel_pool(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
     // Pre-extract all variants (returns empty vec on failure)
     let channels = extract_color_channels(img);
     let hue = extract_hue_channel(img);
@@ -238,14 +554,25 @@ fn try_unified_parallel_pool(img: &DynamicImage) -> Result<MultiDecodeResult> {
         }
     }
 
+    // Local thresholds and CLAHE for artwork whose colours change by region,
+    // on a copy no larger than the known-good resizes
+    let adaptive_source = if img.width().max(img.height()) > 512 { img.thumbnail(512, 512) } else { img.clone() };
+    variants.extend(adaptive::adaptive_variants(&adaptive_source).into_iter().map(|(_, v)| v));
+
+    // Closing, opening, median and majority filters sized to the module
+    // pitch, rebuilding solid modules from dots and other stylised shapes
+    variants.extend(morphology::morphology_variants(&adaptive_source).into_iter().map(|(_, v)| v));
+
     // Try all in parallel with 3 variants each (raw + otsu + inverted)
+    // Channel polarity is arbitrary here, so a read from the inverted Otsu
+    // says nothing about the printed symbol and is not reported as inverted
     // SECURITY: Gracefully handle processing failures inside parallel loop
     variants.par_iter().find_map_any(|v| {
-        if let Ok(r) = try_decode_with_both(v) { return Some(r); }
+        if let Ok(r) = try_decode_with_both(v, options) { return Some(r); }
         if let Some(otsu) = apply_otsu_threshold(v) {
-            if let Ok(r) = try_decode_with_both(&otsu) { return Some(r); }
+            if let Ok(r) = try_decode_with_both(&otsu, options) { return Some(r); }
             if let Some(inv) = invert_image(&otsu) {
-                if let Ok(r) = try_decode_with_both(&inv) { return Some(r); }
+                if let Ok(r) = try_decode_with_both(&inv, options) { return Some(r); }
             }
         }
         None
@@ -254,7 +581,7 @@ fn try_unified_parallel_pool(img: &DynamicImage) -> Result<MultiDecodeResult> {
 
 /// Mini brute force: 64 random combos (reduced from 256)
 /// Only runs if known-good strategies fail
-fn try_mini_brute_force(img: &DynamicImage, num_tries: u32) -> Result<MultiDecodeResult> {
+fn try_mini_brute_force(img: &DynamicImage, num_tries: u32, options: &DecodeOptions) -> Result<MultiDecodeResult> {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     let mut seed = SystemTime::now()
@@ -284,11 +611,11 @@ fn try_mini_brute_force(img: &DynamicImage, num_tries: u32) -> Result<MultiDecod
     // SECURITY: Gracefully handle processing failures
     params_list.par_iter().find_map_any(|params| {
         let processed = apply_preprocessing_fast(img, params)?;
-        if let Ok(r) = try_decode_with_both(&processed) { return Some(r); }
+        if let Ok(r) = try_decode_with_both(&processed, options) { return Some(r); }
         if let Some(otsu) = apply_otsu_threshold(&processed) {
-            if let Ok(r) = try_decode_with_both(&otsu) { return Some(r); }
+            if let Ok(r) = try_decode_with_both(&otsu, options) { return Some(r); }
             if let Some(inv) = invert_image(&otsu) {
-                if let Ok(r) = try_decode_with_both(&inv) { return Some(r); }
+                if let Ok(r) = try_decode_with_both(&inv, options) { return Some(r); }
             }
         }
         None
@@ -352,18 +679,40 @@ fn apply_preprocessing_fast(img: &DynamicImage, params: &PreprocessParams) -> Op
 /// Returns early when first decoder succeeds for performance optimization
 /// Pre-converts to luma8 once to avoid duplicate conversions (~100ms saved)
 /// Quick Win 2: Only call rqrr if rxing lacks metadata (saves ~50% decode time)
-fn try_decode_with_both(img: &DynamicImage) -> Result<MultiDecodeResult> {
+pub(crate) fn try_decode_with_both(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
     // Phase 2 optimization: Single luma8 conversion for both decoders
     let luma = img.to_luma8();
     let (width, height) = luma.dimensions();
     let luma_data = luma.into_raw();
 
     // Try rxing first
-    if let Ok(rxing_result) = decode_with_rxing_raw(&luma_data, width, height) {
+    if let Ok(rxing_result) = decode_with_rxing_raw(&luma_data, width, height, options) {
         // Quick Win 2: Only try rqrr if rxing lacks metadata (version is a good indicator)
-        let (version, error_correction, decoders) = if rxing_result.version.is_none() {
+        let encoding = rxing_result.encoding.clone();
+        let symbology = rxing_result.symbology;
+        let mut mirrored = false;
+        let (mut ec_used_ratio, mut ec_usage) = (rxing_result.ec_used_ratio, rxing_result.ec_usage);
+        let mut grid = rxing_result.grid.clone();
+        let (version, error_correction, decoders) = if symbology != Symbology::Qr {
+            // rqrr only reads standard QR, so there is nothing to cross-check
+            (
+                rxing_result.version.unwrap_or(0),
+                rxing_result.error_correction.unwrap_or(ErrorCorrectionLevel::M),
+                vec!["rxing".to_string()],
+            )
+        } else if rxing_result.version.is_none() {
             // rxing lacks metadata, try rqrr to get it
-            if let Ok(rqrr_result) = decode_with_rqrr_raw(&luma_data, width, height) {
+            if let Ok(rqrr_result) = decode_with_rqrr_either_way(&luma_data, width, height) {
+                // rxing reads mirrored symbols silently; rqrr needing the
+                // flipped copy for the same content is how we find out
+                let same = rqrr_result.content == rxing_result.content;
+                mirrored = rqrr_result.mirrored && same;
+                if same && ec_used_ratio.is_none() {
+                    (ec_used_ratio, ec_usage) = (rqrr_result.ec_used_ratio, rqrr_result.ec_usage);
+                }
+                if same && grid.is_none() {
+                    grid = rqrr_result.grid;
+                }
                 (
                     rqrr_result.version.unwrap_or(0),
                     rqrr_result.error_correction.unwrap_or(ErrorCorrectionLevel::M),
@@ -371,7 +720,11 @@ fn try_decode_with_both(img: &DynamicImage) -> Result<MultiDecodeResult> {
                 )
             } else {
                 // rqrr also failed, use defaults
-                (0, ErrorCorrectionLevel::M, vec!["rxing".to_string()])
+                (
+                    0,
+                    rxing_result.error_correction.unwrap_or(ErrorCorrectionLevel::M),
+                    vec!["rxing".to_string()],
+                )
             }
         } else {
             // rxing has metadata, skip rqrr entirely (50% faster!)
@@ -382,38 +735,181 @@ fn try_decode_with_both(img: &DynamicImage) -> Result<MultiDecodeResult> {
             )
         };
 
-        let modules = if version > 0 { 17 + version * 4 } else { 0 };
+        let modules = micro::symbol_size(symbology, version).map_or(0, |(columns, _)| columns);
         return Ok(MultiDecodeResult {
             content: rxing_result.content.clone(),
+            raw_bytes: encoding.as_ref().map(|e| e.raw_bytes.clone()),
             metadata: Some(QrMetadata {
+                symbology,
                 version,
                 error_correction,
                 modules,
                 decoders_success: decoders.clone(),
+                ec_used_ratio,
+                ec_usage,
+                eci: encoding.as_ref().and_then(|e| e.eci),
+                charset: encoding.as_ref().map(|e| e.charset),
+                structured_append: encoding.as_ref().and_then(|e| e.structured_append),
+                fnc1: encoding.as_ref().and_then(|e| e.fnc1),
+                trace: None,
+                inverted: false,
+                mirrored,
+                segments: encoding.map(|e| e.segments).unwrap_or_default(),
             }),
             decoders_success: decoders,
+            grid,
         });
     }
 
-    // Only try rqrr if rxing failed
-    if let Ok(result) = decode_with_rqrr_raw(&luma_data, width, height) {
+    // Only try rqrr if rxing failed (it reads standard QR only)
+    let rqrr_result = if options.accepts(Symbology::Qr) {
+        decode_with_rqrr_raw(&luma_data, width, height)
+    } else {
+        Err(QraiError::DecodeFailed)
+    };
+    if let Ok(result) = rqrr_result {
         let version = result.version.unwrap_or(0);
         let modules = if version > 0 { 17 + version * 4 } else { 0 };
+        let encoding = result.encoding;
         return Ok(MultiDecodeResult {
             content: result.content.clone(),
+            raw_bytes: encoding.as_ref().map(|e| e.raw_bytes.clone()),
             metadata: Some(QrMetadata {
+                symbology: Symbology::Qr,
                 version,
                 error_correction: result.error_correction.unwrap_or(ErrorCorrectionLevel::M),
                 modules,
                 decoders_success: vec!["rqrr".to_string()],
+                ec_used_ratio: result.ec_used_ratio,
+                ec_usage: result.ec_usage,
+                eci: encoding.as_ref().and_then(|e| e.eci),
+                charset: encoding.as_ref().map(|e| e.charset),
+                structured_append: encoding.as_ref().and_then(|e| e.structured_append),
+                fnc1: encoding.as_ref().and_then(|e| e.fnc1),
+                trace: None,
+                inverted: false,
+                mirrored: false,
+                segments: encoding.map(|e| e.segments).unwrap_or_default(),
             }),
             decoders_success: vec!["rqrr".to_string()],
+            grid: result.grid,
+        });
+    }
+
+    // Pure Micro QR / rMQR symbols that rxing's finder detector missed
+    if let Some(read) = GrayImage::from_raw(width, height, luma_data)
+        .and_then(|gray| micro::read_pure(&gray))
+        .filter(|read| options.accepts(read.symbology))
+    {
+        let encoding = read.encoding;
+        return Ok(MultiDecodeResult {
+            content: read.content,
+            raw_bytes: Some(encoding.raw_bytes.clone()),
+            metadata: Some(QrMetadata {
+                symbology: read.symbology,
+                version: read.version,
+                error_correction: read.error_correction.unwrap_or(ErrorCorrectionLevel::M),
+                modules: read.columns,
+                decoders_success: vec!["native".to_string()],
+                ec_used_ratio: None,
+                ec_usage: None,
+                eci: encoding.eci,
+                charset: Some(encoding.charset),
+                segments: encoding.segments,
+                structured_append: encoding.structured_append,
+                fnc1: encoding.fnc1,
+                trace: None,
+                inverted: false,
+                mirrored: false,
+            }),
+            decoders_success: vec!["native".to_string()],
+            grid: None,
         });
     }
 
     Err(QraiError::DecodeFailed)
 }
 
+/// Every QR-family content both decoders read from one image, as (decoder, content)
+///
+/// Unlike `try_decode_with_both` this never stops early: both decoders run
+/// and every symbol they find is kept, so disagreements are visible.
+pub(crate) fn decode_all_with_both(img: &DynamicImage) -> Vec<(&'static str, String)> {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    let mut reads = Vec::new();
+
+    if let Ok(results) = rxing::helpers::detect_multiple_in_luma(luma.as_raw().clone(), width, height) {
+        reads.extend(
+            results
+                .iter()
+                .filter(|r| {
+                    symbology_of(r.getBarcodeFormat()).is_some_and(|s| Symbology::QR_FAMILY.contains(&s))
+                })
+                .map(|r| ("rxing", r.getText().to_string())),
+        );
+    }
+
+    let rqrr_reads = catch_rqrr_panic(|| {
+        let mut prepared = rqrr::PreparedImage::prepare(luma.clone());
+        let grids = prepared.detect_grids();
+        grids.iter().filter_map(|grid| grid.decode().ok()).map(|(_, content)| content).collect::<Vec<_>>()
+    });
+    reads.extend(rqrr_reads.into_iter().flatten().map(|content| ("rqrr", content)));
+
+    if let Some(read) = micro::read_pure(&luma) {
+        reads.push(("native", read.content));
+    }
+
+    reads
+}
+
+/// Bitstream of every standard QR symbol in an image
+///
+/// Reads each grid rqrr finds, in the image and its Otsu threshold, so the
+/// Structured Append headers of every symbol are available. The same symbol
+/// may be reported more than once.
+pub(crate) fn read_all_encodings(img: &DynamicImage) -> Vec<PayloadEncoding> {
+    [Some(img.clone()), apply_otsu_threshold(img)]
+        .into_iter()
+        .flatten()
+        .flat_map(|variant| {
+            catch_rqrr_panic(|| {
+                let mut prepared = rqrr::PreparedImage::prepare(variant.to_luma8());
+                let grids = prepared.detect_grids();
+                grids.iter().filter_map(|grid| reread_grid(&grid.grid).map(|r| r.encoding)).collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Named preprocessing variants used for consensus decoding
+///
+/// A fixed, deterministic subset of the tiers in `multi_decode_image`:
+/// thresholds, colour and HSV channels and one known-good resize.
+pub(crate) fn consensus_variants(img: &DynamicImage) -> Vec<(&'static str, DynamicImage)> {
+    let otsu = apply_otsu_threshold(img);
+    let otsu_inverted = otsu.as_ref().and_then(invert_image);
+    let resize_params = PreprocessParams { resize: 400, contrast: 2.0, brightness: 1.0, blur: 0.0, grayscale: true };
+
+    let mut variants = vec![
+        ("original", Some(img.clone())),
+        ("otsu", otsu),
+        ("otsu_inverted", otsu_inverted),
+        ("high_contrast", apply_high_contrast_threshold(img)),
+        ("contrast_stretch", enhance_contrast(img)),
+        ("resize_400", apply_preprocessing_fast(img, &resize_params)),
+    ];
+    let channel_names = ["red", "green", "blue", "saturation"];
+    variants.extend(channel_names.into_iter().zip(extract_color_channels(img).into_iter().map(Some)));
+    variants.push(("hue", extract_hue_channel(img)));
+    variants.push(("value", extract_value_channel(img)));
+
+    // SECURITY: Drop variants whose processing failed
+    variants.into_iter().filter_map(|(name, v)| Some((name, v?))).collect()
+}
+
 // ============================================================================
 // Image Preprocessing Functions for Artistic QR Codes
 // ============================================================================
@@ -450,7 +946,7 @@ fn enhance_contrast(img: &DynamicImage) -> Option<DynamicImage> {
 /// Apply Otsu's thresholding for automatic binarization
 /// Optimized: Uses raw buffer instead of put_pixel
 /// Returns None if image buffer creation fails (security: prevents panic on malformed input)
-fn apply_otsu_threshold(img: &DynamicImage) -> Option<DynamicImage> {
+pub(crate) fn apply_otsu_threshold(img: &DynamicImage) -> Option<DynamicImage> {
     let gray = img.to_luma8();
     let (width, height) = gray.dimensions();
     let raw = gray.as_raw();
@@ -657,10 +1153,76 @@ fn extract_version_from_rxing(_result: &rxing::RXingResult) -> Option<u8> {
     None
 }
 
-/// Extract error correction level from rxing result
-fn extract_ec_from_rxing(_result: &rxing::RXingResult) -> Option<ErrorCorrectionLevel> {
-    // Similar to version, EC level extraction from rxing is complex
-    None
+/// Extract error correction level from rxing result metadata
+fn extract_ec_from_rxing(result: &rxing::RXingResult) -> Option<ErrorCorrectionLevel> {
+    match result.getRXingResultMetadata().get(&rxing::RXingResultMetadataType::ERROR_CORRECTION_LEVEL) {
+        Some(rxing::RXingResultMetadataValue::ErrorCorrectionLevel(level)) => micro::parse_ec_level(level),
+        _ => None,
+    }
+}
+
+/// GS1 mode from rxing's AIM symbology identifier (`]Q3`/`]Q4` for QR, `]d2` for Data Matrix)
+///
+/// Covers symbols whose bitstream we could not re-read ourselves.
+fn extract_fnc1_from_rxing(result: &rxing::RXingResult) -> Option<Fnc1Mode> {
+    match result.getRXingResultMetadata().get(&rxing::RXingResultMetadataType::SYMBOLOGY_IDENTIFIER) {
+        Some(rxing::RXingResultMetadataValue::SymbologyIdentifier(id)) if matches!(id.as_str(), "]Q3" | "]Q4" | "]d2") => {
+            Some(Fnc1Mode::Gs1)
+        }
+        _ => None,
+    }
+}
+
+/// Our symbology for an rxing barcode format, `None` for formats we don't read
+fn symbology_of(format: &rxing::BarcodeFormat) -> Option<Symbology> {
+    match format {
+        rxing::BarcodeFormat::QR_CODE => Some(Symbology::Qr),
+        rxing::BarcodeFormat::MICRO_QR_CODE => Some(Symbology::MicroQr),
+        rxing::BarcodeFormat::RECTANGULAR_MICRO_QR_CODE => Some(Symbology::RectangularMicroQr),
+        rxing::BarcodeFormat::DATA_MATRIX => Some(Symbology::DataMatrix),
+        rxing::BarcodeFormat::AZTEC => Some(Symbology::Aztec),
+        rxing::BarcodeFormat::PDF_417 => Some(Symbology::Pdf417),
+        _ => None,
+    }
+}
+
+/// rxing barcode format for one of our symbologies
+fn rxing_format(symbology: Symbology) -> rxing::BarcodeFormat {
+    match symbology {
+        Symbology::Qr => rxing::BarcodeFormat::QR_CODE,
+        Symbology::MicroQr => rxing::BarcodeFormat::MICRO_QR_CODE,
+        Symbology::RectangularMicroQr => rxing::BarcodeFormat::RECTANGULAR_MICRO_QR_CODE,
+        Symbology::DataMatrix => rxing::BarcodeFormat::DATA_MATRIX,
+        Symbology::Aztec => rxing::BarcodeFormat::AZTEC,
+        Symbology::Pdf417 => rxing::BarcodeFormat::PDF_417,
+    }
+}
+
+/// rxing's symbol re-read from the image, for the segments, EC usage and
+/// module grid rxing keeps private
+///
+/// rxing reports the decoded payload bytes but not the bitstream, so the
+/// symbol is located again and its corrected codewords parsed. The re-read
+/// is only kept if it reproduces rxing's bytes.
+fn reread_rxing(result: &rxing::RXingResult, luma_data: &[u8], width: u32, height: u32) -> Option<Reread> {
+    let gray = GrayImage::from_raw(width, height, luma_data.to_vec())?;
+    let location = locate_in_luma(&gray).or_else(|| locate_with_rxing(&gray))?;
+    let (sampled, symbol) = read_symbol(&gray, &location)?;
+    let encoding = parse_segments(&symbol.data_codewords(), symbol.version.getVersionNumber() as u8)?;
+    (encoding.raw_bytes == result.getRawBytes()).then(|| Reread {
+        encoding,
+        symbol,
+        grid: ModuleGrid::from_dark(&sampled, location.size),
+    })
+}
+
+/// Re-read rqrr's sampled grid to recover the data codewords rqrr keeps private
+fn reread_grid<G: rqrr::BitGrid>(grid: &G) -> Option<Reread> {
+    let size = grid.size();
+    let dark: Vec<bool> = (0..size * size).map(|i| grid.bit(i / size, i % size)).collect();
+    let symbol = correct_symbol(&dark, size)?;
+    let encoding = parse_segments(&symbol.data_codewords(), symbol.version.getVersionNumber() as u8)?;
+    Some(Reread { encoding, symbol, grid: ModuleGrid::from_dark(&dark, size) })
 }
 
 /// Convert rqrr ECC level (u16) to our type
@@ -715,6 +1277,21 @@ mod tests {
         assert_eq!(result.unwrap().content, "https://example.com");
     }
 
+    #[test]
+    fn both_decoders_report_the_same_segments() {
+        use crate::segments::SegmentMode;
+        use image::Luma;
+
+        let code = qrcode::QrCode::new(b"ORDER 12345678901234 ok").unwrap();
+        let img = DynamicImage::ImageLuma8(code.render::<Luma<u8>>().build());
+        let rxing = decode_with_rxing(&img).unwrap().encoding.unwrap();
+        let rqrr = decode_with_rqrr(&img).unwrap().encoding.unwrap();
+
+        assert_eq!(rxing, rqrr);
+        assert_eq!(rxing.raw_bytes, b"ORDER 12345678901234 ok");
+        assert!(rxing.segments.iter().any(|s| s.mode == SegmentMode::Numeric));
+    }
+
     #[test]
     fn multi_decode_tries_both() {
         let qr_bytes = create_test_qr();
@@ -744,6 +1321,90 @@ mod tests {
         assert!(meta.modules > 0);
     }
 
+    #[test]
+    fn clean_codes_are_traced_to_the_original_stage() {
+        let result = multi_decode(&create_test_qr()).unwrap();
+        let trace = result.metadata.unwrap().trace.unwrap();
+        assert_eq!(trace, DecodeTrace { stage: DecodeStage::Original, ..Default::default() });
+    }
+
+    #[test]
+    fn ec_usage_comes_from_the_winning_read() {
+        use image::Luma;
+
+        let code = qrcode::QrCode::with_error_correction_level(b"https://example.com/ec", qrcode::EcLevel::H).unwrap();
+        let size = code.width() as u32;
+        let mut colors = code.to_colors();
+        // Flip a run of data modules in the bottom-right corner
+        for x in size - 4..size {
+            let i = ((size - 1) * size + x) as usize;
+            colors[i] = colors[i].select(qrcode::Color::Light, qrcode::Color::Dark);
+        }
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn((size + 8) * 8, (size + 8) * 8, |x, y| {
+            let (mx, my) = ((x / 8).wrapping_sub(4), (y / 8).wrapping_sub(4));
+            let dark = mx < size && my < size && colors[(my * size + mx) as usize] == qrcode::Color::Dark;
+            Luma([if dark { 0 } else { 255 }])
+        }));
+
+        let meta = try_decode_with_both(&img, &DecodeOptions::default()).unwrap().metadata.unwrap();
+        assert!(meta.ec_usage.unwrap().codewords_corrected > 0);
+        assert!(meta.ec_used_ratio.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn inverted_and_mirrored_codes_are_reported() {
+        let img = image::load_from_memory(&create_test_qr()).unwrap();
+        let inverted = invert_image(&img).unwrap();
+        for (variant, expected) in [
+            (img.clone(), (false, false)),
+            (inverted.clone(), (true, false)),
+            (img.fliph(), (false, true)),
+            (inverted.fliph(), (true, true)),
+        ] {
+            let result = multi_decode_image(&variant).unwrap();
+            assert_eq!(result.content, "https://example.com");
+            let meta = result.metadata.unwrap();
+            assert_eq!((meta.inverted, meta.mirrored), expected);
+        }
+    }
+
+    #[test]
+    fn flipped_copies_flip_the_reported_mirroring() {
+        let img = image::load_from_memory(&create_test_qr()).unwrap();
+        let options = DecodeOptions::default();
+        let mirrored = |result: MultiDecodeResult| oriented(result, false, true).metadata.unwrap().mirrored;
+
+        // A mirrored symbol's flipped copy reads as a normal symbol
+        assert!(mirrored(try_decode_with_both(&img, &options).unwrap()));
+
+        // A normal symbol's flipped copy reads as mirrored
+        let flipped = try_decode_with_both(&img.fliph(), &options).unwrap();
+        assert!(flipped.metadata.as_ref().unwrap().mirrored);
+        assert!(!mirrored(flipped));
+
+        // Unflipped copies keep the read's own report
+        let kept = oriented(try_decode_with_both(&img.fliph(), &options).unwrap(), true, false);
+        assert!(kept.metadata.unwrap().mirrored);
+    }
+
+    #[test]
+    fn rqrr_reads_mirrored_symbols_only_from_a_flipped_copy() {
+        let mirrored = image::load_from_memory(&create_test_qr()).unwrap().fliph().to_luma8();
+        let (width, height) = mirrored.dimensions();
+        assert!(decode_with_rqrr_raw(mirrored.as_raw(), width, height).is_err());
+        let result = decode_with_rqrr_either_way(mirrored.as_raw(), width, height).unwrap();
+        assert_eq!(result.content, "https://example.com");
+        assert!(result.mirrored);
+    }
+
+    #[test]
+    fn one_pixel_modules_do_not_panic_rqrr() {
+        // rqrr measures the timing pattern as zero modules and asserts
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let img = DynamicImage::ImageLuma8(code.render::<image::Luma<u8>>().module_dimensions(1, 1).build());
+        assert_eq!(multi_decode_image(&img).unwrap().content, "https://example.com");
+    }
+
     #[test]
     fn decode_invalid_image_returns_error() {
         let garbage = b"not an image at all";
@@ -763,4 +1424,30 @@ mod tests {
         let result = multi_decode(&buf);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn image_from_pixels_handles_stride_and_bgra() {
+        // 2x2 BGRA with 4 bytes of padding per row
+        let data = [
+            255, 0, 0, 255, 0, 0, 255, 255, 9, 9, 9, 9, //
+            0, 255, 0, 255, 10, 20, 30, 40, 9, 9, 9, 9,
+        ];
+        let pixels = PixelBuffer { data: &data, width: 2, height: 2, stride: 12, format: PixelFormat::Bgra8 };
+        let rgba = image_from_pixels(&pixels).unwrap().to_rgba8();
+        assert_eq!(rgba.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(rgba.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        assert_eq!(rgba.get_pixel(1, 1).0, [30, 20, 10, 40]);
+
+        // The last row needs no padding
+        assert!(image_from_pixels(&PixelBuffer { data: &data[..20], ..pixels }).is_ok());
+        assert!(matches!(
+            image_from_pixels(&PixelBuffer { data: &data[..19], ..pixels }),
+            Err(QraiError::BufferMismatch { expected: 20, actual: 19 })
+        ));
+        assert!(matches!(
+            image_from_pixels(&PixelBuffer { stride: 7, ..pixels }),
+            Err(QraiError::InvalidStride { stride: 7, row_bytes: 8 })
+        ));
+        assert!(image_from_pixels(&PixelBuffer::new(&[], 0, 0, PixelFormat::Luma8)).is_err());
+    }
 }
@@ -20,20 +20,57 @@ pub enum QraiError {
     ImageProcessing(String),
 
     /// Image dimensions exceed safety limits
-    #[error("Image too large: {width}x{height} exceeds maximum {max_dimension}x{max_dimension}")]
+    #[error("Image too large: {width}x{height} exceeds maximum {max_width}x{max_height}")]
     DimensionsTooLarge {
         width: u32,
         height: u32,
-        max_dimension: u32,
+        max_width: u32,
+        max_height: u32,
     },
 
+    /// Image pixel count exceeds safety limits
+    #[error("Image too large: {pixels} pixels exceeds maximum {max_pixels}")]
+    TooManyPixels { pixels: u64, max_pixels: u64 },
+
+    /// Input buffer exceeds safety limits
+    #[error("Input too large: {bytes} bytes exceeds maximum {max_bytes}")]
+    InputTooLarge { bytes: usize, max_bytes: usize },
+
+    /// Image decoder hit a resource limit (e.g. memory allocation)
+    #[error("Resource limit exceeded: {0}")]
+    LimitExceeded(String),
+
     /// Failed to create image buffer (dimension/data mismatch)
     #[error("Failed to create image buffer: expected {expected} bytes, got {actual}")]
     BufferMismatch { expected: usize, actual: usize },
 
+    /// Pixel buffer rows shorter than the image width
+    #[error("Invalid stride: {stride} bytes per row, need at least {row_bytes}")]
+    InvalidStride { stride: usize, row_bytes: usize },
+
     /// Integer overflow in dimension calculation
     #[error("Dimension overflow: {width} x {height} overflows")]
     DimensionOverflow { width: u32, height: u32 },
+
+    /// Decoded content differs from what the caller expected
+    #[error("Content mismatch: expected {expected:?}, decoded {actual:?} ({diff})")]
+    ContentMismatch {
+        expected: String,
+        actual: String,
+        diff: String,
+    },
+
+    /// Structured Append message with parts that were not found
+    #[error("Structured append message incomplete: missing part(s) {missing:?} of {total}")]
+    MissingAppendParts { total: u8, missing: Vec<u8> },
+
+    /// Reassembled Structured Append message fails its parity check
+    #[error("Structured append parity mismatch: header says {expected:#04x}, message XORs to {actual:#04x}")]
+    AppendParityMismatch { expected: u8, actual: u8 },
+
+    /// Structured Append parts that cannot belong to one message
+    #[error("Inconsistent structured append parts: {0}")]
+    InconsistentAppendParts(String),
 }
 
 pub type Result<T> = std::result::Result<T, QraiError>;
@@ -63,6 +100,40 @@ mod tests {
         assert!(err.to_string().contains("IO error"));
     }
 
+    #[test]
+    fn error_display_content_mismatch() {
+        let err = QraiError::ContentMismatch {
+            expected: "a".to_string(),
+            actual: "b".to_string(),
+            diff: "first difference at byte 0: [-a-]{+b+}".to_string(),
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("Content mismatch"));
+        assert!(msg.contains("[-a-]{+b+}"));
+    }
+
+    #[test]
+    fn error_display_missing_append_parts() {
+        let err = QraiError::MissingAppendParts { total: 4, missing: vec![1, 3] };
+        let msg = err.to_string();
+        assert!(msg.contains("incomplete"));
+        assert!(msg.contains("[1, 3] of 4"));
+    }
+
+    #[test]
+    fn error_display_resource_limits() {
+        let err = QraiError::DimensionsTooLarge {
+            width: 20_000,
+            height: 50,
+            max_width: 10_000,
+            max_height: 8_000,
+        };
+        assert!(err.to_string().contains("20000x50 exceeds maximum 10000x8000"));
+
+        let err = QraiError::InputTooLarge { bytes: 100, max_bytes: 10 };
+        assert!(err.to_string().contains("100 bytes exceeds maximum 10"));
+    }
+
     #[test]
     fn error_display_image_processing() {
         let err = QraiError::ImageProcessing("resize failed".to_string());
@@ -0,0 +1,227 @@
+//! Expected-content assertions
+//!
+//! Proves a generated code still encodes exactly what was requested. The
+//! default comparison is byte-for-byte against the raw payload; the URL mode
+//! tolerates differences that don't change where a link goes (host case,
+//! default ports, a trailing slash).
+
+use crate::error::{QraiError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Characters of context shown around the first difference
+const DIFF_CONTEXT: usize = 20;
+
+/// How decoded content is compared with the expected value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MatchMode {
+    /// Byte-for-byte equality with the raw payload
+    #[default]
+    Exact,
+    /// Compare normalised URLs; falls back to exact when either side isn't a URL
+    Url,
+}
+
+/// Check decoded content against the expected value
+///
+/// `raw_bytes` is the payload as encoded, if known; otherwise `content` is
+/// compared as UTF-8.
+///
+/// # Errors
+/// * `QraiError::ContentMismatch` with a diff when they differ
+pub fn check_content(raw_bytes: Option<&[u8]>, content: &str, expected: &[u8], mode: MatchMode) -> Result<()> {
+    let actual = raw_bytes.unwrap_or(content.as_bytes());
+    if mode == MatchMode::Url {
+        let normalized = std::str::from_utf8(expected)
+            .ok()
+            .and_then(normalize_url)
+            .zip(normalize_url(content));
+        if let Some((expected_url, actual_url)) = normalized {
+            return match expected_url == actual_url {
+                true => Ok(()),
+                false => Err(mismatch(expected_url.as_bytes(), actual_url.as_bytes())),
+            };
+        }
+    }
+    match actual == expected {
+        true => Ok(()),
+        false => Err(mismatch(expected, actual)),
+    }
+}
+
+/// Canonical form of an `http`/`https` URL
+///
+/// Lower-cases the scheme and host, drops default ports (80, 443) and a
+/// trailing slash on the path; the query and fragment are kept verbatim.
+/// Returns `None` for anything that isn't an HTTP(S) URL.
+pub fn normalize_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => "80",
+        "https" => "443",
+        _ => return None,
+    };
+
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(authority_end);
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((user, host)) => (Some(user), host),
+        None => (None, authority),
+    };
+    // IPv6 hosts keep their brackets
+    let (host, port) = match host_port.find(']') {
+        Some(end) if host_port.starts_with('[') => {
+            let (host, port) = host_port.split_at(end + 1);
+            (host, port.strip_prefix(':'))
+        }
+        _ => match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+    if host.is_empty() {
+        return None;
+    }
+
+    let path_end = tail.find(['?', '#']).unwrap_or(tail.len());
+    let (path, suffix) = tail.split_at(path_end);
+    let path = path.trim_end_matches('/');
+
+    let mut normalized = format!("{scheme}://");
+    if let Some(user) = userinfo {
+        normalized.push_str(user);
+        normalized.push('@');
+    }
+    normalized.push_str(&host.trim_end_matches('.').to_lowercase());
+    if let Some(port) = port.filter(|p| !p.is_empty() && *p != default_port) {
+        normalized.push(':');
+        normalized.push_str(port);
+    }
+    normalized.push_str(path);
+    normalized.push_str(suffix);
+    Some(normalized)
+}
+
+fn mismatch(expected: &[u8], actual: &[u8]) -> QraiError {
+    QraiError::ContentMismatch {
+        expected: display(expected),
+        actual: display(actual),
+        diff: render_diff(expected, actual),
+    }
+}
+
+/// Text as-is, or escaped bytes when it isn't valid UTF-8
+fn display(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.escape_ascii().to_string(),
+    }
+}
+
+/// One-line diff around the first difference: `…same[-expected-]{+actual+}same…`
+///
+/// Text is shown as-is (cut on character boundaries); if either side isn't
+/// UTF-8 the whole diff is byte-escaped.
+fn render_diff(expected: &[u8], actual: &[u8]) -> String {
+    let text = std::str::from_utf8(expected).is_ok() && std::str::from_utf8(actual).is_ok();
+    // Byte offsets that are safe to cut at in both inputs
+    let boundary = |bytes: &[u8], i: usize| !text || i >= bytes.len() || (bytes[i] as i8) >= -0x40;
+
+    let mut prefix = expected.iter().zip(actual).take_while(|(a, b)| a == b).count();
+    while !(boundary(expected, prefix) && boundary(actual, prefix)) {
+        prefix -= 1;
+    }
+    let max_suffix = expected.len().min(actual.len()) - prefix;
+    let mut suffix = expected
+        .iter()
+        .rev()
+        .zip(actual.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !(boundary(expected, expected.len() - suffix) && boundary(actual, actual.len() - suffix)) {
+        suffix -= 1;
+    }
+
+    let mut start = prefix.saturating_sub(DIFF_CONTEXT);
+    while !boundary(expected, start) {
+        start -= 1;
+    }
+    let mut end = (expected.len() - suffix + DIFF_CONTEXT.min(suffix)).min(expected.len());
+    while !boundary(expected, end) {
+        end += 1;
+    }
+
+    let show = |bytes: &[u8]| match text {
+        true => String::from_utf8_lossy(bytes).into_owned(),
+        false => bytes.escape_ascii().to_string(),
+    };
+    format!(
+        "first difference at byte {prefix}: {}{}[-{}-]{{+{}+}}{}{}",
+        if start > 0 { "…" } else { "" },
+        show(&expected[start..prefix]),
+        show(&expected[prefix..expected.len() - suffix]),
+        show(&actual[prefix..actual.len() - suffix]),
+        show(&expected[expected.len() - suffix..end]),
+        if end < expected.len() { "…" } else { "" },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_mode_requires_identical_bytes() {
+        assert!(check_content(None, "https://example.com", b"https://example.com", MatchMode::Exact).is_ok());
+        assert!(check_content(None, "https://example.com/", b"https://example.com", MatchMode::Exact).is_err());
+        // Raw bytes win over the lossy text view
+        assert!(check_content(Some(&[0xE9]), "\u{FFFD}", &[0xE9], MatchMode::Exact).is_ok());
+    }
+
+    #[test]
+    fn mismatch_carries_a_diff() {
+        let err = check_content(None, "https://example.com/promo-b", b"https://example.com/promo-a", MatchMode::Exact)
+            .unwrap_err();
+        match err {
+            QraiError::ContentMismatch { expected, actual, diff } => {
+                assert_eq!(expected, "https://example.com/promo-a");
+                assert_eq!(actual, "https://example.com/promo-b");
+                assert_eq!(diff, "first difference at byte 26: …//example.com/promo-[-a-]{+b+}");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn diff_handles_insertions_and_binary() {
+        assert_eq!(render_diff(b"abc", b"abXc"), "first difference at byte 2: ab[--]{+X+}c");
+        assert_eq!(render_diff(&[0x00, 0xFF], &[0x00, 0xFE]), "first difference at byte 1: \\x00[-\\xff-]{+\\xfe+}");
+        // "é" and "è" share their first byte
+        assert_eq!(render_diff("café".as_bytes(), "cafè".as_bytes()), "first difference at byte 3: caf[-é-]{+è+}");
+    }
+
+    #[test]
+    fn url_mode_ignores_equivalent_spellings() {
+        let same = |a: &str, b: &str| check_content(None, a, b.as_bytes(), MatchMode::Url).is_ok();
+        assert!(same("https://EXAMPLE.com:443/menu/", "https://example.com/menu"));
+        assert!(same("HTTP://example.com:80", "http://example.com/"));
+        assert!(same("https://example.com/?a=1", "https://example.com?a=1"));
+        assert!(!same("https://example.com/Menu", "https://example.com/menu"));
+        assert!(!same("https://example.com:8443/", "https://example.com/"));
+        assert!(!same("http://example.com", "https://example.com"));
+        // Not URLs: exact comparison
+        assert!(!same("Hello", "hello"));
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        assert_eq!(
+            normalize_url("HTTPS://User@Example.COM.:443/a/b/?q=X#Top").as_deref(),
+            Some("https://User@example.com/a/b?q=X#Top")
+        );
+        assert_eq!(normalize_url("https://[::1]:443/").as_deref(), Some("https://[::1]"));
+        assert_eq!(normalize_url("ftp://example.com"), None);
+        assert_eq!(normalize_url("example.com"), None);
+    }
+}
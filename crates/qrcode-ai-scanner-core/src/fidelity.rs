@@ -0,0 +1,423 @@
+//! Module-level fidelity
+//!
+//! Compares the modules sampled from the image with the symbol they were
+//! meant to be. The reference is rebuilt from the Reed-Solomon corrected
+//! codewords (same version, EC level and mask), so every module the styling
+//! flipped shows up, along with how much of the EC budget those flips cost.
+
+use crate::locate::{is_qr_size, locate_symbol, otsu_level, sample_modules, SymbolLocation};
+use crate::types::EcUsage;
+use image::{DynamicImage, GrayImage};
+use rxing::common::reedsolomon::{get_predefined_genericgf, PredefinedGenericGF, ReedSolomonDecoder};
+use rxing::common::{BitArray, BitMatrix};
+use rxing::qrcode::decoder::{BitMatrixParser, DataBlock, ErrorCorrectionLevel as RxingEcLevel, Version, VersionRef};
+use rxing::qrcode::encoder::{matrix_util, ByteMatrix};
+use serde::{Deserialize, Serialize};
+
+/// Cells per side of the error map
+const ERROR_MAP_CELLS: usize = 4;
+
+/// EC levels format information can name
+const EC_LEVELS: [RxingEcLevel; 4] = [RxingEcLevel::L, RxingEcLevel::M, RxingEcLevel::Q, RxingEcLevel::H];
+
+/// Flipped modules inside one cell of the error map
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionErrors {
+    /// Left module column of the region
+    pub x: u8,
+    /// Top module row of the region
+    pub y: u8,
+    /// Region width in modules
+    pub width: u8,
+    /// Region height in modules
+    pub height: u8,
+    /// Modules that differ from the reference
+    pub flipped: u16,
+    /// Share of the region's modules that differ (0.0-1.0)
+    pub error_ratio: f32,
+}
+
+/// Fidelity of the sampled modules against the reference symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleFidelity {
+    /// Modules per side
+    pub modules: u8,
+    /// Number of modules that differ from the reference
+    pub flipped_modules: usize,
+    /// Positions (x, y) of the flipped modules, row-major
+    pub flipped_positions: Vec<(u8, u8)>,
+    /// Share of all modules that differ (0.0-1.0)
+    pub flipped_ratio: f32,
+    /// Codewords Reed-Solomon had to correct, across all blocks
+    pub codewords_corrected: usize,
+    /// Codewords Reed-Solomon can correct, across all blocks
+    pub codewords_correctable: usize,
+    /// EC budget consumed by the worst block (0.0-1.0)
+    ///
+    /// Blocks fail independently, so the worst one bounds how much damage
+    /// the symbol can still absorb.
+    pub ec_budget_used: f32,
+    /// Flipped modules per region, row-major over a 4x4 grid
+    pub error_map: Vec<RegionErrors>,
+}
+
+/// One Reed-Solomon block after correction
+#[derive(Debug, Clone)]
+pub(crate) struct CorrectedBlock {
+    /// Corrected data + EC codewords
+    pub codewords: Vec<u8>,
+    pub num_data: usize,
+    /// Codewords that had to be corrected
+    pub errors: usize,
+    /// Codewords this block can correct
+    pub capacity: usize,
+}
+
+/// Symbol read from a module grid, with every block error-corrected
+#[derive(Debug, Clone)]
+pub(crate) struct CorrectedSymbol {
+    pub version: VersionRef,
+    pub ec_level: RxingEcLevel,
+    pub mask: u8,
+    pub blocks: Vec<CorrectedBlock>,
+}
+
+impl CorrectedSymbol {
+    /// Worst block's share of its correction capacity (0.0-1.0)
+    pub fn worst_block_usage(&self) -> f32 {
+        self.blocks
+            .iter()
+            .map(|b| if b.capacity == 0 { 0.0 } else { b.errors as f32 / b.capacity as f32 })
+            .fold(0.0, f32::max)
+    }
+
+    /// Corrected data codewords of every block, in bitstream order
+    pub fn data_codewords(&self) -> Vec<u8> {
+        self.blocks
+            .iter()
+            .flat_map(|b| b.codewords[..b.num_data].iter().copied())
+            .collect()
+    }
+
+    /// Corrected codewords and blocks against the symbol's capacity
+    pub fn ec_usage(&self) -> EcUsage {
+        EcUsage {
+            codewords_corrected: self.blocks.iter().map(|b| b.errors as u32).sum(),
+            codewords_capacity: self.blocks.iter().map(|b| b.capacity as u32).sum(),
+            blocks_corrected: self.blocks.iter().filter(|b| b.errors > 0).count() as u32,
+            blocks: self.blocks.len() as u32,
+        }
+    }
+}
+
+/// Locate the symbol and compare it module by module with its reference
+///
+/// Returns `None` when the symbol cannot be located or its codewords are
+/// beyond what Reed-Solomon can repair.
+pub fn analyze_fidelity(img: &DynamicImage) -> Option<ModuleFidelity> {
+    let location = locate_symbol(img)?;
+    analyze_at(&img.to_luma8(), &location)
+}
+
+/// Compare a symbol at a known location with its reference
+pub fn analyze_at(gray: &GrayImage, location: &SymbolLocation) -> Option<ModuleFidelity> {
+    let (sampled, symbol) = read_symbol(gray, location)?;
+    compare(&sampled, &symbol, location.size)
+}
+
+/// Sample a located symbol (row-major, dark = true) and error-correct its codewords
+pub(crate) fn read_symbol(gray: &GrayImage, location: &SymbolLocation) -> Option<(Vec<bool>, CorrectedSymbol)> {
+    let samples = sample_modules(gray, location);
+    let level = otsu_level(&samples);
+    let sampled: Vec<bool> = samples.iter().map(|&s| s < level).collect();
+    let symbol = correct_symbol(&sampled, location.size)?;
+    Some((sampled, symbol))
+}
+
+/// Diff sampled modules against the reference rebuilt from `symbol`
+pub(crate) fn compare(sampled: &[bool], symbol: &CorrectedSymbol, size: usize) -> Option<ModuleFidelity> {
+    let reference = reference_modules(symbol, size)?;
+
+    let mut flipped_positions = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            if sampled[y * size + x] != reference[y * size + x] {
+                flipped_positions.push((x as u8, y as u8));
+            }
+        }
+    }
+
+    let usage = symbol.ec_usage();
+    Some(ModuleFidelity {
+        modules: size as u8,
+        flipped_modules: flipped_positions.len(),
+        flipped_ratio: flipped_positions.len() as f32 / (size * size) as f32,
+        codewords_corrected: usage.codewords_corrected as usize,
+        codewords_correctable: usage.codewords_capacity as usize,
+        ec_budget_used: symbol.worst_block_usage(),
+        error_map: error_map(size, &flipped_positions),
+        flipped_positions,
+    })
+}
+
+/// Read and Reed-Solomon correct every block of a row-major module grid
+pub(crate) fn correct_symbol(dark: &[bool], size: usize) -> Option<CorrectedSymbol> {
+    if dark.len() != size * size {
+        return None;
+    }
+    let mut matrix = BitMatrix::with_single_dimension(size as u32).ok()?;
+    for y in 0..size {
+        for x in 0..size {
+            if dark[y * size + x] {
+                matrix.set(x as u32, y as u32);
+            }
+        }
+    }
+
+    let mut parser = BitMatrixParser::new(matrix).ok()?;
+    let version = parser.readVersion().ok()?;
+    let format = parser.readFormatInformation().ok()?;
+    let (ec_level, mask) = (format.getErrorCorrectionLevel(), format.getDataMask());
+    let codewords = parser.readCodewords().ok()?;
+
+    let rs = ReedSolomonDecoder::new(get_predefined_genericgf(PredefinedGenericGF::QrCodeField256));
+    let mut blocks = Vec::new();
+    for block in DataBlock::getDataBlocks(&codewords, version, ec_level).ok()? {
+        let num_data = block.getNumDataCodewords() as usize;
+        let mut ints: Vec<i32> = block.getCodewords().iter().map(|&c| c as i32).collect();
+        let ec_count = ints.len().saturating_sub(num_data);
+        let errors = rs.decode(&mut ints, ec_count as i32).ok()?;
+        blocks.push(CorrectedBlock {
+            codewords: ints.into_iter().map(|c| c as u8).collect(),
+            num_data,
+            errors,
+            capacity: ec_count / 2,
+        });
+    }
+
+    Some(CorrectedSymbol {
+        version,
+        ec_level,
+        mask,
+        blocks,
+    })
+}
+
+/// Correct a grid whose format or version information is unreadable
+///
+/// Every EC level and mask pair is written over both format information
+/// copies, and for version 7 and up the version implied by the module count
+/// over both version information blocks. The pair Reed-Solomon corrects
+/// with the fewest errors wins; a wrong mask or block layout leaves
+/// codewords no decode can repair.
+pub(crate) fn recover_format(dark: &[bool], size: usize) -> Option<CorrectedSymbol> {
+    if dark.len() != size * size || !is_qr_size(size) {
+        return None;
+    }
+    let version = Version::getVersionForNumber(((size - 17) / 4) as u32).ok()?;
+    EC_LEVELS
+        .iter()
+        .flat_map(|ec_level| (0..8).map(move |mask| (ec_level, mask)))
+        .filter_map(|(ec_level, mask)| {
+            let mut matrix = ByteMatrix::new(size as u32, size as u32);
+            for (i, &module) in dark.iter().enumerate() {
+                matrix.set_bool((i % size) as u32, (i / size) as u32, module);
+            }
+            matrix_util::embedTypeInfo(ec_level, mask, &mut matrix).ok()?;
+            matrix_util::maybeEmbedVersionInfo(version, &mut matrix).ok()?;
+            let rewritten: Vec<bool> = (0..size * size)
+                .map(|i| matrix.get((i % size) as u32, (i / size) as u32) == 1)
+                .collect();
+            correct_symbol(&rewritten, size)
+        })
+        .min_by_key(|symbol| symbol.ec_usage().codewords_corrected)
+}
+
+/// Rebuild the ideal module grid (row-major, dark = true) from corrected codewords
+pub(crate) fn reference_modules(symbol: &CorrectedSymbol, size: usize) -> Option<Vec<bool>> {
+    // Re-interleave: data codewords column by column, then EC codewords
+    let mut bits = BitArray::new();
+    let max_data = symbol.blocks.iter().map(|b| b.num_data).max().unwrap_or(0);
+    for i in 0..max_data {
+        for block in symbol.blocks.iter().filter(|b| i < b.num_data) {
+            bits.appendBits(block.codewords[i] as usize, 8).ok()?;
+        }
+    }
+    let max_ec = symbol
+        .blocks
+        .iter()
+        .map(|b| b.codewords.len() - b.num_data)
+        .max()
+        .unwrap_or(0);
+    for i in 0..max_ec {
+        for block in &symbol.blocks {
+            if let Some(&cw) = block.codewords.get(block.num_data + i) {
+                bits.appendBits(cw as usize, 8).ok()?;
+            }
+        }
+    }
+
+    let mut matrix = ByteMatrix::new(size as u32, size as u32);
+    matrix_util::buildMatrix(&bits, &symbol.ec_level, symbol.version, symbol.mask as i32, &mut matrix).ok()?;
+
+    let mut modules = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            modules.push(matrix.get(x as u32, y as u32) == 1);
+        }
+    }
+    Some(modules)
+}
+
+/// Bucket flipped modules into an `ERROR_MAP_CELLS` x `ERROR_MAP_CELLS` grid
+fn error_map(size: usize, flipped: &[(u8, u8)]) -> Vec<RegionErrors> {
+    let bounds = |i: usize| (i * size / ERROR_MAP_CELLS, (i + 1) * size / ERROR_MAP_CELLS);
+
+    let mut regions = Vec::with_capacity(ERROR_MAP_CELLS * ERROR_MAP_CELLS);
+    for row in 0..ERROR_MAP_CELLS {
+        let (y0, y1) = bounds(row);
+        for col in 0..ERROR_MAP_CELLS {
+            let (x0, x1) = bounds(col);
+            let count = flipped
+                .iter()
+                .filter(|&&(x, y)| (x0..x1).contains(&(x as usize)) && (y0..y1).contains(&(y as usize)))
+                .count();
+            let total = (x1 - x0) * (y1 - y0);
+            regions.push(RegionErrors {
+                x: x0 as u8,
+                y: y0 as u8,
+                width: (x1 - x0) as u8,
+                height: (y1 - y0) as u8,
+                flipped: count as u16,
+                error_ratio: if total == 0 { 0.0 } else { count as f32 / total as f32 },
+            });
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    const MODULE_PX: u32 = 6;
+    const QUIET_PX: u32 = 4 * MODULE_PX;
+
+    fn render(code: &qrcode::QrCode) -> GrayImage {
+        code.render::<Luma<u8>>()
+            .module_dimensions(MODULE_PX, MODULE_PX)
+            .build()
+    }
+
+    fn flip(img: &mut GrayImage, x: u32, y: u32) {
+        let cx = QUIET_PX + x * MODULE_PX + MODULE_PX / 2;
+        let cy = QUIET_PX + y * MODULE_PX + MODULE_PX / 2;
+        let value = if img.get_pixel(cx, cy).0[0] < 128 { 255 } else { 0 };
+        for py in 0..MODULE_PX {
+            for px in 0..MODULE_PX {
+                img.put_pixel(QUIET_PX + x * MODULE_PX + px, QUIET_PX + y * MODULE_PX + py, Luma([value]));
+            }
+        }
+    }
+
+    #[test]
+    fn clean_symbol_matches_reference() {
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let img = DynamicImage::ImageLuma8(render(&code));
+        let fidelity = analyze_fidelity(&img).expect("fidelity");
+
+        assert_eq!(fidelity.modules as usize, code.width());
+        assert_eq!(fidelity.flipped_modules, 0);
+        assert_eq!(fidelity.codewords_corrected, 0);
+        assert_eq!(fidelity.ec_budget_used, 0.0);
+        assert!(fidelity.codewords_correctable > 0);
+        assert_eq!(fidelity.error_map.len(), ERROR_MAP_CELLS * ERROR_MAP_CELLS);
+    }
+
+    #[test]
+    fn reference_matches_encoder_output() {
+        let code = qrcode::QrCode::with_error_correction_level(b"reference", qrcode::EcLevel::Q).unwrap();
+        let dark: Vec<bool> = code.to_colors().iter().map(|&c| c == qrcode::Color::Dark).collect();
+        let symbol = correct_symbol(&dark, code.width()).unwrap();
+
+        assert_eq!(reference_modules(&symbol, code.width()).unwrap(), dark);
+    }
+
+    #[test]
+    fn format_and_version_information_are_recovered() {
+        let code = qrcode::QrCode::with_version(b"https://example.com/recover", qrcode::Version::Normal(8), qrcode::EcLevel::Q)
+            .unwrap();
+        let size = code.width();
+        let dark: Vec<bool> = code.to_colors().iter().map(|&c| c == qrcode::Color::Dark).collect();
+
+        // Paint over both format copies and both version blocks
+        let mut damaged = dark.clone();
+        let mut paint = |x: usize, y: usize| damaged[y * size + x] = true;
+        for i in 0..9 {
+            paint(i, 8);
+            paint(8, i);
+        }
+        for i in size - 8..size {
+            paint(i, 8);
+            paint(8, i);
+        }
+        for i in 0..6 {
+            for j in size - 11..size - 8 {
+                paint(i, j);
+                paint(j, i);
+            }
+        }
+        assert!(correct_symbol(&damaged, size).is_none());
+
+        let symbol = recover_format(&damaged, size).unwrap();
+        assert_eq!(symbol.version.getVersionNumber(), 8);
+        assert_eq!(symbol.ec_level, RxingEcLevel::Q);
+        assert_eq!(reference_modules(&symbol, size).unwrap(), dark);
+
+        // A size between versions would otherwise round down to one
+        assert!(recover_format(&vec![false; 23 * 23], 23).is_none());
+    }
+
+    #[test]
+    fn flipped_data_modules_are_reported() {
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let size = code.width() as u32;
+        let mut gray = render(&code);
+        // Bottom-right corner is always data, far from function patterns
+        flip(&mut gray, size - 1, size - 1);
+        flip(&mut gray, size - 2, size - 1);
+
+        let fidelity = analyze_fidelity(&DynamicImage::ImageLuma8(gray)).unwrap();
+        assert_eq!(fidelity.flipped_modules, 2);
+        assert!(fidelity.flipped_positions.contains(&((size - 1) as u8, (size - 1) as u8)));
+        assert!(fidelity.codewords_corrected >= 1);
+        assert!(fidelity.ec_budget_used > 0.0);
+
+        let corner = fidelity.error_map.last().unwrap();
+        assert_eq!(corner.flipped, 2);
+        assert!(fidelity.error_map[..fidelity.error_map.len() - 1].iter().all(|r| r.flipped == 0));
+    }
+
+    #[test]
+    fn ec_usage_counts_corrected_blocks() {
+        let code = qrcode::QrCode::with_version(b"blocks", qrcode::Version::Normal(5), qrcode::EcLevel::H).unwrap();
+        let size = code.width();
+        let mut dark: Vec<bool> = code.to_colors().iter().map(|&c| c == qrcode::Color::Dark).collect();
+        dark[size * size - 1] = !dark[size * size - 1];
+
+        let usage = correct_symbol(&dark, size).unwrap().ec_usage();
+        // 5-H: 2 blocks of 11 + 2 blocks of 12 data codewords, 22 EC each
+        assert_eq!(usage.blocks, 4);
+        assert_eq!(usage.blocks_corrected, 1);
+        assert_eq!(usage.codewords_corrected, 1);
+        assert_eq!(usage.codewords_capacity, 4 * 11);
+    }
+
+    #[test]
+    fn error_map_covers_every_module() {
+        let regions = error_map(25, &[]);
+        let covered: usize = regions.iter().map(|r| r.width as usize * r.height as usize).sum();
+        assert_eq!(covered, 25 * 25);
+    }
+}
@@ -0,0 +1,353 @@
+//! Raw module matrix extraction
+//!
+//! Samples the located symbol into a boolean module matrix with a confidence
+//! per module, for debugging stylized codes. The grid plugs straight into
+//! both decoders: it implements rqrr's [`rqrr::BitGrid`] and converts to and
+//! from rxing's [`BitMatrix`].
+
+use crate::decoder::{multi_decode_image_with, DecodeOptions};
+use crate::error::{QraiError, Result};
+use crate::locate::{is_qr_size, locate_symbol, otsu_level, sample_modules, SymbolLocation};
+use image::{DynamicImage, GrayImage};
+use rxing::common::BitMatrix;
+use serde::{Deserialize, Serialize};
+
+/// Dark module in the compact text form
+pub const TEXT_DARK: char = '#';
+/// Light module in the compact text form
+pub const TEXT_LIGHT: char = '.';
+
+/// Sampled module matrix of a QR symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleGrid {
+    /// Modules per side
+    pub size: usize,
+    /// Module colours by row (`true` = dark)
+    pub modules: Vec<Vec<bool>>,
+    /// How clearly each module sits on its side of the threshold (0.0-1.0)
+    pub confidence: Vec<Vec<f32>>,
+    /// Where the symbol was sampled from, if it came from an image
+    pub location: Option<SymbolLocation>,
+}
+
+impl ModuleGrid {
+    /// Whether module (`x`, `y`) is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y][x]
+    }
+
+    /// Mean confidence over all modules
+    pub fn mean_confidence(&self) -> f32 {
+        let total: f32 = self.confidence.iter().flatten().sum();
+        total / (self.size * self.size).max(1) as f32
+    }
+
+    /// Number of modules whose confidence is below `threshold`
+    pub fn low_confidence_count(&self, threshold: f32) -> usize {
+        self.confidence.iter().flatten().filter(|&&c| c < threshold).count()
+    }
+
+    /// Compact text form: one row per line, `#` dark and `.` light
+    pub fn to_text(&self) -> String {
+        self.modules
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&dark| if dark { TEXT_DARK } else { TEXT_LIGHT })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Parse the compact text form (confidence is set to 1.0)
+    ///
+    /// Returns `None` unless the text is a square of `#` and `.` whose side
+    /// is a standard QR size (21 to 177 modules, in steps of 4).
+    pub fn from_text(text: &str) -> Option<Self> {
+        let modules: Vec<Vec<bool>> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        TEXT_DARK => Some(true),
+                        TEXT_LIGHT => Some(false),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<bool>>>()
+            })
+            .collect::<Option<_>>()?;
+
+        let grid = Self::from_modules(modules);
+        grid.is_well_formed().then_some(grid)
+    }
+
+    /// Grid from rxing's sampled bit matrix (confidence is set to 1.0)
+    ///
+    /// # Errors
+    /// * `QraiError::DecodeFailed` if the matrix is not a standard QR size
+    pub fn from_bit_matrix(bits: &BitMatrix) -> Result<Self> {
+        let size = bits.getWidth() as usize;
+        if bits.getHeight() as usize != size || !is_qr_size(size) {
+            return Err(QraiError::DecodeFailed);
+        }
+        let modules = (0..size)
+            .map(|y| (0..size).map(|x| bits.get(x as u32, y as u32)).collect())
+            .collect();
+        Ok(Self::from_modules(modules))
+    }
+
+    /// Whether the grid is a standard QR size with every row and confidence
+    /// row `size` modules long, as both decoders assume
+    pub fn is_well_formed(&self) -> bool {
+        is_qr_size(self.size)
+            && self.modules.len() == self.size
+            && self.confidence.len() == self.size
+            && self.modules.iter().all(|row| row.len() == self.size)
+            && self.confidence.iter().all(|row| row.len() == self.size)
+    }
+
+    /// Convert to rxing's bit matrix
+    pub fn to_bit_matrix(&self) -> Result<BitMatrix> {
+        let mut bits = BitMatrix::with_single_dimension(self.size as u32)
+            .map_err(|_| QraiError::DecodeFailed)?;
+        for (y, row) in self.modules.iter().enumerate() {
+            for (x, &dark) in row.iter().enumerate() {
+                if dark {
+                    bits.set(x as u32, y as u32);
+                }
+            }
+        }
+        Ok(bits)
+    }
+
+    /// Decode the grid directly, without going back to pixels
+    ///
+    /// Tries rqrr first, then rxing's bit matrix decoder (which also handles
+    /// mirrored symbols). Grids that are not well formed, e.g. deserialized
+    /// from hand-edited JSON, fail with `QraiError::DecodeFailed`.
+    pub fn decode(&self) -> Result<String> {
+        if !self.is_well_formed() {
+            return Err(QraiError::DecodeFailed);
+        }
+        if let Ok((_, content)) = rqrr::Grid::new(self).decode() {
+            return Ok(content);
+        }
+        let bits = self.to_bit_matrix()?;
+        rxing::qrcode::decoder::qrcode_decoder::decode_bitmatrix(&bits)
+            .map(|result| result.getText().to_string())
+            .map_err(|_| QraiError::DecodeFailed)
+    }
+
+    /// Grid from row-major module colours (`true` = dark), confidence 1.0
+    pub(crate) fn from_dark(dark: &[bool], size: usize) -> Self {
+        Self::from_modules(dark.chunks(size.max(1)).map(<[bool]>::to_vec).collect())
+    }
+
+    fn from_modules(modules: Vec<Vec<bool>>) -> Self {
+        let size = modules.len();
+        Self {
+            size,
+            confidence: vec![vec![1.0; size]; size],
+            modules,
+            location: None,
+        }
+    }
+}
+
+impl rqrr::BitGrid for ModuleGrid {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn bit(&self, y: usize, x: usize) -> bool {
+        self.modules[y][x]
+    }
+}
+
+impl rqrr::BitGrid for &ModuleGrid {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn bit(&self, y: usize, x: usize) -> bool {
+        self.modules[y][x]
+    }
+}
+
+/// Locate the symbol in an image and sample its module grid
+///
+/// The image itself is sampled first, for per-module confidence and a
+/// location in image coordinates. When that grid does not decode, e.g. a
+/// stylized code only the preprocessing tiers read, the grid of the winning
+/// decode is returned instead; it has no location and full confidence. A
+/// grid that does not decode is still returned when nothing reads the code.
+///
+/// # Errors
+/// * `QraiError::DecodeFailed` if no symbol can be located or decoded
+pub fn extract_grid_from_image(img: &DynamicImage, options: &DecodeOptions) -> Result<ModuleGrid> {
+    let sampled = locate_symbol(img).map(|location| sample_grid(&img.to_luma8(), &location));
+    if let Some(grid) = sampled.as_ref().filter(|grid| grid.decode().is_ok()) {
+        return Ok(grid.clone());
+    }
+    multi_decode_image_with(img, options)
+        .ok()
+        .and_then(|result| result.grid)
+        .or(sampled)
+        .ok_or(QraiError::DecodeFailed)
+}
+
+/// Sample the module grid of a symbol at a known location
+///
+/// Confidence is the distance of each module's mean luma from the midpoint
+/// between the average dark and light modules, relative to half their gap.
+pub fn sample_grid(gray: &GrayImage, location: &SymbolLocation) -> ModuleGrid {
+    let size = location.size;
+    let samples = sample_modules(gray, location);
+    let level = otsu_level(&samples);
+
+    let mean = |dark: bool| {
+        let (sum, count) = samples
+            .iter()
+            .filter(|&&s| (s < level) == dark)
+            .fold((0.0f32, 0u32), |(sum, n), &s| (sum + s, n + 1));
+        if count == 0 { level } else { sum / count as f32 }
+    };
+    let (dark_mean, light_mean) = (mean(true), mean(false));
+    let midpoint = (dark_mean + light_mean) / 2.0;
+    let half_gap = ((light_mean - dark_mean) / 2.0).max(1.0);
+
+    let mut modules = Vec::with_capacity(size);
+    let mut confidence = Vec::with_capacity(size);
+    for row in samples.chunks(size.max(1)) {
+        modules.push(row.iter().map(|&s| s < level).collect());
+        confidence.push(
+            row.iter()
+                .map(|&s| ((s - midpoint).abs() / half_gap).min(1.0))
+                .collect(),
+        );
+    }
+
+    ModuleGrid {
+        size,
+        modules,
+        confidence,
+        location: Some(*location),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn render(code: &qrcode::QrCode) -> DynamicImage {
+        DynamicImage::ImageLuma8(code.render::<Luma<u8>>().module_dimensions(6, 6).build())
+    }
+
+    #[test]
+    fn extracts_encoded_matrix() {
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let grid = extract_grid_from_image(&render(&code), &DecodeOptions::default()).unwrap();
+
+        assert_eq!(grid.size, code.width());
+        let expected: Vec<bool> = code.to_colors().iter().map(|&c| c == qrcode::Color::Dark).collect();
+        let actual: Vec<bool> = grid.modules.iter().flatten().copied().collect();
+        assert_eq!(actual, expected);
+        assert!(grid.mean_confidence() > 0.9);
+        assert_eq!(grid.low_confidence_count(0.5), 0);
+        assert_eq!(grid.decode().unwrap(), "https://example.com");
+    }
+
+    #[test]
+    fn grid_comes_from_the_winning_decode_when_the_image_cannot_be_sampled() {
+        let code = qrcode::QrCode::new(b"https://example.com/inverted").unwrap();
+        let mut img = render(&code);
+        img.invert();
+        assert!(locate_symbol(&img).is_none());
+
+        let grid = extract_grid_from_image(&img, &DecodeOptions::default()).unwrap();
+        let expected: Vec<bool> = code.to_colors().iter().map(|&c| c == qrcode::Color::Dark).collect();
+        assert_eq!(grid.modules.iter().flatten().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(grid.location, None);
+        assert_eq!(grid.decode().unwrap(), "https://example.com/inverted");
+    }
+
+    #[test]
+    fn gray_modules_have_low_confidence() {
+        let code = qrcode::QrCode::new(b"confidence").unwrap();
+        let mut gray = code.render::<Luma<u8>>().module_dimensions(6, 6).build();
+        // Wash out module (10, 10) to mid-gray
+        for py in 0..6 {
+            for px in 0..6 {
+                gray.put_pixel(24 + 10 * 6 + px, 24 + 10 * 6 + py, Luma([128]));
+            }
+        }
+        let grid = extract_grid_from_image(&DynamicImage::ImageLuma8(gray), &DecodeOptions::default()).unwrap();
+
+        assert!(grid.confidence[10][10] < 0.1, "{}", grid.confidence[10][10]);
+        assert_eq!(grid.low_confidence_count(0.5), 1);
+    }
+
+    #[test]
+    fn text_form_round_trips() {
+        let code = qrcode::QrCode::new(b"text").unwrap();
+        let grid = extract_grid_from_image(&render(&code), &DecodeOptions::default()).unwrap();
+        let text = grid.to_text();
+
+        assert_eq!(text.lines().count(), grid.size);
+        let parsed = ModuleGrid::from_text(&text).unwrap();
+        assert_eq!(parsed.modules, grid.modules);
+        assert_eq!(parsed.decode().unwrap(), "text");
+    }
+
+    #[test]
+    fn from_text_rejects_malformed_input() {
+        assert!(ModuleGrid::from_text("").is_none());
+        assert!(ModuleGrid::from_text("#.\n#").is_none());
+        assert!(ModuleGrid::from_text("#x\n.#").is_none());
+        // Squares that are not QR sizes
+        assert!(ModuleGrid::from_text("#.\n.#").is_none());
+        assert!(ModuleGrid::from_text(&vec![".".repeat(23); 23].join("\n")).is_none());
+    }
+
+    #[test]
+    fn grids_that_are_not_qr_sized_fail_to_decode() {
+        let mut grid = code_grid("size");
+        grid.size = 2;
+        assert!(matches!(grid.decode(), Err(QraiError::DecodeFailed)));
+
+        let mut ragged = code_grid("size");
+        ragged.modules[3].pop();
+        assert!(matches!(ragged.decode(), Err(QraiError::DecodeFailed)));
+
+        let bits = BitMatrix::with_single_dimension(2).unwrap();
+        assert!(ModuleGrid::from_bit_matrix(&bits).is_err());
+    }
+
+    #[test]
+    fn bit_matrix_round_trips() {
+        let grid = code_grid("bits");
+        let bits = grid.to_bit_matrix().unwrap();
+        assert_eq!(bits.get(0, 0), grid.is_dark(0, 0));
+        assert_eq!(bits.get(8, 1), grid.is_dark(8, 1));
+        assert_eq!(ModuleGrid::from_bit_matrix(&bits).unwrap().modules, grid.modules);
+    }
+
+    #[test]
+    fn serializes_to_json() {
+        let grid = code_grid("json");
+        let json = serde_json::to_string(&grid).unwrap();
+        assert!(json.contains("\"modules\":[[true,true,true,true,true,true,true,false,"));
+        let back: ModuleGrid = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, grid);
+    }
+
+    /// Grid of an encoded symbol, parsed from its text form
+    fn code_grid(text: &str) -> ModuleGrid {
+        let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+        ModuleGrid::from_text(&code.to_debug_str('#', '.')).unwrap()
+    }
+}
@@ -0,0 +1,392 @@
+//! GS1 element strings and Digital Link URIs
+//!
+//! GS1 codes carry product data as application identifiers (AIs): a numeric
+//! prefix naming the field, then its value, e.g. `01` for the GTIN or `17`
+//! for the expiry date. Symbols in FNC1 first-position mode hold them as an
+//! element string, where variable-length values end with a group separator
+//! (0x1D). GS1 Digital Link carries the same pairs in a web URI instead.
+//! Both parse into the same element list, with check digits and dates
+//! validated.
+
+use crate::payload::percent_decode;
+use serde::{Deserialize, Serialize};
+
+/// Group separator ending variable-length values in element strings
+pub const GROUP_SEPARATOR: char = '\u{1D}';
+
+/// Where the GS1 data came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gs1Syntax {
+    /// FNC1 symbol holding concatenated AI / value pairs
+    ElementString,
+    /// `https://` URI with AI / value pairs in its path and query
+    DigitalLink,
+}
+
+/// One application identifier and its value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gs1Element {
+    /// Application identifier, e.g. `01`
+    pub ai: String,
+    /// GS1 data title, e.g. `GTIN`
+    pub title: String,
+    pub value: String,
+}
+
+#[derive(Clone, Copy)]
+enum Length {
+    Fixed(usize),
+    Variable(usize),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// Digits only
+    Numeric,
+    /// Digits ending in a GS1 mod-10 check digit
+    CheckDigit,
+    /// `YYMMDD`, day `00` meaning the end of the month
+    Date,
+    /// GS1 AI encodable character set 82 (printable ASCII, no space)
+    Text,
+}
+
+struct AiSpec {
+    /// Leading digits identifying the AI; the rest of `len` is a free digit
+    prefix: &'static str,
+    len: usize,
+    length: Length,
+    kind: Kind,
+    title: &'static str,
+}
+
+const fn ai(prefix: &'static str, len: usize, length: Length, kind: Kind, title: &'static str) -> AiSpec {
+    AiSpec {
+        prefix,
+        len,
+        length,
+        kind,
+        title,
+    }
+}
+
+use Kind::{CheckDigit, Date, Numeric, Text};
+use Length::{Fixed, Variable};
+
+/// Application identifiers in common retail and logistics use, most specific first
+const AIS: &[AiSpec] = &[
+    ai("00", 2, Fixed(18), CheckDigit, "SSCC"),
+    ai("01", 2, Fixed(14), CheckDigit, "GTIN"),
+    ai("02", 2, Fixed(14), CheckDigit, "CONTENT"),
+    ai("10", 2, Variable(20), Text, "BATCH/LOT"),
+    ai("11", 2, Fixed(6), Date, "PROD DATE"),
+    ai("12", 2, Fixed(6), Date, "DUE DATE"),
+    ai("13", 2, Fixed(6), Date, "PACK DATE"),
+    ai("15", 2, Fixed(6), Date, "BEST BEFORE"),
+    ai("16", 2, Fixed(6), Date, "SELL BY"),
+    ai("17", 2, Fixed(6), Date, "USE BY OR EXPIRY"),
+    ai("20", 2, Fixed(2), Numeric, "VARIANT"),
+    ai("21", 2, Variable(20), Text, "SERIAL"),
+    ai("22", 2, Variable(20), Text, "CPV"),
+    ai("235", 3, Variable(28), Text, "TPX"),
+    ai("240", 3, Variable(30), Text, "ADDITIONAL ID"),
+    ai("241", 3, Variable(30), Text, "CUST. PART No."),
+    ai("250", 3, Variable(30), Text, "SECONDARY SERIAL"),
+    ai("251", 3, Variable(30), Text, "REF. TO SOURCE"),
+    ai("253", 3, Variable(30), Text, "GDTI"),
+    ai("254", 3, Variable(20), Text, "GLN EXTENSION COMPONENT"),
+    ai("30", 2, Variable(8), Numeric, "VAR. COUNT"),
+    ai("310", 4, Fixed(6), Numeric, "NET WEIGHT (kg)"),
+    ai("320", 4, Fixed(6), Numeric, "NET WEIGHT (lb)"),
+    ai("330", 4, Fixed(6), Numeric, "GROSS WEIGHT (kg)"),
+    ai("37", 2, Variable(8), Numeric, "COUNT"),
+    ai("390", 4, Variable(15), Numeric, "AMOUNT"),
+    ai("391", 4, Variable(18), Numeric, "AMOUNT"),
+    ai("392", 4, Variable(15), Numeric, "PRICE"),
+    ai("393", 4, Variable(18), Numeric, "PRICE"),
+    ai("400", 3, Variable(30), Text, "ORDER NUMBER"),
+    ai("401", 3, Variable(30), Text, "GINC"),
+    ai("402", 3, Fixed(17), CheckDigit, "GSIN"),
+    ai("410", 3, Fixed(13), CheckDigit, "SHIP TO LOC"),
+    ai("411", 3, Fixed(13), CheckDigit, "BILL TO"),
+    ai("412", 3, Fixed(13), CheckDigit, "PURCHASE FROM"),
+    ai("413", 3, Fixed(13), CheckDigit, "SHIP FOR LOC"),
+    ai("414", 3, Fixed(13), CheckDigit, "LOC No."),
+    ai("415", 3, Fixed(13), CheckDigit, "PAY TO"),
+    ai("416", 3, Fixed(13), CheckDigit, "PROD/SERV LOC"),
+    ai("417", 3, Fixed(13), CheckDigit, "PARTY"),
+    ai("420", 3, Variable(20), Text, "SHIP TO POST"),
+    ai("422", 3, Fixed(3), Numeric, "ORIGIN"),
+    ai("7003", 4, Fixed(10), Numeric, "EXPIRY TIME"),
+    ai("8003", 4, Variable(30), Text, "GRAI"),
+    ai("8004", 4, Variable(30), Text, "GIAI"),
+    ai("8006", 4, Fixed(18), Numeric, "ITIP"),
+    ai("8017", 4, Fixed(18), CheckDigit, "GSRN - PROVIDER"),
+    ai("8018", 4, Fixed(18), CheckDigit, "GSRN - RECIPIENT"),
+    ai("8020", 4, Variable(25), Text, "REF No."),
+    ai("8200", 4, Variable(70), Text, "PRODUCT URL"),
+    ai("90", 2, Variable(30), Text, "INTERNAL"),
+    ai("9", 2, Variable(90), Text, "INTERNAL"),
+];
+
+/// AIs that can start a Digital Link path (the primary keys)
+const PRIMARY_KEYS: [&str; 10] = ["01", "00", "253", "401", "402", "414", "417", "8003", "8004", "8006"];
+
+/// Digital Link short names for AIs
+const ALIASES: [(&str, &str); 9] = [
+    ("gtin", "01"),
+    ("sscc", "00"),
+    ("lot", "10"),
+    ("ser", "21"),
+    ("cpv", "22"),
+    ("gln", "414"),
+    ("party", "417"),
+    ("itip", "8006"),
+    ("exp", "17"),
+];
+
+/// Spec for an AI at the start of `data`
+fn spec_at(data: &str) -> Option<(&'static AiSpec, &str)> {
+    let spec = AIS.iter().find(|spec| data.starts_with(spec.prefix))?;
+    let ai = data.get(..spec.len)?;
+    ai.bytes().all(|b| b.is_ascii_digit()).then_some((spec, ai))
+}
+
+/// Spec for a complete AI (or Digital Link alias), with the AI it names
+fn spec_for(key: &str) -> Option<(&'static AiSpec, &str)> {
+    let key = ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(key))
+        .map_or(key, |&(_, ai)| ai);
+    spec_at(key).filter(|(spec, _)| spec.len == key.len())
+}
+
+fn element(spec: &AiSpec, ai: &str, value: &str) -> Gs1Element {
+    Gs1Element {
+        ai: ai.to_string(),
+        title: spec.title.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Split an element string into its AI / value pairs
+///
+/// Accepts an optional AIM symbology identifier (`]Q3`, `]d2`, ...) and
+/// leading FNC1 separators. Fails on an unknown AI, since its value length
+/// cannot be known.
+pub fn parse_element_string(data: &str) -> Result<Vec<Gs1Element>, String> {
+    let mut rest = match data.strip_prefix(']') {
+        Some(identified) => identified.get(2..).unwrap_or_default(),
+        None => data,
+    };
+    let mut elements = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(GROUP_SEPARATOR);
+        if rest.is_empty() {
+            break;
+        }
+        let (spec, ai) = spec_at(rest)
+            .ok_or_else(|| format!("unknown application identifier at '{}'", rest.chars().take(4).collect::<String>()))?;
+        let body = &rest[ai.len()..];
+        let end = match spec.length {
+            Fixed(n) if body.len() >= n && body.is_char_boundary(n) => n,
+            Fixed(n) => return Err(format!("({ai}) needs {n} characters, found {}", body.len())),
+            Variable(_) => body.find(GROUP_SEPARATOR).unwrap_or(body.len()),
+        };
+        elements.push(element(spec, ai, &body[..end]));
+        rest = &body[end..];
+    }
+    if elements.is_empty() {
+        return Err("no application identifiers".to_string());
+    }
+    Ok(elements)
+}
+
+/// AI / value pairs of a GS1 Digital Link URI, or `None` if it is not one
+///
+/// The path must end in `/{primary key}/{value}` followed by any qualifier
+/// pairs; AIs in the query string are data attributes. Short GTINs are
+/// zero-padded to 14 digits.
+pub fn parse_digital_link(url: &str) -> Option<Vec<Gs1Element>> {
+    let rest = url.split_once("://")?.1;
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (location, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let segments: Vec<&str> = location.split('/').skip(1).filter(|s| !s.is_empty()).collect();
+
+    let start = (0..segments.len()).position(|i| {
+        (segments.len() - i).is_multiple_of(2) && is_primary_key(segments[i], &percent_decode(segments[i + 1]))
+    })?;
+
+    let mut elements = Vec::new();
+    for pair in segments[start..].chunks(2) {
+        let (spec, ai) = spec_for(pair[0])?;
+        elements.push(element(spec, ai, &percent_decode(pair[1])));
+    }
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        if let Some((spec, ai)) = spec_for(key) {
+            elements.push(element(spec, ai, &percent_decode(value)));
+        }
+    }
+
+    for element in elements.iter_mut().filter(|e| e.ai == "01") {
+        if matches!(element.value.len(), 8 | 12 | 13) {
+            element.value = format!("{:0>14}", element.value);
+        }
+    }
+    Some(elements)
+}
+
+/// Whether a path pair is a primary key with a value of the right shape
+///
+/// Only the length and character set are checked, not the check digit, so
+/// `/2024/01/15` is a dated path rather than a GTIN while a mistyped GTIN
+/// still parses and is reported by [`check_element`].
+fn is_primary_key(key: &str, value: &str) -> bool {
+    let Some((spec, ai)) = spec_for(key).filter(|(_, ai)| PRIMARY_KEYS.contains(ai)) else {
+        return false;
+    };
+    let length_ok = match spec.length {
+        Fixed(14) if ai == "01" => matches!(value.len(), 8 | 12 | 13 | 14),
+        Fixed(n) => value.len() == n,
+        Variable(max) => (1..=max).contains(&value.len()),
+    };
+    length_ok
+        && match spec.kind {
+            Text => value.bytes().all(|b| b.is_ascii_graphic()),
+            _ => value.bytes().all(|b| b.is_ascii_digit()),
+        }
+}
+
+/// What is wrong with an element's value, if anything
+pub fn check_element(element: &Gs1Element) -> Option<String> {
+    let (spec, _) = spec_for(&element.ai)?;
+    let value = &element.value;
+    match spec.length {
+        Fixed(n) if value.len() != n => return Some(format!("expected {n} characters, found {}", value.len())),
+        Variable(max) if value.is_empty() || value.len() > max => {
+            return Some(format!("expected 1-{max} characters, found {}", value.len()))
+        }
+        _ => {}
+    }
+
+    let digits = value.bytes().all(|b| b.is_ascii_digit());
+    match spec.kind {
+        Text if !value.bytes().all(|b| b.is_ascii_graphic()) => Some("contains characters outside the GS1 set".into()),
+        Text => None,
+        _ if !digits => Some("expected digits only".into()),
+        CheckDigit => {
+            let expected = check_digit(&value[..value.len() - 1]);
+            let actual = value.as_bytes()[value.len() - 1] - b'0';
+            (expected != actual).then(|| format!("check digit is {actual}, expected {expected}"))
+        }
+        Date => check_date(value),
+        Numeric => None,
+    }
+}
+
+/// GS1 mod-10 check digit: weights 3 and 1 alternating from the right
+pub fn check_digit(digits: &str) -> u8 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| u32::from(b - b'0') * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+fn check_date(value: &str) -> Option<String> {
+    let field = |range: std::ops::Range<usize>| value[range].parse::<u32>().unwrap_or_default();
+    let (year, month, day) = (2000 + field(0..2), field(2..4), field(4..6));
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year.is_multiple_of(4) => 29,
+        2 => 28,
+        _ => return Some(format!("month {month:02} does not exist")),
+    };
+    (day > days).then(|| format!("day {day:02} does not exist in month {month:02}"))
+}
+
+/// Human-readable form, e.g. `(01)09506000134352(17)201225`
+pub fn to_hri(elements: &[Gs1Element]) -> String {
+    elements.iter().map(|e| format!("({}){}", e.ai, e.value)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(elements: &[Gs1Element]) -> Vec<(&str, &str)> {
+        elements.iter().map(|e| (e.ai.as_str(), e.value.as_str())).collect()
+    }
+
+    #[test]
+    fn splits_element_strings() {
+        let elements = parse_element_string("]Q30109506000134352172012251012AB\u{1D}21XYZ").unwrap();
+        assert_eq!(
+            pairs(&elements),
+            [("01", "09506000134352"), ("17", "201225"), ("10", "12AB"), ("21", "XYZ")]
+        );
+        assert_eq!(elements[1].title, "USE BY OR EXPIRY");
+        assert_eq!(to_hri(&elements), "(01)09506000134352(17)201225(10)12AB(21)XYZ");
+
+        let elements = parse_element_string("\u{1D}3103000750").unwrap();
+        assert_eq!(pairs(&elements), [("3103", "000750")]);
+
+        assert!(parse_element_string("0109506").is_err());
+        assert!(parse_element_string("5512345").is_err());
+    }
+
+    #[test]
+    fn reads_digital_link_uris() {
+        let elements =
+            parse_digital_link("https://id.gs1.org/01/9506000134352/10/AB%2F12/21/7?17=201225&utm=x").unwrap();
+        assert_eq!(
+            pairs(&elements),
+            [("01", "09506000134352"), ("10", "AB/12"), ("21", "7"), ("17", "201225")]
+        );
+
+        let elements = parse_digital_link("https://brand.example/products/gtin/09506000134352/lot/A1").unwrap();
+        assert_eq!(pairs(&elements), [("01", "09506000134352"), ("10", "A1")]);
+
+        assert!(parse_digital_link("https://example.com/01/2024/report").is_none());
+        assert!(parse_digital_link("https://example.com/blog/10/ideas").is_none());
+
+        // Date-style and slug paths whose segments happen to be primary-key AIs
+        assert!(parse_digital_link("https://news.example/2024/01/15").is_none());
+        assert!(parse_digital_link("https://example.com/blog/01/intro").is_none());
+        assert!(parse_digital_link("https://shop.example/catalog/00/item").is_none());
+        assert!(parse_digital_link("https://example.com/archive/01/1234567890").is_none());
+
+        // A wrong check digit still reads as a GTIN, for check_element to report
+        let elements = parse_digital_link("https://id.gs1.org/01/09506000134353").unwrap();
+        assert_eq!(pairs(&elements), [("01", "09506000134353")]);
+    }
+
+    #[test]
+    fn validates_check_digits_and_dates() {
+        let check = |ai: &str, value: &str| {
+            check_element(&Gs1Element {
+                ai: ai.into(),
+                title: String::new(),
+                value: value.into(),
+            })
+        };
+        assert_eq!(check_digit("0950600013435"), 2);
+        assert_eq!(check("01", "09506000134352"), None);
+        assert_eq!(check("01", "09506000134353").unwrap(), "check digit is 3, expected 2");
+        assert_eq!(check("00", "106141411234567897"), None);
+        assert!(check("01", "0950600013435").is_some());
+
+        assert_eq!(check("17", "240229"), None);
+        assert_eq!(check("17", "250700"), None);
+        assert!(check("17", "230229").is_some());
+        assert!(check("17", "251301").is_some());
+
+        assert!(check("10", "").is_some());
+        assert!(check("10", "A B").is_some());
+        assert_eq!(check("99", "anything"), None);
+    }
+}
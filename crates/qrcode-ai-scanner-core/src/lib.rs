@@ -17,40 +17,127 @@
 //! println!("Content: {:?}", result.content);
 //! ```
 
+pub mod adaptive;
+pub mod append;
+pub mod centres;
+pub mod consensus;
 pub mod decoder;
 pub mod error;
+pub mod expect;
+pub mod fidelity;
+pub mod grid;
+pub mod gs1;
+pub mod limits;
+pub mod locate;
+pub mod micro;
+pub mod morphology;
+pub mod payload;
+pub mod print;
+pub mod pyramid;
+pub mod roi;
 pub mod scorer;
+pub mod segments;
+pub mod structure;
+pub mod tiles;
 pub mod types;
+pub mod upscale;
+pub mod url_risk;
 
+pub use append::AppendedMessage;
+pub use consensus::{Consensus, ContentReading};
+pub use decoder::DecodeOptions;
 pub use error::{QraiError, Result};
+pub use expect::MatchMode;
+pub use fidelity::ModuleFidelity;
+pub use grid::ModuleGrid;
+pub use gs1::{Gs1Element, Gs1Syntax};
+pub use limits::ResourceLimits;
+pub use payload::{parse_payload, parse_payload_with, ParsedPayload, Payload};
+pub use print::{CameraModel, PrintOptions, PrintRecommendation};
+pub use segments::{Charset, Fnc1Mode, PayloadEncoding, Segment, SegmentMode, StructuredAppend};
+pub use structure::StructureHealth;
 pub use types::{
-    DecodeResult, ErrorCorrectionLevel, QrMetadata, StressResults, ValidationResult,
+    DecodeResult, DecodeStage, DecodeTrace, Detection, EcUsage, ErrorCorrectionLevel, PixelBuffer,
+    PixelFormat, QrMetadata, Region, RegionOfInterest, RoiSource, StressResults, Symbology, UpscaleFilter,
+    Upscaling, ValidationResult,
 };
+pub use url_risk::{analyze_url, RiskLevel, UrlRisk};
 
-use decoder::{multi_decode, multi_decode_image};
+use decoder::multi_decode_image_with;
 use image::GenericImageView;
-use scorer::{calculate_fast_score, calculate_score, run_fast_stress_tests, run_stress_tests_on_image};
+use scorer::{
+    apply_ec_penalty, apply_orientation_penalty, calculate_fast_score_for, calculate_score_for, pad_for_stress,
+    run_fast_stress_tests_with, run_stress_tests_with,
+};
 
-// ============================================================================
-// SECURITY: Maximum allowed image dimensions to prevent DoS attacks
-// 10000x10000 = 100MP = ~300MB uncompressed RGB, reasonable for QR scanning
-// ============================================================================
-const MAX_DIMENSION: u32 = 10_000;
-
-/// Validate image dimensions to prevent DoS via memory exhaustion
-fn validate_dimensions(width: u32, height: u32) -> Result<()> {
-    if width > MAX_DIMENSION || height > MAX_DIMENSION {
-        return Err(error::QraiError::DimensionsTooLarge {
-            width,
-            height,
-            max_dimension: MAX_DIMENSION,
-        });
+/// Print recommendation for the default print options, if the module count is known
+///
+/// Only standard QR: the print model assumes a square symbol with a 4-module quiet zone.
+fn recommend_print(
+    metadata: Option<&QrMetadata>,
+    stress: &StressResults,
+    image_size: (u32, u32),
+) -> Option<PrintRecommendation> {
+    let modules = metadata
+        .filter(|m| m.symbology == Symbology::Qr)
+        .map(|m| m.modules)
+        .filter(|&m| m > 0)?;
+    let ppm = print::required_pixels_per_module(modules, stress, image_size);
+    Some(print::recommend(modules, ppm, &PrintOptions::default()))
+}
+
+/// Symbol-level analyses that share a single location pass
+#[derive(Default)]
+struct SymbolAnalysis {
+    structure: Option<StructureHealth>,
+    fidelity: Option<ModuleFidelity>,
+    ec_used_ratio: Option<f32>,
+    ec_usage: Option<EcUsage>,
+}
+
+/// Locate the symbol once, then run pattern health, fidelity and EC usage on it
+///
+/// These analyses model QR structure, so other symbologies get none.
+fn analyze_symbol(img: &image::DynamicImage, symbology: Symbology) -> SymbolAnalysis {
+    if !Symbology::QR_FAMILY.contains(&symbology) {
+        return SymbolAnalysis::default();
     }
-    // Also check for potential overflow in pixel calculations
-    if width.checked_mul(height).is_none() {
-        return Err(error::QraiError::DimensionOverflow { width, height });
+    let Some(location) = locate::locate_symbol(img) else {
+        return SymbolAnalysis::default();
+    };
+    let gray = img.to_luma8();
+    let structure = structure::analyze_at(&gray, &location);
+
+    let Some((sampled, symbol)) = fidelity::read_symbol(&gray, &location) else {
+        return SymbolAnalysis {
+            structure,
+            ..SymbolAnalysis::default()
+        };
+    };
+    SymbolAnalysis {
+        structure,
+        fidelity: fidelity::compare(&sampled, &symbol, location.size),
+        ec_used_ratio: Some(symbol.worst_block_usage()),
+        ec_usage: Some(symbol.ec_usage()),
+    }
+}
+
+/// Fill EC usage the decode could not measure from the analysis, if both
+/// describe the same symbol
+///
+/// The decoders report the EC budget of the read that succeeded; the
+/// analysis re-samples the original image and only stands in for it.
+fn attach_ec_usage(metadata: &mut Option<QrMetadata>, analysis: &SymbolAnalysis) {
+    let (Some(meta), Some(fidelity)) = (metadata.as_mut(), analysis.fidelity.as_ref()) else {
+        return;
+    };
+    if meta.ec_used_ratio.is_some() {
+        return;
+    }
+    if meta.modules == 0 || meta.modules == fidelity.modules {
+        meta.ec_used_ratio = analysis.ec_used_ratio;
+        meta.ec_usage = analysis.ec_usage;
     }
-    Ok(())
 }
 
 /// Validate a QR code image and compute scannability score
@@ -70,27 +157,83 @@ fn validate_dimensions(width: u32, height: u32) -> Result<()> {
 /// * `QraiError::ImageLoad` if the image cannot be parsed
 /// * `QraiError::DecodeFailed` if no QR code is found
 pub fn validate(image_bytes: &[u8]) -> Result<ValidationResult> {
+    validate_with(image_bytes, &DecodeOptions::default())
+}
+
+/// [`validate`] with decode options, e.g. to also accept Data Matrix or Aztec
+pub fn validate_with(image_bytes: &[u8], options: &DecodeOptions) -> Result<ValidationResult> {
     // Quick Win 1: Single image load - pass DynamicImage to both decode and stress tests
-    let img = image::load_from_memory(image_bytes)
-        .map_err(|e| error::QraiError::ImageLoad(e.to_string()))?;
+    // SECURITY: Limits are checked from the header, before any pixel is decoded
+    let img = options.limits.load_image(image_bytes)?;
 
-    // SECURITY: Validate image dimensions to prevent DoS
-    let (width, height) = img.dimensions();
-    validate_dimensions(width, height)?;
+    validate_image(&img, options, false)
+}
 
-    let decode_result = multi_decode_image(&img)?;
-    let stress_results = run_stress_tests_on_image(&img)?;
-    let score = calculate_score(&stress_results, decode_result.decoders_success.len());
+/// [`validate_with`] for already-decoded pixels, e.g. a camera frame or canvas
+///
+/// # Errors
+/// * `QraiError::InvalidStride` / `QraiError::BufferMismatch` if the buffer
+///   does not hold `width` x `height` pixels
+/// * Any error from [`validate`]
+pub fn validate_pixels(pixels: &PixelBuffer, options: &DecodeOptions) -> Result<ValidationResult> {
+    validate_image(&load_pixels(pixels, &options.limits)?, options, false)
+}
+
+/// Decode, stress test and score an image (the reduced stress set when `fast`)
+fn validate_image(img: &image::DynamicImage, options: &DecodeOptions, fast: bool) -> Result<ValidationResult> {
+    let mut decode_result = multi_decode_image_with(img, options)?;
+    let view = pyramid::symbol_view(img, &decode_result);
+    let img = view.as_ref().unwrap_or(img);
+    let padded = pad_for_stress(img, decode_result.metadata.as_ref());
+    let stress_img = padded.as_ref().unwrap_or(img);
+    let stress_results = if fast {
+        run_fast_stress_tests_with(stress_img, options)?
+    } else {
+        run_stress_tests_with(stress_img, options)?
+    };
+    let symbology = decode_result.metadata.as_ref().map(|m| m.symbology).unwrap_or_default();
+    let analysis = analyze_symbol(img, symbology);
+    attach_ec_usage(&mut decode_result.metadata, &analysis);
+
+    let decoders = decode_result.decoders_success.len();
+    let mut score = if fast {
+        calculate_fast_score_for(&stress_results, decoders, symbology)
+    } else {
+        calculate_score_for(&stress_results, decoders, symbology)
+    };
+    if let Some(ratio) = decode_result.metadata.as_ref().and_then(|m| m.ec_used_ratio) {
+        score = apply_ec_penalty(score, ratio);
+    }
+    if let Some(meta) = decode_result.metadata.as_ref() {
+        score = apply_orientation_penalty(score, meta.inverted, meta.mirrored);
+    }
+    let print = recommend_print(decode_result.metadata.as_ref(), &stress_results, img.dimensions());
 
     Ok(ValidationResult {
         score,
         decodable: true,
+        payload: Some(parse_payload_with(
+            &decode_result.content,
+            decode_result.metadata.as_ref().and_then(|m| m.fnc1),
+        )),
+        url_risk: analyze_url(&decode_result.content),
         content: Some(decode_result.content),
+        raw_bytes: decode_result.raw_bytes,
         metadata: decode_result.metadata,
         stress_results,
+        print,
+        structure: analysis.structure,
+        fidelity: analysis.fidelity,
     })
 }
 
+/// Image from a pixel buffer, within the resource limits
+fn load_pixels(pixels: &PixelBuffer, limits: &ResourceLimits) -> Result<image::DynamicImage> {
+    // SECURITY: Validate buffer size and dimensions to prevent DoS
+    limits.check_pixels(pixels)?;
+    decoder::image_from_pixels(pixels)
+}
+
 /// Fast decode without stress tests
 ///
 /// Use this when you only need to verify the QR is readable
@@ -102,14 +245,117 @@ pub fn validate(image_bytes: &[u8]) -> Result<ValidationResult> {
 /// # Returns
 /// * `DecodeResult` with content and metadata
 pub fn decode_only(image_bytes: &[u8]) -> Result<DecodeResult> {
-    let result = multi_decode(image_bytes)?;
+    decode_only_with(image_bytes, &DecodeOptions::default())
+}
+
+/// [`decode_only`] with decode options
+pub fn decode_only_with(image_bytes: &[u8], options: &DecodeOptions) -> Result<DecodeResult> {
+    let img = options.limits.load_image(image_bytes)?;
+    decode_image(&img, options)
+}
+
+/// [`decode_only_with`] for already-decoded pixels
+pub fn decode_only_pixels(pixels: &PixelBuffer, options: &DecodeOptions) -> Result<DecodeResult> {
+    decode_image(&load_pixels(pixels, &options.limits)?, options)
+}
+
+fn decode_image(img: &image::DynamicImage, options: &DecodeOptions) -> Result<DecodeResult> {
+    let result = multi_decode_image_with(img, options)?;
 
     Ok(DecodeResult {
         content: result.content,
+        raw_bytes: result.raw_bytes,
         metadata: result.metadata,
     })
 }
 
+/// Extract the sampled module matrix, with per-module confidence
+///
+/// Useful for debugging stylized codes: shows exactly which modules the
+/// scanner sees as dark, and which ones it is unsure about.
+///
+/// The grid is sampled from the image itself when that grid decodes, and
+/// otherwise taken from the decode that read the symbol, so stylized codes
+/// that only decode after preprocessing still yield their grid.
+///
+/// # Errors
+/// * `QraiError::ImageLoad` if the image cannot be parsed
+/// * `QraiError::DecodeFailed` if no symbol can be located or decoded
+pub fn extract_grid(image_bytes: &[u8]) -> Result<ModuleGrid> {
+    extract_grid_with(image_bytes, &ResourceLimits::default())
+}
+
+/// [`extract_grid`] with custom resource limits
+pub fn extract_grid_with(image_bytes: &[u8], limits: &ResourceLimits) -> Result<ModuleGrid> {
+    let img = limits.load_image(image_bytes)?;
+    let options = DecodeOptions {
+        limits: *limits,
+        ..DecodeOptions::with_formats(&[Symbology::Qr])
+    };
+    grid::extract_grid_from_image(&img, &options)
+}
+
+/// Find every symbol in a large scene by scanning overlapping tiles
+///
+/// Each tile is decoded at native resolution, so symbols covering only a few
+/// percent of a photo are read even when a whole-image decode shrinks them
+/// below decodable size. Detections are positioned in image coordinates.
+///
+/// # Errors
+/// * `QraiError::ImageLoad` if the image cannot be parsed
+/// * `QraiError::DecodeFailed` if no tile decodes
+pub fn decode_tiled(image_bytes: &[u8], options: &DecodeOptions) -> Result<Vec<Detection>> {
+    let img = options.limits.load_image(image_bytes)?;
+    let detections = tiles::scan_tiles(&img, options);
+    if detections.is_empty() {
+        return Err(error::QraiError::DecodeFailed);
+    }
+    Ok(detections)
+}
+
+/// Decode with every decoder over several preprocessing variants
+///
+/// Reports every distinct content seen and flags the image as `ambiguous`
+/// when decoders or variants disagree. Slower than [`decode_only`]; use it
+/// to screen untrusted uploads.
+///
+/// # Errors
+/// * `QraiError::ImageLoad` if the image cannot be parsed
+/// * `QraiError::DecodeFailed` if nothing decodes
+pub fn decode_consensus(image_bytes: &[u8]) -> Result<Consensus> {
+    decode_consensus_with(image_bytes, &ResourceLimits::default())
+}
+
+/// [`decode_consensus`] with custom resource limits
+pub fn decode_consensus_with(image_bytes: &[u8], limits: &ResourceLimits) -> Result<Consensus> {
+    let img = limits.load_image(image_bytes)?;
+    consensus::consensus_decode_image(&img)
+}
+
+/// Reassemble a Structured Append message from one or more images
+///
+/// Each image may hold any number of the message's symbols, in any order.
+///
+/// # Errors
+/// * `QraiError::ImageLoad` if an image cannot be parsed
+/// * `QraiError::DecodeFailed` if no Structured Append symbol is found
+/// * `QraiError::MissingAppendParts` listing the parts that were not found
+/// * `QraiError::AppendParityMismatch` if the reassembled message fails its parity check
+/// * `QraiError::InconsistentAppendParts` if the symbols belong to different messages
+pub fn decode_structured_append(images: &[&[u8]]) -> Result<AppendedMessage> {
+    decode_structured_append_with(images, &ResourceLimits::default())
+}
+
+/// [`decode_structured_append`] with custom resource limits, applied to each image
+pub fn decode_structured_append_with(images: &[&[u8]], limits: &ResourceLimits) -> Result<AppendedMessage> {
+    let images = images
+        .iter()
+        .map(|bytes| limits.load_image(bytes))
+        .collect::<Result<Vec<_>>>()?;
+
+    append::reassemble_images(&images)
+}
+
 /// Fast validation with reduced stress tests
 ///
 /// Runs only a subset of stress tests for faster response times.
@@ -118,24 +364,41 @@ pub fn decode_only(image_bytes: &[u8]) -> Result<DecodeResult> {
 /// # Performance
 /// ~2-3x faster than full validation
 pub fn validate_fast(image_bytes: &[u8]) -> Result<ValidationResult> {
-    let img = image::load_from_memory(image_bytes)
-        .map_err(|e| error::QraiError::ImageLoad(e.to_string()))?;
+    validate_fast_with(image_bytes, &DecodeOptions::default())
+}
 
-    // SECURITY: Validate image dimensions to prevent DoS
-    let (width, height) = img.dimensions();
-    validate_dimensions(width, height)?;
+/// [`validate_fast`] with decode options
+pub fn validate_fast_with(image_bytes: &[u8], options: &DecodeOptions) -> Result<ValidationResult> {
+    let img = options.limits.load_image(image_bytes)?;
 
-    let decode_result = multi_decode_image(&img)?;
-    let stress_results = run_fast_stress_tests(&img)?;
-    let score = calculate_fast_score(&stress_results, decode_result.decoders_success.len());
+    validate_image(&img, options, true)
+}
 
-    Ok(ValidationResult {
-        score,
-        decodable: true,
-        content: Some(decode_result.content),
-        metadata: decode_result.metadata,
-        stress_results,
-    })
+/// [`validate_fast_with`] for already-decoded pixels
+pub fn validate_fast_pixels(pixels: &PixelBuffer, options: &DecodeOptions) -> Result<ValidationResult> {
+    validate_image(&load_pixels(pixels, &options.limits)?, options, true)
+}
+
+/// Validate a QR code and check that it encodes exactly `expected`
+///
+/// # Errors
+/// * `QraiError::ContentMismatch` with a diff if the payload differs
+/// * Any error from [`validate`]
+pub fn validate_expecting(image_bytes: &[u8], expected: &str) -> Result<ValidationResult> {
+    validate_expecting_with(image_bytes, expected.as_bytes(), MatchMode::Exact, &DecodeOptions::default())
+}
+
+/// [`validate_expecting`] with raw expected bytes, a comparison mode and decode options
+pub fn validate_expecting_with(
+    image_bytes: &[u8],
+    expected: &[u8],
+    mode: MatchMode,
+    options: &DecodeOptions,
+) -> Result<ValidationResult> {
+    let result = validate_with(image_bytes, options)?;
+    let content = result.content.as_deref().unwrap_or_default();
+    expect::check_content(result.raw_bytes.as_deref(), content, expected, mode)?;
+    Ok(result)
 }
 
 /// Validate from a file path (convenience function)
@@ -326,6 +589,298 @@ mod tests {
         assert!(!meta.decoders_success.is_empty());
     }
 
+    #[test]
+    fn validate_includes_print_recommendation() {
+        let qr_bytes = create_test_qr();
+        let result = validate(&qr_bytes).unwrap();
+
+        let print = result.print.expect("print recommendation");
+        assert_eq!(print.modules, result.metadata.unwrap().modules);
+        assert!(print.min_printed_width_mm > 0.0);
+        assert!(print.max_scan_distance_mm >= print.scan_distance_mm);
+    }
+
+    #[test]
+    fn validate_includes_structure_health() {
+        let qr_bytes = create_test_qr();
+        let result = validate(&qr_bytes).unwrap();
+
+        let structure = result.structure.expect("structure health");
+        assert_eq!(structure.modules, result.metadata.unwrap().modules);
+        assert_eq!(structure.finders.len(), 3);
+        assert!(structure.score >= 90);
+    }
+
+    #[test]
+    fn validate_includes_module_fidelity() {
+        let qr_bytes = create_test_qr();
+        let result = validate(&qr_bytes).unwrap();
+
+        let fidelity = result.fidelity.expect("module fidelity");
+        assert_eq!(fidelity.flipped_modules, 0);
+        assert_eq!(fidelity.ec_budget_used, 0.0);
+    }
+
+    #[test]
+    fn metadata_reports_ec_usage() {
+        let qr_bytes = create_test_qr();
+        let meta = decode_only(&qr_bytes).unwrap().metadata.unwrap();
+
+        assert_eq!(meta.ec_used_ratio, Some(0.0));
+        let usage = meta.ec_usage.expect("ec usage");
+        assert_eq!(usage.codewords_corrected, 0);
+        assert!(usage.codewords_capacity > 0);
+        assert!(usage.blocks >= 1);
+    }
+
+    #[test]
+    fn validate_parses_payload() {
+        let result = validate(&create_test_qr()).unwrap();
+        let payload = result.payload.expect("payload");
+        assert_eq!(
+            payload.payload,
+            Payload::Url {
+                url: "https://example.com".to_string()
+            }
+        );
+        assert!(payload.is_valid());
+    }
+
+    #[test]
+    fn validate_assesses_url_risk() {
+        let risk = validate(&create_test_qr()).unwrap().url_risk.expect("url risk");
+        assert_eq!(risk.level, RiskLevel::Safe);
+        assert_eq!(risk.host.as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn validate_micro_qr() {
+        use image::Luma;
+
+        let code = qrcode::QrCode::with_version(b"LOT 4711", qrcode::Version::Micro(3), qrcode::EcLevel::M).unwrap();
+        let img = image::DynamicImage::ImageLuma8(code.render::<Luma<u8>>().module_dimensions(8, 8).build());
+        let mut buf = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png).unwrap();
+
+        let result = validate(&buf).unwrap();
+        assert_eq!(result.content.as_deref(), Some("LOT 4711"));
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta.symbology, Symbology::MicroQr);
+        assert_eq!((meta.version, meta.modules), (3, 15));
+        assert_eq!(meta.error_correction, ErrorCorrectionLevel::M);
+        assert!(result.stress_results.blur_light);
+        assert!(result.print.is_none());
+    }
+
+    #[test]
+    fn decode_rmqr_natively() {
+        let img = image::DynamicImage::ImageLuma8(micro::tests::rmqr_image());
+        let mut buf = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png).unwrap();
+
+        let result = decode_only(&buf).unwrap();
+        assert_eq!(result.content, "ABCDEFG");
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta.symbology, Symbology::RectangularMicroQr);
+        assert_eq!((meta.version, meta.modules), (1, 43));
+    }
+
+    /// PNG of an rxing-encoded symbol, `scale` px per module plus a 4-module margin
+    fn create_test_symbol(content: &str, format: rxing::BarcodeFormat, scale: u32) -> Vec<u8> {
+        create_test_symbol_with(content, format, scale, &rxing::EncodeHints::default())
+    }
+
+    fn create_test_symbol_with(
+        content: &str,
+        format: rxing::BarcodeFormat,
+        scale: u32,
+        hints: &rxing::EncodeHints,
+    ) -> Vec<u8> {
+        use rxing::Writer;
+
+        let bits = rxing::MultiFormatWriter.encode_with_hints(content, &format, 0, 0, hints).unwrap();
+        let (w, h) = (bits.getWidth(), bits.getHeight());
+        let margin = 4 * scale;
+        let img = image::GrayImage::from_fn(w * scale + 2 * margin, h * scale + 2 * margin, |x, y| {
+            let inside = x >= margin && y >= margin && x < margin + w * scale && y < margin + h * scale;
+            let dark = inside && bits.get((x - margin) / scale, (y - margin) / scale);
+            Luma([if dark { 0 } else { 255 }])
+        });
+
+        let mut buf = Vec::new();
+        DynamicImage::ImageLuma8(img)
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn other_symbologies_are_opt_in() {
+        let data_matrix = create_test_symbol("LOT 4711", rxing::BarcodeFormat::DATA_MATRIX, 8);
+        assert!(decode_only(&data_matrix).is_err());
+
+        let options = DecodeOptions::with_formats(&Symbology::ALL);
+        let result = decode_only_with(&data_matrix, &options).unwrap();
+        assert_eq!(result.content, "LOT 4711");
+        assert_eq!(result.metadata.unwrap().symbology, Symbology::DataMatrix);
+
+        // QR codes still decode when other formats are enabled
+        let result = decode_only_with(&create_test_qr(), &options).unwrap();
+        assert_eq!(result.metadata.unwrap().symbology, Symbology::Qr);
+    }
+
+    #[test]
+    fn validate_scores_aztec() {
+        let aztec = create_test_symbol("https://example.com/a", rxing::BarcodeFormat::AZTEC, 6);
+        let options = DecodeOptions::with_formats(&[Symbology::Aztec]);
+
+        let result = validate_with(&aztec, &options).unwrap();
+        assert_eq!(result.content.as_deref(), Some("https://example.com/a"));
+        assert_eq!(result.metadata.as_ref().unwrap().symbology, Symbology::Aztec);
+        assert!(result.stress_results.original);
+        assert!(result.score > 0);
+        assert!(result.structure.is_none());
+        assert!(result.print.is_none());
+    }
+
+    #[test]
+    fn validates_raw_pixel_buffers() {
+        let img = image::load_from_memory(&create_test_qr()).unwrap().to_rgba8();
+        let (width, height) = img.dimensions();
+        // BGRA rows padded to a 64-byte boundary, as camera frames often are
+        let stride = (width as usize * 4).next_multiple_of(64);
+        let mut data = vec![0u8; stride * height as usize];
+        for (y, row) in img.rows().enumerate() {
+            for (x, px) in row.enumerate() {
+                let [r, g, b, a] = px.0;
+                data[y * stride + x * 4..][..4].copy_from_slice(&[b, g, r, a]);
+            }
+        }
+        let pixels = PixelBuffer { data: &data, width, height, stride, format: PixelFormat::Bgra8 };
+        let options = DecodeOptions::default();
+
+        let result = validate_pixels(&pixels, &options).unwrap();
+        assert_eq!(result.content.as_deref(), Some("https://example.com"));
+        assert_eq!(result.score, validate(&create_test_qr()).unwrap().score);
+        assert_eq!(decode_only_pixels(&pixels, &options).unwrap().content, "https://example.com");
+
+        let gray = image::load_from_memory(&create_test_qr()).unwrap().to_luma8();
+        let pixels = PixelBuffer::new(gray.as_raw(), width, height, PixelFormat::Luma8);
+        assert!(validate_fast_pixels(&pixels, &options).unwrap().decodable);
+
+        let short = PixelBuffer { height: height + 1, ..pixels };
+        assert!(matches!(decode_only_pixels(&short, &options), Err(QraiError::BufferMismatch { .. })));
+    }
+
+    #[test]
+    fn every_entry_point_applies_resource_limits() {
+        let qr = create_test_qr();
+        let options = DecodeOptions {
+            limits: ResourceLimits {
+                max_width: 50,
+                max_height: 50,
+                ..ResourceLimits::default()
+            },
+            ..DecodeOptions::default()
+        };
+        let too_large = |r: Result<()>| matches!(r, Err(QraiError::DimensionsTooLarge { max_width: 50, .. }));
+
+        assert!(too_large(validate_with(&qr, &options).map(drop)));
+        assert!(too_large(validate_fast_with(&qr, &options).map(drop)));
+        assert!(too_large(decode_only_with(&qr, &options).map(drop)));
+        assert!(too_large(decode_consensus_with(&qr, &options.limits).map(drop)));
+        assert!(too_large(extract_grid_with(&qr, &options.limits).map(drop)));
+        assert!(too_large(decode_structured_append_with(&[&qr], &options.limits).map(drop)));
+
+        let rgba = image::load_from_memory(&qr).unwrap().to_rgba8();
+        let pixels = PixelBuffer::new(rgba.as_raw(), rgba.width(), rgba.height(), PixelFormat::Rgba8);
+        assert!(too_large(decode_only_pixels(&pixels, &options).map(drop)));
+
+        assert!(decode_only(&qr).is_ok());
+    }
+
+    #[test]
+    fn validate_parses_gs1_symbols() {
+        let hints = rxing::EncodeHints::default().with(rxing::EncodeHintValue::Gs1Format(true));
+        let gs1 = create_test_symbol_with(
+            "010950600013435217250731\u{1D}10A1B2",
+            rxing::BarcodeFormat::QR_CODE,
+            8,
+            &hints,
+        );
+
+        let result = validate(&gs1).unwrap();
+        assert_eq!(result.metadata.as_ref().unwrap().fnc1, Some(Fnc1Mode::Gs1));
+        let parsed = result.payload.unwrap();
+        assert!(parsed.is_valid(), "{:?}", parsed.errors);
+        let Payload::Gs1 { gtin, expiry, batch, .. } = parsed.payload else {
+            panic!("expected GS1 payload, got {:?}", parsed.payload);
+        };
+        assert_eq!(gtin.as_deref(), Some("09506000134352"));
+        assert_eq!(expiry.as_deref(), Some("250731"));
+        assert_eq!(batch.as_deref(), Some("A1B2"));
+    }
+
+    #[test]
+    fn validate_expecting_checks_content() {
+        let qr_bytes = create_test_qr();
+        assert!(validate_expecting(&qr_bytes, "https://example.com").is_ok());
+
+        let err = validate_expecting(&qr_bytes, "https://example.org").unwrap_err();
+        assert!(matches!(err, QraiError::ContentMismatch { .. }), "{err:?}");
+
+        assert!(validate_expecting(&qr_bytes, "https://EXAMPLE.com:443/").is_err());
+        let options = DecodeOptions::default();
+        assert!(validate_expecting_with(&qr_bytes, b"https://EXAMPLE.com:443/", MatchMode::Url, &options).is_ok());
+
+        // Decode options apply: a Data Matrix-only scan finds no QR code
+        let data_matrix = DecodeOptions::with_formats(&[Symbology::DataMatrix]);
+        let err = validate_expecting_with(&qr_bytes, b"https://example.com", MatchMode::Exact, &data_matrix).unwrap_err();
+        assert!(matches!(err, QraiError::DecodeFailed), "{err:?}");
+    }
+
+    #[test]
+    fn decode_only_keeps_binary_payload() {
+        use image::Luma;
+
+        let payload = [0x00, 0xFF, 0xC3, 0x28, 0x80, 0x7F];
+        let code = qrcode::QrCode::new(payload).unwrap();
+        let img = image::DynamicImage::ImageLuma8(code.render::<Luma<u8>>().build());
+        let mut buf = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+
+        let result = decode_only(&buf).unwrap();
+        assert_eq!(result.raw_bytes.as_deref(), Some(&payload[..]));
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta.charset, Some(Charset::Iso8859_1));
+        assert_eq!(meta.segments.len(), 1);
+        assert_eq!(meta.segments[0].mode, SegmentMode::Byte);
+        assert_eq!(meta.segments[0].byte_len, payload.len() as u32);
+    }
+
+    #[test]
+    fn metadata_reports_segments_and_charset() {
+        let result = decode_only(&create_test_qr()).unwrap();
+        assert_eq!(result.raw_bytes.as_deref(), Some(&b"https://example.com"[..]));
+
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta.eci, None);
+        assert_eq!(meta.charset, Some(Charset::Ascii));
+        let total: u32 = meta.segments.iter().map(|s| s.byte_len).sum();
+        assert_eq!(total, 19);
+    }
+
+    #[test]
+    fn extract_grid_matches_metadata() {
+        let qr_bytes = create_test_qr();
+        let grid = extract_grid(&qr_bytes).unwrap();
+        let meta = decode_only(&qr_bytes).unwrap().metadata.unwrap();
+
+        assert_eq!(grid.size, meta.modules as usize);
+        assert_eq!(grid.decode().unwrap(), "https://example.com");
+    }
+
     #[test]
     fn validate_fast_is_faster() {
         let qr_bytes = create_test_qr();
@@ -0,0 +1,197 @@
+//! Resource limits for untrusted images
+//!
+//! A small PNG can declare a 100 000 x 100 000 canvas and expand to tens of
+//! gigabytes once decoded (a decompression bomb). Every entry point loads
+//! images through [`ResourceLimits::load_image`]. It checks the input size,
+//! reads the dimensions from the header, rejects oversized images before any
+//! pixel is decoded, and then decodes under an `image::Limits` allocation cap.
+
+use crate::error::{QraiError, Result};
+use crate::types::PixelBuffer;
+use image::{DynamicImage, ImageError, ImageReader};
+use std::io::Cursor;
+
+/// Caps on the images accepted for decoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Widest image accepted, in pixels
+    pub max_width: u32,
+    /// Tallest image accepted, in pixels
+    pub max_height: u32,
+    /// Largest width x height accepted
+    pub max_pixels: u64,
+    /// Largest encoded (PNG, JPEG, ...) or raw pixel input, in bytes
+    pub max_input_bytes: usize,
+    /// Most memory the image decoder may allocate, in bytes
+    pub max_decode_memory: u64,
+}
+
+impl Default for ResourceLimits {
+    /// 10 000 px per side, 100 MP, 64 MiB of input and 512 MiB of decoder memory
+    fn default() -> Self {
+        Self {
+            max_width: 10_000,
+            max_height: 10_000,
+            max_pixels: 100_000_000,
+            max_input_bytes: 64 * 1024 * 1024,
+            max_decode_memory: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// Decode an encoded image, rejecting it before pixel decoding if it is too large
+    ///
+    /// # Errors
+    /// * `QraiError::InputTooLarge` if `bytes` exceeds `max_input_bytes`
+    /// * `QraiError::DimensionsTooLarge` / `QraiError::TooManyPixels` if the
+    ///   header declares an image over the limits
+    /// * `QraiError::LimitExceeded` if decoding needs more than `max_decode_memory`
+    /// * `QraiError::ImageLoad` if the image cannot be parsed
+    pub fn load_image(&self, bytes: &[u8]) -> Result<DynamicImage> {
+        self.check_input_len(bytes.len())?;
+        let reader = || {
+            ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .map_err(|e| QraiError::ImageLoad(e.to_string()))
+        };
+
+        // Header only: no pixel data is decoded yet
+        let (width, height) = reader()?.into_dimensions().map_err(load_error)?;
+        self.check_dimensions(width, height)?;
+
+        let mut reader = reader()?;
+        reader.limits(self.image_limits());
+        reader.decode().map_err(load_error)
+    }
+
+    /// Check a raw pixel buffer against the limits
+    pub fn check_pixels(&self, pixels: &PixelBuffer) -> Result<()> {
+        self.check_input_len(pixels.data.len())?;
+        self.check_dimensions(pixels.width, pixels.height)?;
+        // Packed copy made while converting to an image
+        let packed = u64::from(pixels.width) * u64::from(pixels.height) * pixels.format.bytes_per_pixel() as u64;
+        if packed > self.max_decode_memory {
+            return Err(QraiError::LimitExceeded(format!(
+                "{packed} bytes of pixels exceeds the {} byte memory limit",
+                self.max_decode_memory
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check image dimensions against the size and pixel-count limits
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<()> {
+        if width > self.max_width || height > self.max_height {
+            return Err(QraiError::DimensionsTooLarge {
+                width,
+                height,
+                max_width: self.max_width,
+                max_height: self.max_height,
+            });
+        }
+        let pixels = u64::from(width) * u64::from(height);
+        if pixels > self.max_pixels {
+            return Err(QraiError::TooManyPixels {
+                pixels,
+                max_pixels: self.max_pixels,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_input_len(&self, len: usize) -> Result<()> {
+        if len > self.max_input_bytes {
+            return Err(QraiError::InputTooLarge {
+                bytes: len,
+                max_bytes: self.max_input_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// The same caps in the form the `image` decoders enforce
+    fn image_limits(&self) -> image::Limits {
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(self.max_width);
+        limits.max_image_height = Some(self.max_height);
+        limits.max_alloc = Some(self.max_decode_memory);
+        limits
+    }
+}
+
+fn load_error(error: ImageError) -> QraiError {
+    match error {
+        ImageError::Limits(_) => QraiError::LimitExceeded(error.to_string()),
+        _ => QraiError::ImageLoad(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PixelFormat;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        DynamicImage::new_luma8(width, height)
+            .write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    /// PNG whose header declares `width` x `height` but carries no pixel data
+    fn bomb_header(width: u32, height: u32) -> Vec<u8> {
+        let mut png = png(1, 1);
+        // IHDR data starts after the 8-byte signature and 8-byte chunk header
+        png[16..20].copy_from_slice(&width.to_be_bytes());
+        png[20..24].copy_from_slice(&height.to_be_bytes());
+        // Chunk CRC covers the type and data (bytes 12..29)
+        let crc = png[12..29].iter().fold(!0u32, |crc, &byte| {
+            (0..8).fold(crc ^ u32::from(byte), |c, _| if c & 1 == 1 { (c >> 1) ^ 0xEDB8_8320 } else { c >> 1 })
+        });
+        png[29..33].copy_from_slice(&(!crc).to_be_bytes());
+        png
+    }
+
+    #[test]
+    fn rejects_oversized_headers_before_decoding() {
+        let limits = ResourceLimits::default();
+        let err = limits.load_image(&bomb_header(60_000, 60_000)).unwrap_err();
+        assert!(matches!(err, QraiError::DimensionsTooLarge { width: 60_000, .. }), "{err:?}");
+
+        let limits = ResourceLimits {
+            max_pixels: 1_000,
+            ..ResourceLimits::default()
+        };
+        let err = limits.load_image(&png(40, 40)).unwrap_err();
+        assert!(matches!(err, QraiError::TooManyPixels { pixels: 1_600, max_pixels: 1_000 }), "{err:?}");
+        assert!(limits.load_image(&png(30, 30)).is_ok());
+    }
+
+    #[test]
+    fn caps_input_size_and_decoder_memory() {
+        let small = ResourceLimits {
+            max_input_bytes: 16,
+            ..ResourceLimits::default()
+        };
+        assert!(matches!(small.load_image(&png(8, 8)), Err(QraiError::InputTooLarge { max_bytes: 16, .. })));
+
+        let tight = ResourceLimits {
+            max_decode_memory: 1_000,
+            ..ResourceLimits::default()
+        };
+        assert!(matches!(tight.load_image(&png(200, 200)), Err(QraiError::LimitExceeded(_))));
+
+        let data = vec![0u8; 200 * 200 * 4];
+        let pixels = PixelBuffer::new(&data, 200, 200, PixelFormat::Rgba8);
+        assert!(matches!(tight.check_pixels(&pixels), Err(QraiError::LimitExceeded(_))));
+        assert!(ResourceLimits::default().check_pixels(&pixels).is_ok());
+    }
+
+    #[test]
+    fn garbage_is_a_load_error() {
+        let err = ResourceLimits::default().load_image(b"not an image").unwrap_err();
+        assert!(matches!(err, QraiError::ImageLoad(_)));
+    }
+}
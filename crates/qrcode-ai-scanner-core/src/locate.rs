@@ -0,0 +1,437 @@
+//! Symbol location and module sampling
+//!
+//! Finds where a QR symbol sits in an image (via rqrr's grid detection) and
+//! maps module coordinates back to image pixels, so analyses can sample the
+//! original artwork at every module.
+
+use crate::decoder::apply_otsu_threshold;
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::sync::Once;
+
+/// Projective mapping from the unit square onto an image quadrilateral
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Perspective {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+    g: f32,
+    h: f32,
+}
+
+impl Perspective {
+    /// Build the mapping for corners ordered top-left, top-right, bottom-right, bottom-left
+    fn from_quad(quad: [(f32, f32); 4]) -> Option<Self> {
+        let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = quad;
+        let dx3 = x0 - x1 + x2 - x3;
+        let dy3 = y0 - y1 + y2 - y3;
+
+        if dx3.abs() < 1e-6 && dy3.abs() < 1e-6 {
+            // Affine case
+            return Some(Self {
+                a: x1 - x0,
+                b: x3 - x0,
+                c: x0,
+                d: y1 - y0,
+                e: y3 - y0,
+                f: y0,
+                g: 0.0,
+                h: 0.0,
+            });
+        }
+
+        let dx1 = x1 - x2;
+        let dx2 = x3 - x2;
+        let dy1 = y1 - y2;
+        let dy2 = y3 - y2;
+        let den = dx1 * dy2 - dx2 * dy1;
+        if den.abs() < 1e-9 {
+            return None;
+        }
+        let g = (dx3 * dy2 - dx2 * dy3) / den;
+        let h = (dx1 * dy3 - dx3 * dy1) / den;
+
+        Some(Self {
+            a: x1 - x0 + g * x1,
+            b: x3 - x0 + h * x3,
+            c: x0,
+            d: y1 - y0 + g * y1,
+            e: y3 - y0 + h * y3,
+            f: y0,
+            g,
+            h,
+        })
+    }
+
+    /// Mapping taking the quadrilateral `from` onto `to`, corner by corner
+    fn between(from: [(f32, f32); 4], to: [(f32, f32); 4]) -> Option<Self> {
+        Self::from_matrix(mul(Self::from_quad(to)?.matrix(), adjugate(Self::from_quad(from)?.matrix())))
+    }
+
+    fn matrix(&self) -> [[f32; 3]; 3] {
+        [[self.a, self.b, self.c], [self.d, self.e, self.f], [self.g, self.h, 1.0]]
+    }
+
+    /// Mapping for a 3x3 matrix, scaled so its last entry is 1
+    fn from_matrix(m: [[f32; 3]; 3]) -> Option<Self> {
+        let s = m[2][2];
+        if s.abs() < 1e-9 {
+            return None;
+        }
+        Some(Self {
+            a: m[0][0] / s,
+            b: m[0][1] / s,
+            c: m[0][2] / s,
+            d: m[1][0] / s,
+            e: m[1][1] / s,
+            f: m[1][2] / s,
+            g: m[2][0] / s,
+            h: m[2][1] / s,
+        })
+    }
+
+    fn map(&self, u: f32, v: f32) -> (f32, f32) {
+        let w = self.g * u + self.h * v + 1.0;
+        (
+            (self.a * u + self.b * v + self.c) / w,
+            (self.d * u + self.e * v + self.f) / w,
+        )
+    }
+}
+
+fn mul(l: [[f32; 3]; 3], r: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| l[i][k] * r[k][j]).sum()))
+}
+
+/// Adjugate of a 3x3 matrix: its inverse up to scale
+fn adjugate(m: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let cofactor = |r: usize, c: usize| {
+        let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+        let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    std::array::from_fn(|i| std::array::from_fn(|j| cofactor(j, i)))
+}
+
+/// Whether `size` modules per side is a standard QR symbol (versions 1-40)
+pub fn is_qr_size(size: usize) -> bool {
+    (21..=177).contains(&size) && size % 4 == 1
+}
+
+/// Where a QR symbol sits in an image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SymbolLocation {
+    /// Modules per side
+    pub size: usize,
+    /// Outer corners of the module area in image pixels
+    /// (top-left, top-right, bottom-right, bottom-left)
+    pub corners: [(f32, f32); 4],
+}
+
+impl SymbolLocation {
+    /// Location from the outer corners of the `size` x `size` module area
+    pub fn new(size: usize, corners: [(f32, f32); 4]) -> Option<Self> {
+        Perspective::from_quad(corners)?;
+        Some(Self { size, corners })
+    }
+
+    /// Location from rqrr grid bounds, which span `size + 1` modules
+    fn from_rqrr_bounds(size: usize, bounds: &[rqrr::Point; 4]) -> Option<Self> {
+        let quad = bounds.map(|p| (p.x as f32, p.y as f32));
+        let perspective = Perspective::from_quad(quad)?;
+        let span = (size + 1) as f32;
+        let s = size as f32 / span;
+        Self::new(
+            size,
+            [
+                perspective.map(0.0, 0.0),
+                perspective.map(s, 0.0),
+                perspective.map(s, s),
+                perspective.map(0.0, s),
+            ],
+        )
+    }
+
+    fn perspective(&self) -> Perspective {
+        // Corners were validated on construction
+        Perspective::from_quad(self.corners).unwrap_or(Perspective {
+            a: 0.0,
+            b: 0.0,
+            c: 0.0,
+            d: 0.0,
+            e: 0.0,
+            f: 0.0,
+            g: 0.0,
+            h: 0.0,
+        })
+    }
+
+    /// Map module coordinates (may be fractional or outside the symbol) to image pixels
+    pub fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        let n = self.size as f32;
+        self.perspective().map(x / n, y / n)
+    }
+
+    /// Image position of the centre of module (`x`, `y`)
+    pub fn module_center(&self, x: usize, y: usize) -> (f32, f32) {
+        self.map(x as f32 + 0.5, y as f32 + 0.5)
+    }
+
+    /// Average module pitch in pixels
+    pub fn module_pitch(&self) -> f32 {
+        let dist = |a: (f32, f32), b: (f32, f32)| ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+        let [tl, tr, br, bl] = self.corners;
+        let perimeter = dist(tl, tr) + dist(tr, br) + dist(br, bl) + dist(bl, tl);
+        perimeter / (4.0 * self.size as f32)
+    }
+}
+
+/// Locate the first QR symbol in an image
+///
+/// Tries rqrr on the image as-is, then on an Otsu-binarised copy for
+/// low-contrast art, then rxing's finder pattern detector.
+pub fn locate_symbol(img: &DynamicImage) -> Option<SymbolLocation> {
+    let gray = img.to_luma8();
+    locate_in_luma(&gray)
+        .or_else(|| apply_otsu_threshold(img).and_then(|otsu| locate_in_luma(&otsu.to_luma8())))
+        .or_else(|| locate_with_rxing(&gray))
+}
+
+/// Locate the first QR symbol in a grayscale image using rqrr's detector
+pub fn locate_in_luma(gray: &GrayImage) -> Option<SymbolLocation> {
+    catch_rqrr_panic(|| {
+        let mut prepared = rqrr::PreparedImage::prepare(gray.clone());
+        let grids = prepared.detect_grids();
+        let grid = grids.first()?;
+        SymbolLocation::from_rqrr_bounds(rqrr::BitGrid::size(&grid.grid), &grid.bounds)
+    })
+    .flatten()
+}
+
+thread_local! {
+    /// Set while rqrr runs on this thread, so the panic hook stays quiet
+    static IN_RQRR: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run rqrr work, turning a panic into `None`
+///
+/// rqrr asserts on some degenerate inputs, such as a timing pattern it
+/// measures as zero modules in symbols drawn at one pixel per module. The
+/// first call wraps the process panic hook, once, so panics on a thread
+/// inside rqrr are not printed; every other panic goes to the previous hook.
+pub(crate) fn catch_rqrr_panic<T>(work: impl FnOnce() -> T + std::panic::UnwindSafe) -> Option<T> {
+    static QUIET_HOOK: Once = Once::new();
+    QUIET_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !IN_RQRR.with(Cell::get) {
+                previous(info);
+            }
+        }));
+    });
+
+    let outer = IN_RQRR.with(|flag| flag.replace(true));
+    let result = std::panic::catch_unwind(work).ok();
+    IN_RQRR.with(|flag| flag.set(outer));
+    result
+}
+
+/// Locate a symbol from rxing's finder and alignment pattern centres
+///
+/// With an alignment pattern the location follows perspective; version 1
+/// symbols have none, so their location is affine from the three finders.
+pub fn locate_with_rxing(gray: &GrayImage) -> Option<SymbolLocation> {
+    use rxing::common::{DetectorRXingResult, HybridBinarizer};
+
+    let (width, height) = gray.dimensions();
+    let source = rxing::Luma8LuminanceSource::new(gray.as_raw().clone(), width, height);
+    let bitmap = rxing::BinaryBitmap::new(HybridBinarizer::new(source));
+    let detected = rxing::qrcode::detector::Detector::new(bitmap.get_black_matrix())
+        .detect()
+        .ok()?;
+
+    let size = detected.getBits().getWidth() as usize;
+    let points = detected.getPoints();
+    if size < 21 || points.len() < 3 {
+        return None;
+    }
+    // rxing orders finder centres bottom-left, top-left, top-right, then the alignment pattern
+    let [bl, tl, tr] = [points[0], points[1], points[2]].map(|p| (p.x, p.y));
+    let n = size as f32;
+    let far = n - 3.5;
+    // Module centres of the finders and of the bottom-right alignment pattern
+    let (corner, image_corner) = match points.get(3) {
+        Some(alignment) => ((far - 3.0, far - 3.0), (alignment.x, alignment.y)),
+        None => ((far, far), (tr.0 + bl.0 - tl.0, tr.1 + bl.1 - tl.1)),
+    };
+    let perspective = Perspective::between([(3.5, 3.5), (far, 3.5), corner, (3.5, far)], [tl, tr, image_corner, bl])?;
+
+    SymbolLocation::new(
+        size,
+        [perspective.map(0.0, 0.0), perspective.map(n, 0.0), perspective.map(n, n), perspective.map(0.0, n)],
+    )
+}
+
+/// Mean luma in a square window of the given radius around an image position
+pub fn sample_luma(gray: &GrayImage, x: f32, y: f32, radius: f32) -> f32 {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+    let clamp = |v: f32, max: u32| v.round().clamp(0.0, (max - 1) as f32) as u32;
+    let r = radius.max(0.0);
+    let (x0, x1) = (clamp(x - r, width), clamp(x + r, width));
+    let (y0, y1) = (clamp(y - r, height), clamp(y + r, height));
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for py in y0..=y1 {
+        for px in x0..=x1 {
+            sum += gray.get_pixel(px, py).0[0] as u64;
+            count += 1;
+        }
+    }
+    sum as f32 / count.max(1) as f32
+}
+
+/// Mean luma around every module centre, row-major
+///
+/// The window covers the central ~40% of each module so stylized edges and
+/// anti-aliasing don't leak into the sample.
+pub fn sample_modules(gray: &GrayImage, location: &SymbolLocation) -> Vec<f32> {
+    let radius = location.module_pitch() * 0.2;
+    let mut samples = Vec::with_capacity(location.size * location.size);
+    for y in 0..location.size {
+        for x in 0..location.size {
+            let (px, py) = location.module_center(x, y);
+            samples.push(sample_luma(gray, px, py, radius));
+        }
+    }
+    samples
+}
+
+/// Otsu threshold over arbitrary luma samples
+pub fn otsu_level(samples: &[f32]) -> f32 {
+    let mut histogram = [0u32; 256];
+    for &v in samples {
+        histogram[v.clamp(0.0, 255.0) as usize] += 1;
+    }
+
+    let total = samples.len() as f64;
+    let sum: f64 = histogram.iter().enumerate().map(|(i, &c)| i as f64 * c as f64).sum();
+    let mut sum_b = 0.0;
+    let mut w_b = 0.0;
+    let mut best = 0.0;
+    let mut level = 127.0;
+
+    for (i, &count) in histogram.iter().enumerate() {
+        w_b += count as f64;
+        if w_b == 0.0 {
+            continue;
+        }
+        let w_f = total - w_b;
+        if w_f == 0.0 {
+            break;
+        }
+        sum_b += i as f64 * count as f64;
+        let m_b = sum_b / w_b;
+        let m_f = (sum - sum_b) / w_f;
+        let variance = w_b * w_f * (m_b - m_f) * (m_b - m_f);
+        if variance > best {
+            best = variance;
+            level = i as f32 + 0.5;
+        }
+    }
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn rqrr_panics_are_caught_quietly() {
+        assert_eq!(catch_rqrr_panic(|| -> u8 { panic!("index out of bounds") }), None);
+        assert_eq!(catch_rqrr_panic(|| catch_rqrr_panic(|| 7)), Some(Some(7)));
+        assert!(!IN_RQRR.with(Cell::get));
+    }
+
+    fn render_qr(content: &[u8]) -> (DynamicImage, usize) {
+        let code = qrcode::QrCode::new(content).unwrap();
+        let size = code.width();
+        let img = code.render::<Luma<u8>>().module_dimensions(6, 6).build();
+        (DynamicImage::ImageLuma8(img), size)
+    }
+
+    #[test]
+    fn locates_generated_symbol() {
+        let (img, size) = render_qr(b"https://example.com");
+        let location = locate_symbol(&img).expect("symbol located");
+
+        assert_eq!(location.size, size);
+        // Quiet zone is 4 modules of 6px
+        let (x, y) = location.corners[0];
+        assert!((x - 24.0).abs() < 3.0 && (y - 24.0).abs() < 3.0, "top-left at {x},{y}");
+        assert!((location.module_pitch() - 6.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn sampled_modules_match_the_encoded_matrix() {
+        let code = qrcode::QrCode::new(b"sampling").unwrap();
+        let img = DynamicImage::ImageLuma8(code.render::<Luma<u8>>().module_dimensions(5, 5).build());
+        let location = locate_symbol(&img).unwrap();
+        let gray = img.to_luma8();
+        let samples = sample_modules(&gray, &location);
+        let level = otsu_level(&samples);
+
+        let colors = code.to_colors();
+        let mismatches = samples
+            .iter()
+            .zip(colors.iter())
+            .filter(|(&s, &c)| (s < level) != (c == qrcode::Color::Dark))
+            .count();
+        assert_eq!(mismatches, 0);
+    }
+
+    #[test]
+    fn rxing_fallback_agrees_with_rqrr() {
+        let (img, size) = render_qr(b"https://example.com/fallback");
+        let gray = img.to_luma8();
+        let rqrr = locate_in_luma(&gray).unwrap();
+        let rxing = locate_with_rxing(&gray).unwrap();
+
+        assert_eq!(rxing.size, size);
+        for (a, b) in rqrr.corners.iter().zip(rxing.corners.iter()) {
+            assert!((a.0 - b.0).abs() < 3.0 && (a.1 - b.1).abs() < 3.0, "{a:?} vs {b:?}");
+        }
+    }
+
+    #[test]
+    fn perspective_between_quads_maps_corner_to_corner() {
+        let from = [(3.5, 3.5), (21.5, 3.5), (18.5, 18.5), (3.5, 21.5)];
+        let to = [(40.0, 30.0), (150.0, 42.0), (128.0, 131.0), (35.0, 160.0)];
+        let perspective = Perspective::between(from, to).unwrap();
+        for (&(u, v), &(x, y)) in from.iter().zip(to.iter()) {
+            let (px, py) = perspective.map(u, v);
+            assert!((px - x).abs() < 0.01 && (py - y).abs() < 0.01, "({u}, {v}) -> ({px}, {py})");
+        }
+    }
+
+    #[test]
+    fn blank_image_has_no_symbol() {
+        let blank = DynamicImage::new_luma8(100, 100);
+        assert!(locate_symbol(&blank).is_none());
+    }
+
+    #[test]
+    fn otsu_level_splits_bimodal_samples() {
+        let samples = [10.0, 12.0, 15.0, 240.0, 235.0, 250.0];
+        let level = otsu_level(&samples);
+        assert!(level > 15.0 && level < 235.0);
+    }
+}
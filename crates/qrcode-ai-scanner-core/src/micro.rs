@@ -0,0 +1,240 @@
+//! Micro QR and rMQR support
+//!
+//! rxing decodes both symbologies, but its finder-pattern detector often
+//! misses rMQR on otherwise clean labels and it never reports a version or
+//! size for either. This module adds the symbol size tables and a native
+//! locator for pure, axis-aligned label images: it finds the symbol's dark
+//! bounding box, takes the module pitch from the 7-module finder pattern,
+//! samples every module and hands the bit matrix to rxing's bitstream
+//! decoder.
+
+use crate::segments::PayloadEncoding;
+use crate::types::{ErrorCorrectionLevel, Symbology};
+use image::GrayImage;
+use rxing::common::BitMatrix;
+
+/// rMQR sizes as (rows, columns), indexed by version - 1 (R7x43 .. R17x139)
+pub const RMQR_SIZES: [(u8, u8); 32] = [
+    (7, 43), (7, 59), (7, 77), (7, 99), (7, 139),
+    (9, 43), (9, 59), (9, 77), (9, 99), (9, 139),
+    (11, 27), (11, 43), (11, 59), (11, 77), (11, 99), (11, 139),
+    (13, 27), (13, 43), (13, 59), (13, 77), (13, 99), (13, 139),
+    (15, 43), (15, 59), (15, 77), (15, 99), (15, 139),
+    (17, 43), (17, 59), (17, 77), (17, 99), (17, 139),
+];
+
+/// Modules across the finder pattern, the same in every QR family
+const FINDER_MODULES: f32 = 7.0;
+
+/// Symbol size as (columns, rows) for a version of the given symbology
+pub fn symbol_size(symbology: Symbology, version: u8) -> Option<(u8, u8)> {
+    match symbology {
+        Symbology::Qr => (1..=40).contains(&version).then(|| {
+            let side = 17 + 4 * version;
+            (side, side)
+        }),
+        Symbology::MicroQr => (1..=4).contains(&version).then(|| {
+            let side = 9 + 2 * version;
+            (side, side)
+        }),
+        Symbology::RectangularMicroQr => {
+            let &(rows, columns) = RMQR_SIZES.get(usize::from(version).checked_sub(1)?)?;
+            Some((columns, rows))
+        }
+        Symbology::DataMatrix | Symbology::Aztec | Symbology::Pdf417 => None,
+    }
+}
+
+/// Symbology and version of a Micro QR or rMQR symbol with the given size
+pub fn identify_size(columns: u8, rows: u8) -> Option<(Symbology, u8)> {
+    if columns == rows && (11..=17).contains(&columns) && columns % 2 == 1 {
+        return Some((Symbology::MicroQr, (columns - 9) / 2));
+    }
+    let index = RMQR_SIZES.iter().position(|&size| size == (rows, columns))?;
+    Some((Symbology::RectangularMicroQr, index as u8 + 1))
+}
+
+/// Micro QR or rMQR symbol read by the native locator
+#[derive(Debug, Clone)]
+pub struct MicroRead {
+    pub symbology: Symbology,
+    pub version: u8,
+    pub columns: u8,
+    pub rows: u8,
+    pub content: String,
+    pub error_correction: Option<ErrorCorrectionLevel>,
+    pub encoding: PayloadEncoding,
+}
+
+/// Locate, sample and decode a single pure Micro QR or rMQR symbol
+///
+/// Expects an unrotated symbol on a light background, as on printed labels
+/// and generated artwork. Returns `None` for standard QR codes.
+pub fn read_pure(gray: &GrayImage) -> Option<MicroRead> {
+    let bits = sample_pure(gray)?;
+    let (columns, rows) = (u8::try_from(bits.getWidth()).ok()?, u8::try_from(bits.getHeight()).ok()?);
+    let (symbology, version) = identify_size(columns, rows)?;
+
+    let decoded = rxing::qrcode::cpp_port::decoder::Decode(&bits).ok()?;
+    if decoded.error().is_some() {
+        return None;
+    }
+    Some(MicroRead {
+        symbology,
+        version,
+        columns,
+        rows,
+        content: decoded.text(),
+        error_correction: parse_ec_level(decoded.ecLevel()),
+        encoding: PayloadEncoding::from_bytes(decoded.content().bytes().to_vec()),
+    })
+}
+
+/// Our EC level from rxing's single-letter form
+pub(crate) fn parse_ec_level(level: &str) -> Option<ErrorCorrectionLevel> {
+    match level {
+        "L" => Some(ErrorCorrectionLevel::L),
+        "M" => Some(ErrorCorrectionLevel::M),
+        "Q" => Some(ErrorCorrectionLevel::Q),
+        "H" => Some(ErrorCorrectionLevel::H),
+        _ => None,
+    }
+}
+
+/// Sample the symbol's modules into a bit matrix (set = dark)
+fn sample_pure(gray: &GrayImage) -> Option<BitMatrix> {
+    let threshold = midpoint_threshold(gray)?;
+    let dark = |x: u32, y: u32| gray.get_pixel(x, y).0[0] < threshold;
+    let (left, top, right, bottom) = dark_bounds(gray, threshold)?;
+
+    // The top-left finder is 7 modules wide: measure its top edge, then
+    // re-measure through the middle of its first module row
+    let run = |y: u32| (left..=right).take_while(|&x| dark(x, y)).count() as f32;
+    let rough_pitch = run(top) / FINDER_MODULES;
+    if rough_pitch < 1.0 {
+        return None;
+    }
+    let pitch = run(top + (rough_pitch / 2.0) as u32) / FINDER_MODULES;
+    if pitch < 1.0 {
+        return None;
+    }
+
+    let width = (right - left + 1) as f32;
+    let height = (bottom - top + 1) as f32;
+    let columns = (width / pitch).round() as u32;
+    let rows = (height / pitch).round() as u32;
+    identify_size(u8::try_from(columns).ok()?, u8::try_from(rows).ok()?)?;
+
+    let (step_x, step_y) = (width / columns as f32, height / rows as f32);
+    let mut bits = BitMatrix::new(columns, rows).ok()?;
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = left as f32 + (column as f32 + 0.5) * step_x;
+            let y = top as f32 + (row as f32 + 0.5) * step_y;
+            if dark(x as u32, y as u32) {
+                bits.set(column, row);
+            }
+        }
+    }
+    Some(bits)
+}
+
+/// Threshold halfway between the darkest and lightest pixel
+fn midpoint_threshold(gray: &GrayImage) -> Option<u8> {
+    let (min, max) = gray
+        .pixels()
+        .fold((u8::MAX, u8::MIN), |(lo, hi), p| (lo.min(p.0[0]), hi.max(p.0[0])));
+    // Flat images have no symbol to find
+    (max.saturating_sub(min) >= 64).then(|| min + (max - min) / 2)
+}
+
+/// Bounding box (left, top, right, bottom) of all pixels darker than `threshold`
+fn dark_bounds(gray: &GrayImage, threshold: u8) -> Option<(u32, u32, u32, u32)> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, p) in gray.enumerate_pixels() {
+        if p.0[0] >= threshold {
+            continue;
+        }
+        bounds = Some(match bounds {
+            None => (x, y, x, y),
+            Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x), b.max(y)),
+        });
+    }
+    bounds
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// R7x43 symbol encoding "ABCDEFG" (from the rxing test suite)
+    const RMQR_R7X43: [&str; 7] = [
+        "XXXXXXX X X X X X X XXX X X X X X X X X XXX",
+        "X     X  X XXX  XXXXX XXX      X X XX   X X",
+        "X XXX X X XXX X X X XXXX XXXX X  X XXXXXXXX",
+        "X XXX X  XX    XXXXX   XXXXXX   X X   X   X",
+        "X XXX X   XX  XXX   XXXXXXX  X X  XX  X X X",
+        "X     X XXXXX XXX XXX XXXXX    XXXXXX X   X",
+        "XXXXXXX X X X X X X XXX X X X X X X X XXXXX",
+    ];
+
+    /// Render module rows at `scale` px per module with a 2-module quiet zone
+    pub(crate) fn render_rows(rows: &[&str], scale: u32) -> GrayImage {
+        let quiet = 2;
+        let (w, h) = (rows[0].len() as u32, rows.len() as u32);
+        GrayImage::from_fn((w + 2 * quiet) * scale, (h + 2 * quiet) * scale, |x, y| {
+            let (mx, my) = ((x / scale).checked_sub(quiet), (y / scale).checked_sub(quiet));
+            let dark = match (mx, my) {
+                (Some(mx), Some(my)) if mx < w && my < h => rows[my as usize].as_bytes()[mx as usize] == b'X',
+                _ => false,
+            };
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    pub(crate) fn rmqr_image() -> GrayImage {
+        render_rows(&RMQR_R7X43, 6)
+    }
+
+    #[test]
+    fn size_tables_round_trip() {
+        assert_eq!(symbol_size(Symbology::Qr, 1), Some((21, 21)));
+        assert_eq!(symbol_size(Symbology::MicroQr, 4), Some((17, 17)));
+        assert_eq!(symbol_size(Symbology::RectangularMicroQr, 1), Some((43, 7)));
+        assert_eq!(symbol_size(Symbology::RectangularMicroQr, 32), Some((139, 17)));
+        assert_eq!(symbol_size(Symbology::RectangularMicroQr, 33), None);
+
+        assert_eq!(identify_size(13, 13), Some((Symbology::MicroQr, 2)));
+        assert_eq!(identify_size(27, 11), Some((Symbology::RectangularMicroQr, 11)));
+        assert_eq!(identify_size(21, 21), None);
+    }
+
+    #[test]
+    fn reads_pure_rmqr() {
+        let read = read_pure(&rmqr_image()).unwrap();
+        assert_eq!(read.symbology, Symbology::RectangularMicroQr);
+        assert_eq!((read.version, read.columns, read.rows), (1, 43, 7));
+        assert_eq!(read.content, "ABCDEFG");
+        assert_eq!(read.error_correction, Some(ErrorCorrectionLevel::M));
+        assert_eq!(read.encoding.raw_bytes, b"ABCDEFG");
+    }
+
+    #[test]
+    fn reads_pure_micro_qr() {
+        let code = qrcode::QrCode::with_version(b"12345", qrcode::Version::Micro(2), qrcode::EcLevel::L).unwrap();
+        let img = code.render::<Luma<u8>>().module_dimensions(5, 5).build();
+
+        let read = read_pure(&img).unwrap();
+        assert_eq!(read.symbology, Symbology::MicroQr);
+        assert_eq!((read.version, read.columns), (2, 13));
+        assert_eq!(read.content, "12345");
+    }
+
+    #[test]
+    fn ignores_standard_qr_and_blank_images() {
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        assert!(read_pure(&code.render::<Luma<u8>>().build()).is_none());
+        assert!(read_pure(&GrayImage::from_pixel(50, 50, Luma([255]))).is_none());
+    }
+}
@@ -0,0 +1,210 @@
+//! Morphological cleanup for stylised modules
+//!
+//! Generators draw modules as circles, diamonds, hearts or halftone dots.
+//! The gaps between and inside those shapes break the sampling both
+//! decoders do at module centres and along timing patterns. The filters
+//! here rebuild solid modules from the shapes on an Otsu binarisation, with
+//! every window sized relative to the module pitch. The pitch is measured on
+//! the finder patterns, which generators usually keep solid, and falls back
+//! to the run-length estimate used for upscaling.
+
+use crate::adaptive::Integral;
+use crate::locate::otsu_level;
+use crate::roi::finder_module_size;
+use crate::upscale::{estimate_module_size, window_extreme};
+use image::{DynamicImage, GrayImage, Luma};
+use rayon::prelude::*;
+
+/// Modules narrower than this, in pixels, are too small to filter
+const MIN_PITCH: f32 = 4.0;
+
+/// Closing radius: gaps up to about half a module are bridged
+const CLOSING_SCALE: f32 = 0.25;
+
+/// Opening radius: dark specks under about a third of a module are removed
+const OPENING_SCALE: f32 = 0.15;
+
+/// Median radius: halftone screens finer than a module are smoothed out
+const MEDIAN_SCALE: f32 = 0.3;
+
+/// Majority radius: the window spans about one module
+const MAJORITY_SCALE: f32 = 0.5;
+
+/// Share of a module-sized window that must be dark for its centre to be
+/// dark; dots and hearts cover well under half their cell
+const MAJORITY_QUORUM: f32 = 0.35;
+
+/// Module pitch of the symbol in an image, in pixels
+pub fn estimate_pitch(gray: &GrayImage) -> Option<f32> {
+    finder_module_size(gray)
+        .or_else(|| estimate_module_size(gray).map(|module| module as f32))
+        .filter(|&pitch| pitch >= MIN_PITCH)
+}
+
+/// Filter radius for a fraction of the module pitch, at least one pixel
+fn radius(pitch: f32, scale: f32) -> u32 {
+    ((pitch * scale).round() as u32).max(1)
+}
+
+/// Otsu binarisation: dark pixels 0, light pixels 255
+pub fn binarize(gray: &GrayImage) -> GrayImage {
+    let samples: Vec<f32> = gray.as_raw().iter().map(|&v| v as f32).collect();
+    let level = otsu_level(&samples);
+    GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        Luma([if (gray.get_pixel(x, y).0[0] as f32) < level { 0 } else { 255 }])
+    })
+}
+
+/// Closing of the dark shapes: dilate then erode, filling gaps narrower
+/// than the window without growing isolated shapes
+pub fn closing(binary: &GrayImage, radius: u32) -> GrayImage {
+    window_extreme(&window_extreme(binary, radius, u8::min), radius, u8::max)
+}
+
+/// Opening of the dark shapes: erode then dilate, removing specks and
+/// strokes narrower than the window
+pub fn opening(binary: &GrayImage, radius: u32) -> GrayImage {
+    window_extreme(&window_extreme(binary, radius, u8::max), radius, u8::min)
+}
+
+/// Median over a square window, with a histogram slid along each row
+pub fn median(gray: &GrayImage, radius: u32) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let mut out = GrayImage::new(width, height);
+    for y in 0..height {
+        let rows = y.saturating_sub(radius)..(y + radius + 1).min(height);
+        let mut histogram = [0u32; 256];
+        let column = |x: u32, add: bool, histogram: &mut [u32; 256]| {
+            for row in rows.clone() {
+                let bin = &mut histogram[gray.get_pixel(x, row).0[0] as usize];
+                if add {
+                    *bin += 1;
+                } else {
+                    *bin -= 1;
+                }
+            }
+        };
+        for x in 0..radius.min(width) {
+            column(x, true, &mut histogram);
+        }
+        for x in 0..width {
+            if x + radius < width {
+                column(x + radius, true, &mut histogram);
+            }
+            if x > radius {
+                column(x - radius - 1, false, &mut histogram);
+            }
+            let columns = (x + radius + 1).min(width) - x.saturating_sub(radius);
+            let half = columns * rows.len() as u32 / 2;
+            let mut seen = 0;
+            let value = histogram.iter().position(|&n| {
+                seen += n;
+                seen > half
+            });
+            out.put_pixel(x, y, Luma([value.unwrap_or(255) as u8]));
+        }
+    }
+    out
+}
+
+/// Majority filter: a pixel is dark when at least `quorum` of its window is
+pub fn majority(binary: &GrayImage, radius: u32, quorum: f32) -> GrayImage {
+    let integral = Integral::new(binary);
+    GrayImage::from_fn(binary.width(), binary.height(), |x, y| {
+        let (mean, _) = integral.stats(x, y, radius);
+        Luma([if 1.0 - mean / 255.0 >= quorum { 0 } else { 255 }])
+    })
+}
+
+/// A named filter taking the grayscale image, its binarisation and the module pitch
+type Filter = (&'static str, fn(&GrayImage, &GrayImage, f32) -> GrayImage);
+
+/// Filters in the decoder's preprocessing pool
+const FILTERS: [Filter; 4] = [
+    ("closing", |_, binary, pitch| closing(binary, radius(pitch, CLOSING_SCALE))),
+    ("opening", |_, binary, pitch| opening(binary, radius(pitch, OPENING_SCALE))),
+    ("median", |gray, _, pitch| median(gray, radius(pitch, MEDIAN_SCALE))),
+    ("majority", |_, binary, pitch| majority(binary, radius(pitch, MAJORITY_SCALE), MAJORITY_QUORUM)),
+];
+
+/// Morphological variants of an image for the decoder's preprocessing pool,
+/// built in parallel; empty when no module pitch can be measured
+pub fn morphology_variants(img: &DynamicImage) -> Vec<(&'static str, DynamicImage)> {
+    let gray = img.to_luma8();
+    let Some(pitch) = estimate_pitch(&gray) else {
+        return Vec::new();
+    };
+    let binary = binarize(&gray);
+    FILTERS
+        .par_iter()
+        .map(|&(name, filter)| (name, DynamicImage::ImageLuma8(filter(&gray, &binary, pitch))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{try_decode_with_both, DecodeOptions};
+
+    /// Symbol with solid finder patterns and data modules drawn by `shape`,
+    /// which takes the position inside a module as fractions of the pitch
+    fn stylized(text: &str, pitch: u32, shape: fn(f32, f32) -> bool) -> GrayImage {
+        let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+        let modules = code.width() as u32;
+        let colors = code.to_colors();
+        let corner = |m: u32| m < 7 || m >= modules - 7;
+        let finder = |mx: u32, my: u32| corner(mx) && corner(my) && (mx < 7 || my < 7);
+        let side = (modules + 8) * pitch;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (mx, my) = ((x / pitch).wrapping_sub(4), (y / pitch).wrapping_sub(4));
+            if mx >= modules || my >= modules || colors[(my * modules + mx) as usize] == qrcode::Color::Light {
+                return Luma([255]);
+            }
+            let (fx, fy) = ((x % pitch) as f32 / pitch as f32, (y % pitch) as f32 / pitch as f32);
+            Luma([if finder(mx, my) || shape(fx, fy) { 0 } else { 255 }])
+        })
+    }
+
+    /// Four dots in the corners of each module, leaving its centre light
+    fn four_dots(fx: f32, fy: f32) -> bool {
+        let near = |f: f32| !(0.3..0.7).contains(&f);
+        near(fx) && near(fy)
+    }
+
+    #[test]
+    fn pitch_is_measured_on_the_finder_patterns() {
+        let gray = stylized("https://example.com/dots", 10, four_dots);
+        let pitch = estimate_pitch(&gray).unwrap();
+        assert!((pitch - 10.0).abs() < 1.0, "pitch {pitch}");
+        assert_eq!(estimate_pitch(&GrayImage::from_pixel(64, 64, Luma([255]))), None);
+    }
+
+    #[test]
+    fn closing_rebuilds_solid_modules_from_dots() {
+        let gray = stylized("https://example.com/dots", 10, four_dots);
+        let options = DecodeOptions::default();
+        assert!(try_decode_with_both(&DynamicImage::ImageLuma8(gray.clone()), &options).is_err());
+
+        let closed = closing(&binarize(&gray), radius(10.0, CLOSING_SCALE));
+        let result = try_decode_with_both(&DynamicImage::ImageLuma8(closed), &options).unwrap();
+        assert_eq!(result.content, "https://example.com/dots");
+    }
+
+    #[test]
+    fn filters_remove_specks_and_fill_sparse_windows() {
+        // A 2 x 2 speck in a light field, and a 9 x 9 window with a third of it dark
+        let speck = GrayImage::from_fn(16, 16, |x, y| Luma([if (7..9).contains(&x) && (7..9).contains(&y) { 0 } else { 255 }]));
+        assert!(opening(&speck, 1).pixels().all(|p| p.0[0] == 255));
+        assert_eq!(median(&speck, 1).get_pixel(7, 7).0[0], 255);
+        let stripes = GrayImage::from_fn(9, 9, |x, _| Luma([if x % 3 == 0 { 0 } else { 255 }]));
+        assert_eq!(majority(&stripes, 4, 0.3).get_pixel(4, 4).0[0], 0);
+        assert_eq!(majority(&stripes, 4, 0.5).get_pixel(4, 4).0[0], 255);
+    }
+
+    #[test]
+    fn variants_are_named() {
+        let img = DynamicImage::ImageLuma8(stylized("https://example.com/dots", 10, four_dots));
+        let names: Vec<_> = morphology_variants(&img).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["closing", "opening", "median", "majority"]);
+    }
+}
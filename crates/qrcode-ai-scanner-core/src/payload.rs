@@ -0,0 +1,1195 @@
+//! Typed payload parsing
+//!
+//! Recognises the common QR content conventions (URLs, Wi-Fi join codes,
+//! contact cards, mail/phone/SMS links, geo points, calendar events, EPC
+//! payment requests and GS1 product data) and checks their fields, so callers
+//! can show what a code will do when scanned. Anything unrecognised is plain
+//! text.
+
+use crate::gs1::{self, Gs1Element, Gs1Syntax};
+use crate::segments::Fnc1Mode;
+use serde::{Deserialize, Serialize};
+
+/// Longest SSID allowed by 802.11, in bytes
+const MAX_SSID_BYTES: usize = 32;
+
+/// Longest beneficiary name accepted by the EPC QR guidelines
+const MAX_EPC_NAME_CHARS: usize = 70;
+
+/// Wi-Fi authentication from the `T:` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WifiSecurity {
+    /// `nopass` or no `T:` field
+    Open,
+    Wep,
+    /// WPA / WPA2 personal
+    Wpa,
+    /// WPA3 (`SAE`)
+    Wpa3,
+}
+
+/// Contact card syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContactFormat {
+    VCard,
+    MeCard,
+}
+
+/// What a decoded QR code contains
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Payload {
+    /// `http://` or `https://` link
+    Url { url: String },
+    /// `WIFI:` network join code
+    Wifi {
+        ssid: String,
+        password: Option<String>,
+        security: WifiSecurity,
+        hidden: bool,
+    },
+    /// vCard (`BEGIN:VCARD`) or MeCard (`MECARD:`)
+    Contact {
+        format: ContactFormat,
+        name: Option<String>,
+        organization: Option<String>,
+        phones: Vec<String>,
+        emails: Vec<String>,
+        url: Option<String>,
+        address: Option<String>,
+    },
+    /// `mailto:` link or `MATMSG:` message
+    Email {
+        to: String,
+        subject: Option<String>,
+        body: Option<String>,
+    },
+    /// `tel:` link
+    Phone { number: String },
+    /// `sms:` / `SMSTO:` message
+    Sms { number: String, message: Option<String> },
+    /// `geo:` point
+    Geo {
+        latitude: f64,
+        longitude: f64,
+        altitude: Option<f64>,
+        query: Option<String>,
+    },
+    /// iCalendar `VEVENT`
+    Event {
+        summary: Option<String>,
+        start: Option<String>,
+        end: Option<String>,
+        location: Option<String>,
+        description: Option<String>,
+    },
+    /// EPC / SEPA credit transfer (`BCD` service tag)
+    Payment {
+        name: String,
+        iban: String,
+        bic: Option<String>,
+        /// Amount without the currency prefix, e.g. `12.50`
+        amount: Option<String>,
+        currency: Option<String>,
+        purpose: Option<String>,
+        reference: Option<String>,
+        text: Option<String>,
+    },
+    /// GS1 element string (FNC1 symbol) or GS1 Digital Link URI
+    Gs1 {
+        syntax: Gs1Syntax,
+        gtin: Option<String>,
+        batch: Option<String>,
+        /// Expiry date as `YYMMDD`
+        expiry: Option<String>,
+        serial: Option<String>,
+        /// Every application identifier in order, including the above
+        elements: Vec<Gs1Element>,
+    },
+    /// Anything else
+    Text { text: String },
+}
+
+impl Payload {
+    /// Short type name, matching the serialized `type` tag
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Payload::Url { .. } => "url",
+            Payload::Wifi { .. } => "wifi",
+            Payload::Contact { .. } => "contact",
+            Payload::Email { .. } => "email",
+            Payload::Phone { .. } => "phone",
+            Payload::Sms { .. } => "sms",
+            Payload::Geo { .. } => "geo",
+            Payload::Event { .. } => "event",
+            Payload::Payment { .. } => "payment",
+            Payload::Gs1 { .. } => "gs1",
+            Payload::Text { .. } => "text",
+        }
+    }
+
+    /// Present fields as display strings (lists are comma-joined)
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        let mut push = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                fields.push((name, value));
+            }
+        };
+        match self {
+            Payload::Url { url } => push("url", Some(url.clone())),
+            Payload::Wifi {
+                ssid,
+                password,
+                security,
+                hidden,
+            } => {
+                push("ssid", Some(ssid.clone()));
+                push("password", password.clone());
+                push("security", Some(format!("{security:?}")));
+                push("hidden", Some(hidden.to_string()));
+            }
+            Payload::Contact {
+                format,
+                name,
+                organization,
+                phones,
+                emails,
+                url,
+                address,
+            } => {
+                push("format", Some(format!("{format:?}")));
+                push("name", name.clone());
+                push("organization", organization.clone());
+                push("phones", Some(phones.join(", ")));
+                push("emails", Some(emails.join(", ")));
+                push("url", url.clone());
+                push("address", address.clone());
+            }
+            Payload::Email { to, subject, body } => {
+                push("to", Some(to.clone()));
+                push("subject", subject.clone());
+                push("body", body.clone());
+            }
+            Payload::Phone { number } => push("number", Some(number.clone())),
+            Payload::Sms { number, message } => {
+                push("number", Some(number.clone()));
+                push("message", message.clone());
+            }
+            Payload::Geo {
+                latitude,
+                longitude,
+                altitude,
+                query,
+            } => {
+                push("latitude", Some(latitude.to_string()));
+                push("longitude", Some(longitude.to_string()));
+                push("altitude", altitude.map(|a| a.to_string()));
+                push("query", query.clone());
+            }
+            Payload::Event {
+                summary,
+                start,
+                end,
+                location,
+                description,
+            } => {
+                push("summary", summary.clone());
+                push("start", start.clone());
+                push("end", end.clone());
+                push("location", location.clone());
+                push("description", description.clone());
+            }
+            Payload::Payment {
+                name,
+                iban,
+                bic,
+                amount,
+                currency,
+                purpose,
+                reference,
+                text,
+            } => {
+                push("name", Some(name.clone()));
+                push("iban", Some(iban.clone()));
+                push("bic", bic.clone());
+                push("amount", amount.clone());
+                push("currency", currency.clone());
+                push("purpose", purpose.clone());
+                push("reference", reference.clone());
+                push("text", text.clone());
+            }
+            Payload::Gs1 {
+                syntax,
+                gtin,
+                batch,
+                expiry,
+                serial,
+                elements,
+            } => {
+                push("syntax", Some(format!("{syntax:?}")));
+                push("gtin", gtin.clone());
+                push("batch", batch.clone());
+                push("expiry", expiry.clone());
+                push("serial", serial.clone());
+                push("elements", Some(gs1::to_hri(elements)));
+            }
+            Payload::Text { text } => push("text", Some(text.clone())),
+        }
+        fields
+    }
+}
+
+/// A payload field that is missing or malformed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Field name, as in [`Payload::fields`]
+    pub field: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+/// Parsed payload with any field validation errors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedPayload {
+    pub payload: Payload,
+    pub errors: Vec<FieldError>,
+}
+
+impl ParsedPayload {
+    /// Whether every field passed validation
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Collects field errors while a payload is parsed
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Parse decoded content into a typed payload
+pub fn parse_payload(content: &str) -> ParsedPayload {
+    parse_payload_with(content, None)
+}
+
+/// [`parse_payload`] for a symbol that declared an FNC1 mode
+///
+/// GS1 (FNC1 first position) content is read as an element string.
+pub fn parse_payload_with(content: &str, fnc1: Option<Fnc1Mode>) -> ParsedPayload {
+    let text = content.trim();
+    let mut errors = Errors::default();
+
+    let payload = if fnc1 == Some(Fnc1Mode::Gs1) {
+        parse_gs1_element_string(content, &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "WIFI:") {
+        parse_wifi(rest, &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "MECARD:") {
+        parse_mecard(rest, &mut errors)
+    } else if strip_prefix_ci(text, "BEGIN:VCARD").is_some() {
+        parse_vcard(text, &mut errors)
+    } else if strip_prefix_ci(text, "BEGIN:VEVENT").is_some() || strip_prefix_ci(text, "BEGIN:VCALENDAR").is_some() {
+        parse_event(text, &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "MATMSG:") {
+        parse_matmsg(rest, &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "mailto:") {
+        parse_mailto(rest, &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "tel:") {
+        check_phone("number", rest, &mut errors);
+        Payload::Phone {
+            number: rest.to_string(),
+        }
+    } else if let Some(rest) = strip_prefix_ci(text, "SMSTO:") {
+        let (number, message) = rest.split_once(':').unwrap_or((rest, ""));
+        sms(number, Some(message.to_string()), &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "sms:") {
+        let (number, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let number = number.trim_end_matches(';');
+        sms(number, query_param(query, "body"), &mut errors)
+    } else if let Some(rest) = strip_prefix_ci(text, "geo:") {
+        parse_geo(rest, &mut errors)
+    } else if text.lines().next().map(str::trim) == Some("BCD") {
+        parse_epc(text, &mut errors)
+    } else if strip_prefix_ci(text, "http://").is_some() || strip_prefix_ci(text, "https://").is_some() {
+        check_url(text, &mut errors);
+        match gs1::parse_digital_link(text) {
+            Some(elements) => gs1_payload(Gs1Syntax::DigitalLink, elements, &mut errors),
+            None => Payload::Url { url: text.to_string() },
+        }
+    } else {
+        Payload::Text {
+            text: content.to_string(),
+        }
+    };
+
+    ParsedPayload {
+        payload,
+        errors: errors.0,
+    }
+}
+
+// ============================================================================
+// Format parsers
+// ============================================================================
+
+fn parse_gs1_element_string(content: &str, errors: &mut Errors) -> Payload {
+    let elements = gs1::parse_element_string(content).unwrap_or_else(|message| {
+        errors.add("elements", message);
+        Vec::new()
+    });
+    gs1_payload(Gs1Syntax::ElementString, elements, errors)
+}
+
+fn gs1_payload(syntax: Gs1Syntax, elements: Vec<Gs1Element>, errors: &mut Errors) -> Payload {
+    for element in &elements {
+        if let Some(message) = gs1::check_element(element) {
+            errors.add(&element.ai, message);
+        }
+    }
+    let value = |ai: &str| elements.iter().find(|e| e.ai == ai).map(|e| e.value.clone());
+    Payload::Gs1 {
+        syntax,
+        gtin: value("01"),
+        batch: value("10"),
+        expiry: value("17"),
+        serial: value("21"),
+        elements,
+    }
+}
+
+fn parse_wifi(body: &str, errors: &mut Errors) -> Payload {
+    let fields = mecard_fields(body);
+    let ssid = field(&fields, "S").unwrap_or_default();
+    let password = field(&fields, "P");
+    let hidden = match field(&fields, "H").as_deref() {
+        None | Some("") => false,
+        Some(h) if h.eq_ignore_ascii_case("true") => true,
+        Some(h) if h.eq_ignore_ascii_case("false") => false,
+        Some(h) => {
+            errors.add("hidden", format!("expected true or false, got '{h}'"));
+            false
+        }
+    };
+    let security = match field(&fields, "T").unwrap_or_default().to_ascii_uppercase().as_str() {
+        "" | "NOPASS" => WifiSecurity::Open,
+        "WEP" => WifiSecurity::Wep,
+        "WPA" | "WPA2" => WifiSecurity::Wpa,
+        "WPA3" | "SAE" => WifiSecurity::Wpa3,
+        other => {
+            errors.add("security", format!("unknown authentication type '{other}'"));
+            WifiSecurity::Open
+        }
+    };
+
+    if ssid.is_empty() {
+        errors.add("ssid", "missing network name");
+    } else if ssid.len() > MAX_SSID_BYTES {
+        errors.add("ssid", format!("longer than {MAX_SSID_BYTES} bytes"));
+    }
+
+    let is_hex = |p: &str| p.chars().all(|c| c.is_ascii_hexdigit());
+    match (security, password.as_deref()) {
+        (WifiSecurity::Open, _) => {}
+        (_, None | Some("")) => errors.add("password", "missing password for a secured network"),
+        (WifiSecurity::Wep, Some(p)) => {
+            let valid = matches!(p.len(), 5 | 13) || (matches!(p.len(), 10 | 26) && is_hex(p));
+            if !valid {
+                errors.add("password", "WEP keys are 5 or 13 characters, or 10 or 26 hex digits");
+            }
+        }
+        (_, Some(p)) => {
+            let valid = (8..=63).contains(&p.len()) || (p.len() == 64 && is_hex(p));
+            if !valid {
+                errors.add("password", "WPA passphrases are 8-63 characters");
+            }
+        }
+    }
+
+    Payload::Wifi {
+        ssid,
+        password,
+        security,
+        hidden,
+    }
+}
+
+fn parse_mecard(body: &str, errors: &mut Errors) -> Payload {
+    let fields = mecard_fields(body);
+    // MeCard names are "Last,First"
+    let name = field(&fields, "N").filter(|n| !n.is_empty()).map(|n| match n.split_once(',') {
+        Some((last, first)) => format!("{} {}", first.trim(), last.trim()).trim().to_string(),
+        None => n,
+    });
+    if name.is_none() {
+        errors.add("name", "missing N: field");
+    }
+    let all = |key: &str| -> Vec<String> {
+        fields
+            .iter()
+            .filter(|(k, v)| k == key && !v.is_empty())
+            .map(|(_, v)| v.clone())
+            .collect()
+    };
+    contact(
+        ContactFormat::MeCard,
+        name,
+        field(&fields, "ORG"),
+        all("TEL"),
+        all("EMAIL"),
+        field(&fields, "URL"),
+        field(&fields, "ADR"),
+        errors,
+    )
+}
+
+fn parse_vcard(text: &str, errors: &mut Errors) -> Payload {
+    let lines = content_lines(text);
+    let value = |name: &str| {
+        lines
+            .iter()
+            .find(|(n, v)| n == name && !v.is_empty())
+            .map(|(_, v)| v.clone())
+    };
+    let all = |name: &str| -> Vec<String> {
+        lines
+            .iter()
+            .filter(|(n, v)| n == name && !v.is_empty())
+            .map(|(_, v)| unescape_vcard(v))
+            .collect()
+    };
+    let components = |value: String, separator: &str| {
+        split_unescaped(&value, ';')
+            .iter()
+            .map(|c| unescape_vcard(c).trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+
+    if !lines.iter().any(|(n, v)| n == "END" && v.eq_ignore_ascii_case("VCARD")) {
+        errors.add("end", "missing END:VCARD");
+    }
+    // Prefer the formatted name; N is "Last;First;Middle;Prefix;Suffix"
+    let name = value("FN").map(|n| unescape_vcard(&n)).or_else(|| {
+        value("N").map(|n| {
+            let parts: Vec<String> = split_unescaped(&n, ';').iter().map(|p| unescape_vcard(p)).collect();
+            let first = parts.get(1).map(String::as_str).unwrap_or("");
+            format!("{} {}", first, parts[0]).trim().to_string()
+        })
+    });
+    let name = name.filter(|n| !n.is_empty());
+    if name.is_none() {
+        errors.add("name", "missing FN or N property");
+    }
+
+    contact(
+        ContactFormat::VCard,
+        name,
+        value("ORG").map(|o| components(o, " ")),
+        all("TEL"),
+        all("EMAIL"),
+        value("URL").map(|u| unescape_vcard(&u)),
+        value("ADR").map(|a| components(a, ", ")),
+        errors,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn contact(
+    format: ContactFormat,
+    name: Option<String>,
+    organization: Option<String>,
+    phones: Vec<String>,
+    emails: Vec<String>,
+    url: Option<String>,
+    address: Option<String>,
+    errors: &mut Errors,
+) -> Payload {
+    for phone in &phones {
+        check_phone("phones", phone, errors);
+    }
+    for email in &emails {
+        check_email("emails", email, errors);
+    }
+    Payload::Contact {
+        format,
+        name,
+        organization,
+        phones,
+        emails,
+        url,
+        address,
+    }
+}
+
+fn parse_event(text: &str, errors: &mut Errors) -> Payload {
+    let lines = content_lines(text);
+    // Only look inside the first VEVENT block
+    let start = lines
+        .iter()
+        .position(|(n, v)| n == "BEGIN" && v.eq_ignore_ascii_case("VEVENT"));
+    let Some(start) = start else {
+        errors.add("event", "calendar contains no VEVENT");
+        return Payload::Event {
+            summary: None,
+            start: None,
+            end: None,
+            location: None,
+            description: None,
+        };
+    };
+    let block = &lines[start + 1..];
+    let end = block
+        .iter()
+        .position(|(n, v)| n == "END" && v.eq_ignore_ascii_case("VEVENT"));
+    if end.is_none() {
+        errors.add("end", "missing END:VEVENT");
+    }
+    let block = &block[..end.unwrap_or(block.len())];
+    let value = |name: &str| {
+        block
+            .iter()
+            .find(|(n, v)| n == name && !v.is_empty())
+            .map(|(_, v)| unescape_vcard(v))
+    };
+
+    let (dtstart, dtend) = (value("DTSTART"), value("DTEND"));
+    match &dtstart {
+        None => errors.add("start", "missing DTSTART"),
+        Some(s) if !is_ical_date(s) => errors.add("start", format!("'{s}' is not an iCalendar date")),
+        Some(_) => {}
+    }
+    if let Some(e) = &dtend {
+        if !is_ical_date(e) {
+            errors.add("end", format!("'{e}' is not an iCalendar date"));
+        } else if let Some(s) = dtstart.as_ref().filter(|s| is_ical_date(s) && s.len() == e.len()) {
+            // Same-format iCalendar dates compare lexically
+            if e < s {
+                errors.add("end", "ends before it starts");
+            }
+        }
+    }
+
+    Payload::Event {
+        summary: value("SUMMARY"),
+        start: dtstart,
+        end: dtend,
+        location: value("LOCATION"),
+        description: value("DESCRIPTION"),
+    }
+}
+
+fn parse_matmsg(body: &str, errors: &mut Errors) -> Payload {
+    let fields = mecard_fields(body);
+    let to = field(&fields, "TO").unwrap_or_default();
+    check_email("to", &to, errors);
+    Payload::Email {
+        to,
+        subject: field(&fields, "SUB").filter(|s| !s.is_empty()),
+        body: field(&fields, "BODY").filter(|s| !s.is_empty()),
+    }
+}
+
+fn parse_mailto(rest: &str, errors: &mut Errors) -> Payload {
+    let (to, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let to = percent_decode(to);
+    if to.is_empty() {
+        errors.add("to", "missing recipient");
+    }
+    for address in to.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+        check_email("to", address, errors);
+    }
+    Payload::Email {
+        to,
+        subject: query_param(query, "subject"),
+        body: query_param(query, "body"),
+    }
+}
+
+fn sms(number: &str, message: Option<String>, errors: &mut Errors) -> Payload {
+    check_phone("number", number, errors);
+    Payload::Sms {
+        number: number.to_string(),
+        message: message.filter(|m| !m.is_empty()),
+    }
+}
+
+fn parse_geo(rest: &str, errors: &mut Errors) -> Payload {
+    let (coords, query) = rest.split_once('?').unwrap_or((rest, ""));
+    // Drop ;crs= / ;u= parameters
+    let coords = coords.split(';').next().unwrap_or("");
+    let mut parts = coords.split(',').map(str::trim);
+
+    let mut coordinate = |name: &str, part: Option<&str>, limit: f64| match part.map(str::parse::<f64>) {
+        Some(Ok(v)) if v.abs() <= limit => v,
+        Some(Ok(v)) => {
+            errors.add(name, format!("{v} is outside ±{limit}"));
+            v
+        }
+        _ => {
+            errors.add(name, "missing or not a number");
+            0.0
+        }
+    };
+    let latitude = coordinate("latitude", parts.next(), 90.0);
+    let longitude = coordinate("longitude", parts.next(), 180.0);
+    let altitude = match parts.next().map(str::parse::<f64>) {
+        None => None,
+        Some(Ok(a)) => Some(a),
+        Some(Err(_)) => {
+            errors.add("altitude", "not a number");
+            None
+        }
+    };
+
+    Payload::Geo {
+        latitude,
+        longitude,
+        altitude,
+        query: query_param(query, "q"),
+    }
+}
+
+/// EPC069-12 "BCD" SEPA credit transfer
+fn parse_epc(text: &str, errors: &mut Errors) -> Payload {
+    let lines: Vec<&str> = text.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let line = |i: usize| lines.get(i).map(|l| l.trim()).filter(|l| !l.is_empty());
+
+    let version = line(1).unwrap_or("");
+    if !matches!(version, "001" | "002") {
+        errors.add("version", format!("unsupported EPC version '{version}'"));
+    }
+    if !line(2).is_some_and(|c| matches!(c, "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8")) {
+        errors.add("charset", "character set must be 1-8");
+    }
+    if line(3) != Some("SCT") {
+        errors.add("identification", "expected SCT");
+    }
+
+    let bic = line(4).map(str::to_string);
+    match &bic {
+        None if version == "001" => errors.add("bic", "BIC is required in version 001"),
+        Some(b) if !is_bic(b) => errors.add("bic", format!("'{b}' is not a valid BIC")),
+        _ => {}
+    }
+
+    let name = line(5).unwrap_or_default().to_string();
+    if name.is_empty() {
+        errors.add("name", "missing beneficiary name");
+    } else if name.chars().count() > MAX_EPC_NAME_CHARS {
+        errors.add("name", format!("longer than {MAX_EPC_NAME_CHARS} characters"));
+    }
+
+    let iban: String = line(6).unwrap_or_default().chars().filter(|c| !c.is_whitespace()).collect();
+    if iban.is_empty() {
+        errors.add("iban", "missing IBAN");
+    } else if !is_iban(&iban) {
+        errors.add("iban", "IBAN checksum does not match");
+    }
+
+    let (currency, amount) = match line(7) {
+        None => (None, None),
+        // The currency code is three ASCII letters; anything else must not be
+        // split mid-character
+        Some(a) => match a.split_at_checked(3) {
+            None => {
+                errors.add("amount", format!("'{a}' does not start with a currency code"));
+                (None, Some(a.to_string()))
+            }
+            Some((currency, value)) => {
+                if currency != "EUR" {
+                    errors.add("amount", "amount must start with EUR");
+                }
+                let cents_ok = value.split_once('.').is_none_or(|(_, d)| d.len() <= 2);
+                match value.parse::<f64>() {
+                    Ok(v) if cents_ok && (0.01..=999_999_999.99).contains(&v) => {}
+                    _ => errors.add("amount", format!("'{value}' is not an amount between 0.01 and 999999999.99")),
+                }
+                (Some(currency.to_string()), Some(value.to_string()))
+            }
+        },
+    };
+
+    let reference = line(9).map(str::to_string);
+    let remittance = line(10).map(str::to_string);
+    if reference.is_some() && remittance.is_some() {
+        errors.add("text", "structured reference and free text are mutually exclusive");
+    }
+
+    Payload::Payment {
+        name,
+        iban,
+        bic,
+        amount,
+        currency,
+        purpose: line(8).map(str::to_string),
+        reference,
+        text: remittance,
+    }
+}
+
+// ============================================================================
+// Field checks
+// ============================================================================
+
+fn check_url(url: &str, errors: &mut Errors) {
+    if url.chars().any(char::is_whitespace) {
+        errors.add("url", "contains whitespace");
+    }
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or("");
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host_port = authority.rsplit_once('@').map(|(_, h)| h).unwrap_or(authority);
+    let (host, port) = if let Some(v6) = host_port.strip_prefix('[') {
+        match v6.split_once(']') {
+            Some((host, port)) => (host, port.strip_prefix(':')),
+            None => (v6, None),
+        }
+    } else {
+        match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+
+    if host.is_empty() {
+        errors.add("url", "missing host");
+    } else if !host.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | ':')) {
+        errors.add("url", format!("invalid host '{host}'"));
+    }
+    if let Some(port) = port {
+        if port.parse::<u16>().is_err() {
+            errors.add("url", format!("invalid port '{port}'"));
+        }
+    }
+}
+
+fn check_email(name: &str, email: &str, errors: &mut Errors) {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    };
+    if !valid {
+        errors.add(name, format!("'{email}' is not an email address"));
+    }
+}
+
+fn check_phone(name: &str, number: &str, errors: &mut Errors) {
+    let number = number.trim();
+    let allowed = number
+        .char_indices()
+        .all(|(i, c)| c.is_ascii_digit() || matches!(c, '-' | '.' | ' ' | '(' | ')') || (c == '+' && i == 0));
+    // E.164 numbers have at most 15 digits
+    let digits = number.chars().filter(char::is_ascii_digit).count();
+    if !allowed || !(3..=15).contains(&digits) {
+        errors.add(name, format!("'{number}' is not a phone number"));
+    }
+}
+
+/// ISO 13616 mod-97 check
+fn is_iban(iban: &str) -> bool {
+    let valid_shape = (15..=34).contains(&iban.len())
+        && iban.chars().all(|c| c.is_ascii_alphanumeric())
+        && iban[..2].chars().all(|c| c.is_ascii_alphabetic())
+        && iban[2..4].chars().all(|c| c.is_ascii_digit());
+    if !valid_shape {
+        return false;
+    }
+    let rearranged = iban[4..].chars().chain(iban[..4].chars());
+    let remainder = rearranged.fold(0u32, |acc, c| {
+        let value = c.to_digit(36).unwrap_or(0);
+        if value >= 10 {
+            (acc * 100 + value) % 97
+        } else {
+            (acc * 10 + value) % 97
+        }
+    });
+    remainder == 1
+}
+
+/// ISO 9362: 4 bank letters, 2 country letters, 2 location and optional 3 branch characters
+fn is_bic(bic: &str) -> bool {
+    matches!(bic.len(), 8 | 11)
+        && bic.chars().all(|c| c.is_ascii_alphanumeric())
+        && bic[..6].chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// `YYYYMMDD` or `YYYYMMDDTHHMMSS[Z]`
+fn is_ical_date(value: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    match value.split_once('T') {
+        None => value.len() == 8 && digits(value),
+        Some((date, time)) => {
+            let time = time.strip_suffix('Z').unwrap_or(time);
+            date.len() == 8 && digits(date) && time.len() == 6 && digits(time)
+        }
+    }
+}
+
+// ============================================================================
+// Syntax helpers
+// ============================================================================
+
+fn strip_prefix_ci<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
+
+/// Split on `separator` where it isn't backslash-escaped (escapes are kept)
+fn split_unescaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == separator && !escaped {
+            parts.push(String::new());
+            continue;
+        }
+        escaped = c == '\\' && !escaped;
+        if let Some(last) = parts.last_mut() {
+            last.push(c);
+        }
+    }
+    parts
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                out.push(next);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// vCard escapes also encode newlines as `\n`
+fn unescape_vcard(text: &str) -> String {
+    unescape(&text.replace("\\n", "\n").replace("\\N", "\n"))
+}
+
+/// `KEY:value;` pairs of WIFI:, MECARD: and MATMSG: codes, keys upper-cased
+fn mecard_fields(body: &str) -> Vec<(String, String)> {
+    split_unescaped(body, ';')
+        .iter()
+        .filter_map(|part| {
+            let (key, value) = part.split_once(':')?;
+            Some((key.trim().to_ascii_uppercase(), unescape(value)))
+        })
+        .collect()
+}
+
+fn field(fields: &[(String, String)], key: &str) -> Option<String> {
+    fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+}
+
+/// Unfolded `NAME;PARAMS:value` lines of vCard / iCalendar text
+///
+/// Names are upper-cased with parameters and group prefixes removed; values
+/// keep their escapes.
+fn content_lines(text: &str) -> Vec<(String, String)> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines().map(|l| l.trim_end_matches('\r')) {
+        match (line.strip_prefix([' ', '\t']), unfolded.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+    unfolded
+        .iter()
+        .filter_map(|line| {
+            let (left, value) = line.split_once(':')?;
+            let name = left.split(';').next()?;
+            let name = name.rsplit('.').next()?;
+            Some((name.trim().to_ascii_uppercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| percent_decode(v))
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_fields(parsed: &ParsedPayload) -> Vec<&str> {
+        parsed.errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_urls() {
+        let parsed = parse_payload("https://example.com/path?q=1");
+        assert_eq!(parsed.payload, Payload::Url { url: "https://example.com/path?q=1".into() });
+        assert!(parsed.is_valid());
+
+        let parsed = parse_payload("http://exa mple.com:99999");
+        assert_eq!(parsed.payload.kind(), "url");
+        assert!(error_fields(&parsed).iter().all(|&f| f == "url"));
+        assert!(parsed.errors.len() >= 2);
+    }
+
+    #[test]
+    fn parses_wifi_with_escapes() {
+        let parsed = parse_payload(r"WIFI:T:WPA;S:Cafe\;Guest;P:pa\:ss1234;H:true;;");
+        assert_eq!(
+            parsed.payload,
+            Payload::Wifi {
+                ssid: "Cafe;Guest".into(),
+                password: Some("pa:ss1234".into()),
+                security: WifiSecurity::Wpa,
+                hidden: true,
+            }
+        );
+        assert!(parsed.is_valid());
+    }
+
+    #[test]
+    fn reports_wifi_field_errors() {
+        let parsed = parse_payload("WIFI:T:WPA;P:short;;");
+        assert_eq!(error_fields(&parsed), vec!["ssid", "password"]);
+
+        let parsed = parse_payload("WIFI:T:WEP;S:home;;");
+        assert_eq!(error_fields(&parsed), vec!["password"]);
+
+        let parsed = parse_payload("WIFI:T:nopass;S:open;;");
+        assert!(parsed.is_valid());
+    }
+
+    #[test]
+    fn parses_mecard_and_vcard() {
+        let parsed = parse_payload("MECARD:N:Doe,John;TEL:+15551234567;EMAIL:john@example.com;;");
+        match parsed.payload {
+            Payload::Contact {
+                format,
+                name,
+                phones,
+                emails,
+                ..
+            } => {
+                assert_eq!(format, ContactFormat::MeCard);
+                assert_eq!(name.as_deref(), Some("John Doe"));
+                assert_eq!(phones, vec!["+15551234567"]);
+                assert_eq!(emails, vec!["john@example.com"]);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let vcard = "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;Jane;;;\r\nORG:Acme\r\nTEL;TYPE=CELL:+44 20 7946 0958\r\n\
+                     EMAIL:jane@acme\r\nADR;TYPE=WORK:;;1 Main St;Springfield;;12345;\r\n  USA\r\nEND:VCARD";
+        let parsed = parse_payload(vcard);
+        match &parsed.payload {
+            Payload::Contact {
+                format,
+                name,
+                organization,
+                phones,
+                address,
+                ..
+            } => {
+                assert_eq!(*format, ContactFormat::VCard);
+                assert_eq!(name.as_deref(), Some("Jane Doe"));
+                assert_eq!(organization.as_deref(), Some("Acme"));
+                assert_eq!(phones, &vec!["+44 20 7946 0958".to_string()]);
+                assert_eq!(address.as_deref(), Some("1 Main St, Springfield, 12345, USA"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert_eq!(error_fields(&parsed), vec!["emails"]);
+    }
+
+    #[test]
+    fn parses_mail_phone_and_sms_links() {
+        let parsed = parse_payload("mailto:info@example.com?subject=Hello%20there&body=Hi");
+        assert_eq!(
+            parsed.payload,
+            Payload::Email {
+                to: "info@example.com".into(),
+                subject: Some("Hello there".into()),
+                body: Some("Hi".into()),
+            }
+        );
+        assert!(parsed.is_valid());
+
+        let parsed = parse_payload("MATMSG:TO:a@b.org;SUB:Re;BODY:Text;;");
+        assert_eq!(parsed.payload.kind(), "email");
+        assert!(parsed.is_valid());
+
+        assert!(parse_payload("tel:+1-555-123-4567").is_valid());
+        assert_eq!(error_fields(&parse_payload("tel:call-me")), vec!["number"]);
+
+        let parsed = parse_payload("SMSTO:+15551234567:See you at 5");
+        assert_eq!(
+            parsed.payload,
+            Payload::Sms {
+                number: "+15551234567".into(),
+                message: Some("See you at 5".into()),
+            }
+        );
+        let parsed = parse_payload("sms:+15551234567?body=hello");
+        assert_eq!(
+            parsed.payload,
+            Payload::Sms {
+                number: "+15551234567".into(),
+                message: Some("hello".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_geo_points() {
+        let parsed = parse_payload("geo:48.8584,2.2945,330?q=Eiffel%20Tower");
+        assert_eq!(
+            parsed.payload,
+            Payload::Geo {
+                latitude: 48.8584,
+                longitude: 2.2945,
+                altitude: Some(330.0),
+                query: Some("Eiffel Tower".into()),
+            }
+        );
+        assert!(parsed.is_valid());
+
+        assert_eq!(error_fields(&parse_payload("geo:91,200")), vec!["latitude", "longitude"]);
+        assert_eq!(error_fields(&parse_payload("geo:abc")), vec!["latitude", "longitude"]);
+    }
+
+    #[test]
+    fn parses_calendar_events() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Launch\\, party\nDTSTART:20260301T180000Z\n\
+                   DTEND:20260301T220000Z\nLOCATION:HQ\nEND:VEVENT\nEND:VCALENDAR";
+        let parsed = parse_payload(ics);
+        assert_eq!(
+            parsed.payload,
+            Payload::Event {
+                summary: Some("Launch, party".into()),
+                start: Some("20260301T180000Z".into()),
+                end: Some("20260301T220000Z".into()),
+                location: Some("HQ".into()),
+                description: None,
+            }
+        );
+        assert!(parsed.is_valid());
+
+        let parsed = parse_payload("BEGIN:VEVENT\nDTSTART:20260302\nDTEND:20260301\nEND:VEVENT");
+        assert_eq!(error_fields(&parsed), vec!["end"]);
+        let parsed = parse_payload("BEGIN:VEVENT\nSUMMARY:No date\n");
+        assert_eq!(error_fields(&parsed), vec!["end", "start"]);
+    }
+
+    #[test]
+    fn parses_epc_payments() {
+        let epc = "BCD\n002\n1\nSCT\nBPOTBEB1\nRed Cross of Belgium\nBE72000000001616\nEUR12.50\nCHAR\n\nDonation";
+        let parsed = parse_payload(epc);
+        assert_eq!(
+            parsed.payload,
+            Payload::Payment {
+                name: "Red Cross of Belgium".into(),
+                iban: "BE72000000001616".into(),
+                bic: Some("BPOTBEB1".into()),
+                amount: Some("12.50".into()),
+                currency: Some("EUR".into()),
+                purpose: Some("CHAR".into()),
+                reference: None,
+                text: Some("Donation".into()),
+            }
+        );
+        assert!(parsed.is_valid(), "{:?}", parsed.errors);
+
+        let bad = "BCD\n001\n1\nSCT\n\nName\nBE72000000001617\nUSD1.234";
+        assert_eq!(error_fields(&parse_payload(bad)), vec!["bic", "iban", "amount", "amount"]);
+
+        // Byte 3 inside a multi-byte character
+        for amount in ["12€", "€12", "EU"] {
+            let epc = format!("BCD\n002\n1\nSCT\n\nName\nBE72000000001616\n{amount}");
+            assert_eq!(error_fields(&parse_payload(&epc)), vec!["amount"], "{amount}");
+        }
+    }
+
+    #[test]
+    fn parses_gs1_element_strings_and_digital_links() {
+        let parsed = parse_payload_with("01095060001343521725073110A1B2\u{1D}21S-42", Some(Fnc1Mode::Gs1));
+        let Payload::Gs1 { syntax, gtin, batch, expiry, serial, elements } = &parsed.payload else {
+            panic!("expected GS1 payload, got {:?}", parsed.payload);
+        };
+        assert_eq!(*syntax, Gs1Syntax::ElementString);
+        assert_eq!(gtin.as_deref(), Some("09506000134352"));
+        assert_eq!(batch.as_deref(), Some("A1B2"));
+        assert_eq!(expiry.as_deref(), Some("250731"));
+        assert_eq!(serial.as_deref(), Some("S-42"));
+        assert_eq!(elements.len(), 4);
+        assert!(parsed.is_valid());
+
+        let parsed = parse_payload("https://id.gs1.org/01/09506000134353?17=251340");
+        assert_eq!(parsed.payload.kind(), "gs1");
+        assert_eq!(error_fields(&parsed), vec!["01", "17"]);
+        assert_eq!(parse_payload("https://news.example/2024/01/15").payload.kind(), "url");
+
+        // Without FNC1 the same digits are plain text
+        assert_eq!(parse_payload("010950600013435217250731").payload.kind(), "text");
+        let parsed = parse_payload_with("0123", Some(Fnc1Mode::Gs1));
+        assert_eq!(error_fields(&parsed), vec!["elements"]);
+    }
+
+    #[test]
+    fn falls_back_to_text() {
+        let parsed = parse_payload("just some words");
+        assert_eq!(parsed.payload, Payload::Text { text: "just some words".into() });
+        assert!(parsed.is_valid());
+    }
+
+    #[test]
+    fn serializes_with_type_tag() {
+        let json = serde_json::to_string(&parse_payload("tel:+15551234567")).unwrap();
+        assert!(json.contains("\"type\":\"phone\""));
+        assert!(json.contains("\"number\":\"+15551234567\""));
+        assert_eq!(
+            parse_payload("geo:1,2").payload.fields(),
+            vec![("latitude", "1".to_string()), ("longitude", "2".to_string())]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Physical print sizing
+//!
+//! Answers two questions from the decoded module count and the downscale
+//! stress limits:
+//! - How wide must the code be printed for a given DPI and scan distance?
+//! - How far away can a code of a given printed width still be scanned?
+//!
+//! Phones are modelled as a pinhole camera (horizontal field of view plus the
+//! frame width handed to the scanner), see [`CameraModel`].
+
+use crate::types::StressResults;
+use serde::{Deserialize, Serialize};
+
+/// Quiet zone required around a standard QR symbol (modules per side)
+pub const QUIET_ZONE_MODULES: u8 = 4;
+
+/// Pixels per module both decoders need on a clean, sharp symbol
+const DECODER_MIN_PIXELS_PER_MODULE: f32 = 2.0;
+
+/// Headroom for focus, motion blur and perspective in real scans
+const SCAN_SAFETY_FACTOR: f32 = 1.5;
+
+/// Single-dot modules bleed into each other on most printers
+const MIN_DOTS_PER_MODULE: u32 = 2;
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// Phone camera model used to project printed modules onto sensor pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraModel {
+    /// Horizontal field of view in degrees
+    pub horizontal_fov_deg: f32,
+    /// Horizontal resolution of the frame analysed by the scanner app
+    pub resolution_px: u32,
+}
+
+impl Default for CameraModel {
+    /// Typical phone main camera: ~66° FOV, scanners analyse 1280px-wide preview frames
+    fn default() -> Self {
+        Self {
+            horizontal_fov_deg: 66.0,
+            resolution_px: 1280,
+        }
+    }
+}
+
+impl CameraModel {
+    /// Camera pixels covering one millimetre of a flat target at `distance_mm`
+    pub fn pixels_per_mm(&self, distance_mm: f32) -> f32 {
+        let half_fov = (self.horizontal_fov_deg.to_radians() / 2.0).tan();
+        let frame_width_mm = 2.0 * distance_mm * half_fov;
+        if frame_width_mm <= 0.0 {
+            return 0.0;
+        }
+        self.resolution_px as f32 / frame_width_mm
+    }
+}
+
+/// Print and scan conditions used for the recommendation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrintOptions {
+    /// Printer resolution in dots per inch
+    pub dpi: u32,
+    /// Expected distance between phone and print, in millimetres
+    pub scan_distance_mm: f32,
+    /// Actual printed width of the symbol (without quiet zone), if already fixed
+    pub printed_width_mm: Option<f32>,
+    /// Phone camera model
+    pub camera: CameraModel,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            dpi: 300,
+            scan_distance_mm: 300.0,
+            printed_width_mm: None,
+            camera: CameraModel::default(),
+        }
+    }
+}
+
+/// Print size and scan distance recommendation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrintRecommendation {
+    /// Modules per side of the symbol
+    pub modules: u8,
+    /// Camera pixels each module needs to decode reliably
+    pub required_pixels_per_module: f32,
+    /// Printer resolution used for the recommendation
+    pub dpi: u32,
+    /// Scan distance used for the recommendation (mm)
+    pub scan_distance_mm: f32,
+    /// Printer dots per module at the minimum width
+    pub dots_per_module: u32,
+    /// Minimum printed width of the symbol, without quiet zone (mm)
+    pub min_printed_width_mm: f32,
+    /// Width of the quiet zone on each side at the minimum width (mm)
+    pub quiet_zone_mm: f32,
+    /// Printed width the maximum distance is computed for (mm)
+    pub printed_width_mm: f32,
+    /// Maximum scan distance for `printed_width_mm` (mm)
+    pub max_scan_distance_mm: f32,
+    /// Camera model used for the recommendation
+    pub camera: CameraModel,
+}
+
+/// Camera pixels per module the symbol needs, derived from the downscale stress limits
+///
+/// The source resolution is estimated from the image size and the module count
+/// (assuming the image spans the symbol plus its quiet zone). The smallest
+/// downscale that still decoded gives the measured limit; codes that survive
+/// every downscale fall back to the decoders' own floor.
+pub fn required_pixels_per_module(modules: u8, stress: &StressResults, image_size: (u32, u32)) -> f32 {
+    if modules == 0 || stress.downscale_25 {
+        return DECODER_MIN_PIXELS_PER_MODULE * SCAN_SAFETY_FACTOR;
+    }
+
+    let span = modules as f32 + 2.0 * QUIET_ZONE_MODULES as f32;
+    let source_ppm = image_size.0.min(image_size.1) as f32 / span;
+    let decodable_factor = if stress.downscale_50 { 0.5 } else { 1.0 };
+
+    (source_ppm * decodable_factor).max(DECODER_MIN_PIXELS_PER_MODULE) * SCAN_SAFETY_FACTOR
+}
+
+/// Minimum printed width (mm, without quiet zone) for the camera to resolve every module
+pub fn min_printed_width_mm(
+    modules: u8,
+    pixels_per_module: f32,
+    distance_mm: f32,
+    camera: &CameraModel,
+) -> f32 {
+    let px_per_mm = camera.pixels_per_mm(distance_mm);
+    if px_per_mm <= 0.0 {
+        return 0.0;
+    }
+    modules as f32 * pixels_per_module / px_per_mm
+}
+
+/// Maximum scan distance (mm) at which a symbol printed `width_mm` wide still resolves
+pub fn max_scan_distance_mm(
+    modules: u8,
+    pixels_per_module: f32,
+    width_mm: f32,
+    camera: &CameraModel,
+) -> f32 {
+    if modules == 0 || pixels_per_module <= 0.0 {
+        return 0.0;
+    }
+    let half_fov = (camera.horizontal_fov_deg.to_radians() / 2.0).tan();
+    if half_fov <= 0.0 {
+        return 0.0;
+    }
+    let module_mm = width_mm / modules as f32;
+    // Distance where one module covers exactly `pixels_per_module` camera pixels
+    module_mm * camera.resolution_px as f32 / (pixels_per_module * 2.0 * half_fov)
+}
+
+/// Build a full recommendation for the given print and scan conditions
+///
+/// The minimum width is rounded up so every module spans a whole number of
+/// printer dots (at least [`MIN_DOTS_PER_MODULE`]).
+pub fn recommend(modules: u8, pixels_per_module: f32, options: &PrintOptions) -> PrintRecommendation {
+    let camera_width = min_printed_width_mm(
+        modules,
+        pixels_per_module,
+        options.scan_distance_mm,
+        &options.camera,
+    );
+
+    let dpi = options.dpi.max(1);
+    let dot_mm = MM_PER_INCH / dpi as f32;
+    let module_mm = if modules > 0 { camera_width / modules as f32 } else { 0.0 };
+    let dots_per_module = ((module_mm / dot_mm).ceil() as u32).max(MIN_DOTS_PER_MODULE);
+    let module_mm = dots_per_module as f32 * dot_mm;
+    let min_width = module_mm * modules as f32;
+
+    let printed_width = options.printed_width_mm.unwrap_or(min_width);
+
+    PrintRecommendation {
+        modules,
+        required_pixels_per_module: pixels_per_module,
+        dpi,
+        scan_distance_mm: options.scan_distance_mm,
+        dots_per_module,
+        min_printed_width_mm: min_width,
+        quiet_zone_mm: module_mm * QUIET_ZONE_MODULES as f32,
+        printed_width_mm: printed_width,
+        max_scan_distance_mm: max_scan_distance_mm(
+            modules,
+            pixels_per_module,
+            printed_width,
+            &options.camera,
+        ),
+        camera: options.camera,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_pass() -> StressResults {
+        StressResults {
+            original: true,
+            downscale_50: true,
+            downscale_25: true,
+            blur_light: true,
+            blur_medium: true,
+            low_contrast: true,
+        }
+    }
+
+    #[test]
+    fn clean_code_uses_decoder_floor() {
+        let ppm = required_pixels_per_module(25, &all_pass(), (330, 330));
+        assert!((ppm - DECODER_MIN_PIXELS_PER_MODULE * SCAN_SAFETY_FACTOR).abs() < 0.01);
+    }
+
+    #[test]
+    fn fragile_code_needs_more_pixels() {
+        let fragile = StressResults {
+            original: true,
+            ..StressResults::default()
+        };
+        let robust = required_pixels_per_module(25, &all_pass(), (1000, 1000));
+        let weak = required_pixels_per_module(25, &fragile, (1000, 1000));
+        assert!(weak > robust);
+    }
+
+    #[test]
+    fn min_width_and_max_distance_are_inverse() {
+        let camera = CameraModel::default();
+        let width = min_printed_width_mm(25, 3.0, 300.0, &camera);
+        let distance = max_scan_distance_mm(25, 3.0, width, &camera);
+        assert!((distance - 300.0).abs() < 0.5, "got {distance}");
+    }
+
+    #[test]
+    fn recommendation_rounds_to_printer_dots() {
+        let rec = recommend(25, 3.0, &PrintOptions::default());
+        let dot_mm = MM_PER_INCH / 300.0;
+        let expected = rec.dots_per_module as f32 * dot_mm * 25.0;
+        assert!((rec.min_printed_width_mm - expected).abs() < 0.001);
+        assert!(rec.dots_per_module >= MIN_DOTS_PER_MODULE);
+        // Rounding up can only increase the reachable distance
+        assert!(rec.max_scan_distance_mm >= rec.scan_distance_mm);
+    }
+
+    #[test]
+    fn fixed_printed_width_sets_max_distance() {
+        let options = PrintOptions {
+            printed_width_mm: Some(50.0),
+            ..PrintOptions::default()
+        };
+        let rec = recommend(25, 3.0, &options);
+        assert_eq!(rec.printed_width_mm, 50.0);
+        let expected = max_scan_distance_mm(25, 3.0, 50.0, &options.camera);
+        assert!((rec.max_scan_distance_mm - expected).abs() < 0.001);
+    }
+
+    #[test]
+    fn farther_scans_need_bigger_prints() {
+        let camera = CameraModel::default();
+        let near = min_printed_width_mm(33, 3.0, 200.0, &camera);
+        let far = min_printed_width_mm(33, 3.0, 1000.0, &camera);
+        assert!(far > near * 4.9);
+    }
+}
@@ -0,0 +1,239 @@
+//! Coarse-to-fine scanning for large images
+//!
+//! Running every decode tier over a 50 MP photo is slow and memory hungry,
+//! while a single downscale loses symbols that only fill a corner of the
+//! frame. Large images are scanned through a grayscale pyramid instead: each
+//! level halves the one below it, symbols are searched for from the coarsest
+//! level up, and every candidate is decoded from a crop of the original image
+//! at native resolution.
+
+use crate::decoder::{apply_otsu_threshold, decode_tiers, try_decode_with_both, DecodeOptions};
+use crate::error::Result;
+use crate::locate::{catch_rqrr_panic, locate_with_rxing};
+use crate::types::{DecodeTrace, MultiDecodeResult, Region, RegionOfInterest, RoiSource};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, GrayImage};
+
+/// Images whose longer side exceeds this are scanned through the pyramid
+pub const PYRAMID_THRESHOLD: u32 = 2048;
+
+/// Levels are halved until their longer side is at most this
+const COARSEST_SIDE: u32 = 1024;
+
+/// Margin added around a candidate, as a fraction of its size (quiet zone plus detection slack)
+const CANDIDATE_MARGIN: f32 = 0.25;
+
+/// Margin around rxing's finder centres, which sit 3.5 modules inside the symbol
+const FINDER_MARGIN: f32 = 0.6;
+
+/// One pyramid level: a grayscale copy `scale` times smaller than the original
+pub(crate) struct Level {
+    pub image: GrayImage,
+    pub scale: f32,
+}
+
+/// Whether an image is large enough to be scanned through the pyramid
+pub fn needs_pyramid(img: &DynamicImage) -> bool {
+    let (width, height) = img.dimensions();
+    width.max(height) > PYRAMID_THRESHOLD
+}
+
+/// Grayscale levels below the original, coarsest first
+///
+/// Each level is built from the one above it, so only one full-resolution
+/// grayscale copy exists at a time.
+pub(crate) fn build_levels(img: &DynamicImage) -> Vec<Level> {
+    let (width, height) = img.dimensions();
+    if width.max(height) <= COARSEST_SIDE {
+        return Vec::new();
+    }
+    let half = |gray: &GrayImage| {
+        let (w, h) = gray.dimensions();
+        imageops::resize(gray, (w / 2).max(1), (h / 2).max(1), FilterType::Triangle)
+    };
+    let level = |image: GrayImage| Level {
+        scale: width as f32 / image.width() as f32,
+        image,
+    };
+
+    let mut levels = vec![level(half(&img.to_luma8()))];
+    while let Some(last) = levels.last().filter(|l| l.image.width().max(l.image.height()) > COARSEST_SIDE) {
+        let next = half(&last.image);
+        levels.push(level(next));
+    }
+    levels.reverse();
+    levels
+}
+
+/// Candidate symbol regions found in one level, mapped to native pixels
+///
+/// Uses rqrr's grid detector (every grid, on the level and its Otsu
+/// binarisation) and rxing's finder pattern detector.
+pub(crate) fn find_candidates(level: &Level, native: (u32, u32)) -> Vec<Region> {
+    let mut quads: Vec<[(f32, f32); 4]> = Vec::new();
+    let otsu = apply_otsu_threshold(&DynamicImage::ImageLuma8(level.image.clone())).map(|img| img.to_luma8());
+    for gray in std::iter::once(&level.image).chain(otsu.as_ref()) {
+        quads.extend(
+            catch_rqrr_panic(|| {
+                let mut prepared = rqrr::PreparedImage::prepare(gray.clone());
+                let grids = prepared.detect_grids();
+                grids.iter().map(|grid| grid.bounds.map(|p| (p.x as f32, p.y as f32))).collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
+        );
+    }
+    if let Some(location) = locate_with_rxing(&level.image) {
+        quads.push(location.corners);
+    }
+
+    let mut regions: Vec<Region> = Vec::new();
+    for quad in quads {
+        let Some(region) = native_region(&quad, level.scale, CANDIDATE_MARGIN, native) else {
+            continue;
+        };
+        if !regions.iter().any(|r| r.contains(region.center())) {
+            regions.push(region);
+        }
+    }
+    regions
+}
+
+/// Native region of the symbol a level decoded whole
+///
+/// The detectors behind [`find_candidates`] can miss a symbol whose modules
+/// are only a pixel or two wide while rxing's decoder still reads it, so the
+/// finder centres it reports are the fallback.
+fn decoded_region(level: &Level, native: (u32, u32), options: &DecodeOptions) -> Option<Region> {
+    if let Some(&region) = find_candidates(level, native).first() {
+        return Some(region);
+    }
+    let (width, height) = level.image.dimensions();
+    let results = rxing::helpers::detect_multiple_in_luma_with_hints(
+        level.image.as_raw().clone(),
+        width,
+        height,
+        &mut options.rxing_hints(),
+    )
+    .ok()?;
+    let points: Vec<(f32, f32)> = results.first()?.getPoints().iter().map(|p| (p.x, p.y)).collect();
+    native_region(&points, level.scale, FINDER_MARGIN, native)
+}
+
+/// Bounding box of level points in native pixels, with a margin
+fn native_region(points: &[(f32, f32)], scale: f32, margin: f32, native: (u32, u32)) -> Option<Region> {
+    let bounds = points.iter().fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |[x0, y0, x1, y1], &(x, y)| {
+        [x0.min(x * scale), y0.min(y * scale), x1.max(x * scale), y1.max(y * scale)]
+    });
+    Region::around(bounds, margin, native)
+}
+
+/// Scan a large image coarse to fine
+///
+/// Each level is first decoded whole (symbols that fill much of the frame
+/// read fine downsampled); then every candidate it reveals is cropped from
+/// the original and decoded at native resolution, capped at
+/// [`PYRAMID_THRESHOLD`] per side. If nothing reads, the whole image is
+/// decoded once at that cap. `upscale` enables the upscale tier on crops.
+pub(crate) fn scan(img: &DynamicImage, options: &DecodeOptions, upscale: bool) -> Result<MultiDecodeResult> {
+    let native = img.dimensions();
+    let mut tried: Vec<Region> = Vec::new();
+
+    for level in build_levels(img) {
+        if let Ok(result) = try_decode_with_both(&DynamicImage::ImageLuma8(level.image.clone()), options) {
+            // Still record where the symbol is, so scoring can crop to it
+            return Ok(match decoded_region(&level, native, options) {
+                Some(region) => from_region(result, region),
+                None => result,
+            });
+        }
+        for region in find_candidates(&level, native) {
+            if tried.iter().any(|r| r.contains(region.center())) {
+                continue;
+            }
+            tried.push(region);
+            let crop = bounded(img.crop_imm(region.x, region.y, region.width, region.height));
+            if let Ok(result) = decode_tiers(&crop, options, upscale) {
+                return Ok(from_region(result, region));
+            }
+        }
+    }
+
+    decode_tiers(&bounded(img.clone()), options, upscale)
+}
+
+/// Report the native region a result was read from
+///
+/// Replaces any region the tiers reported, which was in crop coordinates.
+fn from_region(mut result: MultiDecodeResult, region: Region) -> MultiDecodeResult {
+    if let Some(meta) = result.metadata.as_mut() {
+        let trace = meta.trace.get_or_insert_with(DecodeTrace::default);
+        trace.roi = Some(RegionOfInterest { region, source: RoiSource::Pyramid });
+    }
+    result
+}
+
+/// The part of a large image to score once `result` has decoded it
+///
+/// Stress variants and symbol analyses run on the native crop the symbol was
+/// decoded from, or on a bounded copy when it read from a whole level, so
+/// validation costs no more than decoding. `None` for images small enough to
+/// use as they are.
+pub(crate) fn symbol_view(img: &DynamicImage, result: &MultiDecodeResult) -> Option<DynamicImage> {
+    if !needs_pyramid(img) {
+        return None;
+    }
+    let roi = result
+        .metadata
+        .as_ref()
+        .and_then(|m| m.trace.as_ref())
+        .and_then(|t| t.roi)
+        .filter(|roi| roi.source == RoiSource::Pyramid);
+    Some(match roi {
+        Some(RegionOfInterest { region: Region { x, y, width, height }, .. }) => {
+            bounded(img.crop_imm(x, y, width, height))
+        }
+        None => bounded(img.clone()),
+    })
+}
+
+/// Downscale an image so neither side exceeds [`PYRAMID_THRESHOLD`]
+fn bounded(img: DynamicImage) -> DynamicImage {
+    if needs_pyramid(&img) {
+        img.thumbnail(PYRAMID_THRESHOLD, PYRAMID_THRESHOLD)
+    } else {
+        img
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::multi_decode_image;
+    use image::Luma;
+
+    #[test]
+    fn levels_halve_down_to_the_coarsest_side() {
+        let img = DynamicImage::new_luma8(6000, 4000);
+        let sizes: Vec<_> = build_levels(&img).iter().map(|l| (l.image.dimensions(), l.scale)).collect();
+        assert_eq!(sizes, [((750, 500), 8.0), ((1500, 1000), 4.0), ((3000, 2000), 2.0)]);
+        assert!(build_levels(&DynamicImage::new_luma8(800, 600)).is_empty());
+    }
+
+    #[test]
+    fn decodes_a_small_symbol_in_a_large_photo() {
+        let code = qrcode::QrCode::new(b"https://example.com/poster").unwrap();
+        let symbol = code.render::<Luma<u8>>().module_dimensions(3, 3).build();
+        let mut canvas = GrayImage::from_pixel(6000, 4000, Luma([255]));
+        imageops::overlay(&mut canvas, &symbol, 4300, 2900);
+        let img = DynamicImage::ImageLuma8(canvas);
+
+        assert!(needs_pyramid(&img));
+        let result = multi_decode_image(&img).unwrap();
+        assert_eq!(result.content, "https://example.com/poster");
+
+        // Scoring works on the symbol's crop, not the whole photo
+        let view = symbol_view(&img, &result).unwrap();
+        assert!(view.width() < 1000 && view.height() < 1000, "{:?}", view.dimensions());
+        assert_eq!(multi_decode_image(&view).unwrap().content, "https://example.com/poster");
+    }
+}
@@ -0,0 +1,326 @@
+//! Region-of-interest detection
+//!
+//! The preprocessing pool and brute-force tiers run dozens of variants over
+//! the image; when the symbol fills a small part of the frame most of that
+//! work is spent on background, and resizing shrinks the symbol. A quick
+//! localisation pass on a small copy finds the likely symbol region so those
+//! tiers can run on a crop instead. Sources are tried from most to least
+//! specific: rqrr grid bounds, a 1:1:3:1:1 finder pattern search, and a map
+//! of dense high-frequency texture.
+
+use crate::locate::{catch_rqrr_panic, otsu_level};
+use crate::types::{Region, RegionOfInterest, RoiSource};
+use image::{DynamicImage, GenericImageView, GrayImage};
+use std::collections::VecDeque;
+
+/// Localisation runs on a copy whose longer side is at most this
+const ANALYSIS_SIDE: u32 = 512;
+
+/// Margin added around the region, as a fraction of its longer side
+const ROI_MARGIN: f32 = 0.25;
+
+/// Regions covering more of the image than this are not worth cropping to
+const MAX_COVERAGE: f32 = 0.6;
+
+/// Side of a texture map cell, in analysis pixels
+const CELL: u32 = 8;
+
+/// Neighbouring pixels differing by more than this count as an edge
+const EDGE_STEP: u8 = 48;
+
+/// Share of pixel pairs in a cell that must be edges for it to count as busy
+const BUSY_DENSITY: f32 = 0.12;
+
+/// Likely symbol region of `img`, with margin, if it is worth cropping to
+pub fn locate_roi(img: &DynamicImage) -> Option<RegionOfInterest> {
+    let (width, height) = img.dimensions();
+    let small = analysis_copy(img);
+    let (bounds, source) = candidate_bounds(&small)?;
+
+    let sx = width as f32 / small.width() as f32;
+    let sy = height as f32 / small.height() as f32;
+    let [x0, y0, x1, y1] = bounds;
+    let region = Region::around([x0 * sx, y0 * sy, x1 * sx, y1 * sy], ROI_MARGIN, (width, height))?;
+    (region.coverage((width, height)) <= MAX_COVERAGE).then_some(RegionOfInterest { region, source })
+}
+
+/// Whether any source finds a likely symbol in `img`, however much of it the symbol covers
+///
+/// A cheap screen for work that would otherwise run the decode tiers on
+/// blank background.
+pub(crate) fn may_hold_symbol(img: &DynamicImage) -> bool {
+    candidate_bounds(&analysis_copy(img)).is_some()
+}
+
+/// Grayscale copy no larger than [`ANALYSIS_SIDE`]
+fn analysis_copy(img: &DynamicImage) -> GrayImage {
+    let (width, height) = img.dimensions();
+    if width.max(height) > ANALYSIS_SIDE {
+        img.thumbnail(ANALYSIS_SIDE, ANALYSIS_SIDE).to_luma8()
+    } else {
+        img.to_luma8()
+    }
+}
+
+/// Bounds of the likely symbol in an analysis copy, from the most specific source that finds one
+fn candidate_bounds(small: &GrayImage) -> Option<([f32; 4], RoiSource)> {
+    grid_bounds(small)
+        .map(|b| (b, RoiSource::Grid))
+        .or_else(|| finder_bounds(small).map(|b| (b, RoiSource::FinderPatterns)))
+        .or_else(|| texture_bounds(small).map(|b| (b, RoiSource::Texture)))
+}
+
+/// Bounds of the first grid rqrr detects
+fn grid_bounds(gray: &GrayImage) -> Option<[f32; 4]> {
+    catch_rqrr_panic(|| {
+        let mut prepared = rqrr::PreparedImage::prepare(gray.clone());
+        let grid = prepared.detect_grids().into_iter().next()?;
+        Some(bounding_box(grid.bounds.iter().map(|p| (p.x as f32, p.y as f32))))
+    })
+    .flatten()
+}
+
+/// A finder pattern centre and its module size
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f32,
+    y: f32,
+    module: f32,
+    hits: u32,
+}
+
+/// Bounds of the symbol outlined by at least two confirmed finder patterns
+///
+/// Finder centres sit 3.5 modules inside the symbol edge.
+pub(crate) fn finder_bounds(gray: &GrayImage) -> Option<[f32; 4]> {
+    let finders = confirmed_finders(gray);
+    if finders.len() < 2 {
+        return None;
+    }
+    let [x0, y0, x1, y1] = bounding_box(finders.iter().map(|f| (f.x, f.y)));
+    let edge = finders.iter().map(|f| f.module).fold(0.0, f32::max) * 3.5;
+    Some([x0 - edge, y0 - edge, x1 + edge, y1 + edge])
+}
+
+/// Module size of the confirmed finder patterns, in pixels
+///
+/// Finder patterns are drawn solid by most generators even when data
+/// modules are stylised, so they measure the pitch where run lengths cannot.
+pub(crate) fn finder_module_size(gray: &GrayImage) -> Option<f32> {
+    let mut modules: Vec<f32> = confirmed_finders(gray).iter().map(|f| f.module).collect();
+    modules.sort_by(f32::total_cmp);
+    modules.get(modules.len() / 2).copied()
+}
+
+/// Finder patterns hit on at least two rows
+///
+/// Rows are scanned for 1:1:3:1:1 dark-light runs, each hit is confirmed
+/// down its column, and hits from neighbouring rows are merged.
+fn confirmed_finders(gray: &GrayImage) -> Vec<Finder> {
+    let samples: Vec<f32> = gray.as_raw().iter().map(|&v| v as f32).collect();
+    let level = otsu_level(&samples);
+    let dark = |x: u32, y: u32| (gray.get_pixel(x, y).0[0] as f32) < level;
+
+    let mut finders: Vec<Finder> = Vec::new();
+    for y in 0..gray.height() {
+        let runs = row_runs(gray.width(), |x| dark(x, y));
+        for window in runs.windows(5) {
+            let [(start, a, true), (_, b, _), (_, c, _), (_, d, _), (_, e, _)] = *window else {
+                continue;
+            };
+            if !finder_ratio([a, b, c, d, e]) {
+                continue;
+            }
+            let cx = start + a + b + c / 2;
+            let Some((cy, module)) = confirm_vertical(gray.height(), y, |row| dark(cx, row)) else {
+                continue;
+            };
+            let x = cx as f32;
+            match finders
+                .iter_mut()
+                .find(|f| (f.x - x).abs() < f.module * 2.0 && (f.y - cy).abs() < f.module * 2.0)
+            {
+                Some(f) => f.hits += 1,
+                None => finders.push(Finder { x, y: cy, module, hits: 1 }),
+            }
+        }
+    }
+
+    finders.retain(|f| f.hits >= 2);
+    finders
+}
+
+/// Alternating runs along a line as (start, length, dark)
+fn row_runs(len: u32, dark: impl Fn(u32) -> bool) -> Vec<(u32, u32, bool)> {
+    let mut runs: Vec<(u32, u32, bool)> = Vec::new();
+    for i in 0..len {
+        let d = dark(i);
+        match runs.last_mut() {
+            Some(run) if run.2 == d => run.1 += 1,
+            _ => runs.push((i, 1, d)),
+        }
+    }
+    runs
+}
+
+/// Whether five run lengths match 1:1:3:1:1 within half a module
+fn finder_ratio(runs: [u32; 5]) -> bool {
+    let total: u32 = runs.iter().sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f32 / 7.0;
+    let slack = module / 2.0;
+    runs.iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&len, expected)| (len as f32 - module * expected).abs() < slack * expected)
+}
+
+/// Check the column through a row hit; returns the pattern's centre row and module size
+fn confirm_vertical(height: u32, y: u32, dark: impl Fn(u32) -> bool) -> Option<(f32, f32)> {
+    // Walk out from the centre: dark core, light ring, dark ring on each side
+    let walk = |step: i64| -> Option<[u32; 3]> {
+        let mut lens = [0u32; 3];
+        let mut pos = y as i64;
+        for (i, want_dark) in [true, false, true].into_iter().enumerate() {
+            while pos >= 0 && pos < height as i64 && dark(pos as u32) == want_dark {
+                lens[i] += 1;
+                pos += step;
+            }
+            if lens[i] == 0 {
+                return None;
+            }
+        }
+        Some(lens)
+    };
+    let up = walk(-1)?;
+    let down = walk(1)?;
+    let core = up[0] + down[0] - 1;
+    if !finder_ratio([up[2], up[1], core, down[1], down[2]]) {
+        return None;
+    }
+    let top = y as f32 - (up[0] as f32 - 1.0);
+    let module = (up.iter().sum::<u32>() + down.iter().sum::<u32>() - 1) as f32 / 7.0;
+    Some((top + core as f32 / 2.0, module))
+}
+
+/// Bounds of the largest connected patch of busy texture cells
+///
+/// A cell is busy when many neighbouring pixel pairs differ sharply, as
+/// they do across module edges. Needs at least four cells.
+pub(crate) fn texture_bounds(gray: &GrayImage) -> Option<[f32; 4]> {
+    let (width, height) = gray.dimensions();
+    let (cols, rows) = (width / CELL, height / CELL);
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+    let busy: Vec<bool> = (0..rows * cols)
+        .map(|i| cell_density(gray, (i % cols) * CELL, (i / cols) * CELL) >= BUSY_DENSITY)
+        .collect();
+
+    let mut seen = vec![false; busy.len()];
+    let mut best: Option<(usize, [u32; 4])> = None;
+    for start in 0..busy.len() {
+        if !busy[start] || seen[start] {
+            continue;
+        }
+        let mut queue = VecDeque::from([start]);
+        seen[start] = true;
+        let (mut count, mut bounds) = (0, [u32::MAX, u32::MAX, 0, 0]);
+        while let Some(i) = queue.pop_front() {
+            let (cx, cy) = (i as u32 % cols, i as u32 / cols);
+            count += 1;
+            bounds = [bounds[0].min(cx), bounds[1].min(cy), bounds[2].max(cx), bounds[3].max(cy)];
+            let neighbours = [
+                (cx > 0).then(|| i - 1),
+                (cx + 1 < cols).then(|| i + 1),
+                (cy > 0).then(|| i - cols as usize),
+                (cy + 1 < rows).then(|| i + cols as usize),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if busy[n] && !seen[n] {
+                    seen[n] = true;
+                    queue.push_back(n);
+                }
+            }
+        }
+        if best.is_none_or(|(c, _)| count > c) {
+            best = Some((count, bounds));
+        }
+    }
+
+    let (count, [c0, r0, c1, r1]) = best?;
+    (count >= 4).then(|| {
+        let cell = CELL as f32;
+        [c0 as f32 * cell, r0 as f32 * cell, (c1 + 1) as f32 * cell, (r1 + 1) as f32 * cell]
+    })
+}
+
+/// Share of horizontal and vertical pixel pairs in a cell that are edges
+fn cell_density(gray: &GrayImage, x0: u32, y0: u32) -> f32 {
+    let px = |x: u32, y: u32| gray.get_pixel(x, y).0[0];
+    let mut edges = 0u32;
+    for y in y0..y0 + CELL {
+        for x in x0..x0 + CELL {
+            if x + 1 < x0 + CELL && px(x, y).abs_diff(px(x + 1, y)) > EDGE_STEP {
+                edges += 1;
+            }
+            if y + 1 < y0 + CELL && px(x, y).abs_diff(px(x, y + 1)) > EDGE_STEP {
+                edges += 1;
+            }
+        }
+    }
+    edges as f32 / (2 * CELL * (CELL - 1)) as f32
+}
+
+fn bounding_box(points: impl Iterator<Item = (f32, f32)>) -> [f32; 4] {
+    points.fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |[x0, y0, x1, y1], (x, y)| {
+        [x0.min(x), y0.min(y), x1.max(x), y1.max(y)]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{imageops, Luma};
+
+    /// 800 x 600 white scene with a 4 px/module QR whose symbol starts at (416, 216)
+    fn scene() -> GrayImage {
+        let code = qrcode::QrCode::new(b"https://example.com/roi").unwrap();
+        let symbol = code.render::<Luma<u8>>().module_dimensions(4, 4).build();
+        let mut scene = GrayImage::from_pixel(800, 600, Luma([255]));
+        imageops::overlay(&mut scene, &symbol, 400, 200);
+        scene
+    }
+
+    fn assert_covers_symbol(bounds: [f32; 4]) {
+        // 25 modules of 4 px from (416, 216)
+        let [x0, y0, x1, y1] = bounds;
+        assert!(x0 <= 420.0 && y0 <= 220.0 && x1 >= 512.0 && y1 >= 312.0, "{bounds:?}");
+        assert!(x0 >= 380.0 && y0 >= 180.0 && x1 <= 570.0 && y1 <= 370.0, "{bounds:?}");
+    }
+
+    #[test]
+    fn finds_the_symbol_region() {
+        let roi = locate_roi(&DynamicImage::ImageLuma8(scene())).unwrap();
+        assert!(matches!(roi.source, RoiSource::Grid | RoiSource::FinderPatterns), "{roi:?}");
+        assert!(roi.region.contains((472.0, 272.0)));
+        assert!(roi.region.coverage((800, 600)) < 0.2);
+    }
+
+    #[test]
+    fn finder_patterns_and_texture_outline_the_symbol() {
+        let scene = scene();
+        assert_covers_symbol(finder_bounds(&scene).unwrap());
+
+        let texture = texture_bounds(&scene).unwrap();
+        let [x0, y0, x1, y1] = texture;
+        assert!(x0 >= 400.0 && y0 >= 200.0 && x1 <= 536.0 && y1 <= 336.0, "{texture:?}");
+    }
+
+    #[test]
+    fn blank_images_have_no_region() {
+        let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(640, 480, Luma([200])));
+        assert!(locate_roi(&blank).is_none());
+    }
+}
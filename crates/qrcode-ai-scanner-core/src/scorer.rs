@@ -2,11 +2,13 @@
 //!
 //! Runs stress tests on QR images and computes a score 0-100.
 
-use crate::decoder::multi_decode_image;
-use crate::error::{QraiError, Result};
-use crate::types::StressResults;
+use crate::decoder::{decode_without_upscale, multi_decode_image_with, DecodeOptions};
+use crate::error::Result;
+use crate::limits::ResourceLimits;
+use crate::micro::symbol_size;
+use crate::types::{QrMetadata, StressResults, Symbology};
 use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
 use rayon::prelude::*;
 
 /// Weights for each stress test component
@@ -26,11 +28,19 @@ const TOTAL_WEIGHT: u32 = WEIGHT_ORIGINAL
     + WEIGHT_LOW_CONTRAST
     + WEIGHT_MULTI_DECODER;
 
+/// EC budget usage below this is normal wear and costs nothing
+const EC_PENALTY_THRESHOLD: f32 = 0.25;
+/// Points lost when the worst block's EC budget is fully consumed
+const EC_PENALTY_MAX: f32 = 25.0;
+
+/// Points lost by an inverted (light on dark) symbol
+const INVERTED_PENALTY: u8 = 15;
+/// Points lost by a mirrored symbol
+const MIRRORED_PENALTY: u8 = 15;
+
 /// Run all stress tests on an image (from bytes)
 pub fn run_stress_tests(image_bytes: &[u8]) -> Result<StressResults> {
-    let img = image::load_from_memory(image_bytes)
-        .map_err(|e| QraiError::ImageLoad(e.to_string()))?;
-
+    let img = ResourceLimits::default().load_image(image_bytes)?;
     run_stress_tests_on_image(&img)
 }
 
@@ -38,8 +48,13 @@ pub fn run_stress_tests(image_bytes: &[u8]) -> Result<StressResults> {
 ///
 /// Uses parallel execution with rayon for better performance.
 pub fn run_stress_tests_on_image(img: &DynamicImage) -> Result<StressResults> {
+    run_stress_tests_with(img, &DecodeOptions::default())
+}
+
+/// Run stress tests, decoding each variant with the given options
+pub fn run_stress_tests_with(img: &DynamicImage, options: &DecodeOptions) -> Result<StressResults> {
     // Test original first (most important, fast path)
-    let original = test_decode(img);
+    let original = test_decode(img, options);
 
     // If original fails, no point in running other tests
     if !original {
@@ -67,7 +82,7 @@ pub fn run_stress_tests_on_image(img: &DynamicImage) -> Result<StressResults> {
     // Test all variants in parallel
     let results: Vec<(&str, bool)> = variants
         .par_iter()
-        .map(|(name, variant)| (*name, test_decode(variant)))
+        .map(|(name, variant)| (*name, test_variant(variant, options)))
         .collect();
 
     // Collect results
@@ -96,15 +111,20 @@ pub fn run_stress_tests_on_image(img: &DynamicImage) -> Result<StressResults> {
 
 /// Fast stress tests - only run a subset for quick validation
 pub fn run_fast_stress_tests(img: &DynamicImage) -> Result<StressResults> {
-    let original = test_decode(img);
+    run_fast_stress_tests_with(img, &DecodeOptions::default())
+}
+
+/// Fast stress tests, decoding each variant with the given options
+pub fn run_fast_stress_tests_with(img: &DynamicImage, options: &DecodeOptions) -> Result<StressResults> {
+    let original = test_decode(img, options);
 
     if !original {
         return Ok(StressResults::default());
     }
 
     // Only test downscale_50 and blur_light for fast mode
-    let downscale_50 = test_decode(&downscale(img, 0.5));
-    let blur_light = test_decode(&apply_blur(img, 1.0));
+    let downscale_50 = test_variant(&downscale(img, 0.5), options);
+    let blur_light = test_variant(&apply_blur(img, 1.0), options);
 
     Ok(StressResults {
         original: true,
@@ -116,8 +136,42 @@ pub fn run_fast_stress_tests(img: &DynamicImage) -> Result<StressResults> {
     })
 }
 
+/// Widen the margin of a Micro QR / rMQR image to a standard QR quiet zone
+///
+/// These symbologies only need a 2-module quiet zone, but the blur and
+/// downscale variants smear such a narrow margin into the frame edge. Padding
+/// keeps the stress tests measuring the symbol rather than how tightly the
+/// label was cropped. Returns `None` for standard QR or an unknown size.
+pub fn pad_for_stress(img: &DynamicImage, metadata: Option<&QrMetadata>) -> Option<DynamicImage> {
+    let meta = metadata.filter(|m| m.symbology.is_micro())?;
+    let (columns, rows) = symbol_size(meta.symbology, meta.version)?;
+    let quiet = meta.symbology.quiet_zone_modules();
+    let missing = Symbology::Qr.quiet_zone_modules().saturating_sub(quiet) as u32;
+
+    let (w, h) = img.dimensions();
+    let pitch = (w / (columns as u32 + 2 * quiet as u32)).min(h / (rows as u32 + 2 * quiet as u32));
+    let pad = pitch * missing;
+    if pad == 0 {
+        return None;
+    }
+
+    let mut canvas = RgbImage::from_pixel(w + 2 * pad, h + 2 * pad, Rgb([255, 255, 255]));
+    image::imageops::overlay(&mut canvas, &img.to_rgb8(), pad as i64, pad as i64);
+    Some(DynamicImage::ImageRgb8(canvas))
+}
+
 /// Calculate score from stress test results
 pub fn calculate_score(stress: &StressResults, num_decoders: usize) -> u8 {
+    calculate_score_for(stress, num_decoders, Symbology::Qr)
+}
+
+/// Calculate score from stress test results for a given symbology
+///
+/// Only rxing reads Micro QR, rMQR and the opt-in 2D symbologies, so the
+/// multi-decoder bonus is left out of their total instead of capping them
+/// below 100.
+pub fn calculate_score_for(stress: &StressResults, num_decoders: usize, symbology: Symbology) -> u8 {
+    let total = if symbology == Symbology::Qr { TOTAL_WEIGHT } else { TOTAL_WEIGHT - WEIGHT_MULTI_DECODER };
     let mut score: u32 = 0;
 
     if stress.original {
@@ -140,18 +194,26 @@ pub fn calculate_score(stress: &StressResults, num_decoders: usize) -> u8 {
     }
 
     // Bonus for multiple decoders succeeding
-    if num_decoders >= 2 {
+    if num_decoders >= 2 && symbology == Symbology::Qr {
         score += WEIGHT_MULTI_DECODER;
     }
 
     // Normalize to 0-100
-    ((score * 100) / TOTAL_WEIGHT).min(100) as u8
+    ((score * 100) / total).min(100) as u8
 }
 
 /// Calculate score for fast mode (adjusted weights)
 pub fn calculate_fast_score(stress: &StressResults, num_decoders: usize) -> u8 {
+    calculate_fast_score_for(stress, num_decoders, Symbology::Qr)
+}
+
+/// Calculate fast-mode score for a given symbology (see [`calculate_score_for`])
+pub fn calculate_fast_score_for(stress: &StressResults, num_decoders: usize, symbology: Symbology) -> u8 {
     // Fast mode only uses original, downscale_50, blur_light
-    let fast_total = WEIGHT_ORIGINAL + WEIGHT_DOWNSCALE_50 + WEIGHT_BLUR_LIGHT + WEIGHT_MULTI_DECODER;
+    let mut fast_total = WEIGHT_ORIGINAL + WEIGHT_DOWNSCALE_50 + WEIGHT_BLUR_LIGHT;
+    if symbology == Symbology::Qr {
+        fast_total += WEIGHT_MULTI_DECODER;
+    }
     let mut score: u32 = 0;
 
     if stress.original {
@@ -163,17 +225,45 @@ pub fn calculate_fast_score(stress: &StressResults, num_decoders: usize) -> u8 {
     if stress.blur_light {
         score += WEIGHT_BLUR_LIGHT;
     }
-    if num_decoders >= 2 {
+    if num_decoders >= 2 && symbology == Symbology::Qr {
         score += WEIGHT_MULTI_DECODER;
     }
 
     ((score * 100) / fast_total).min(100) as u8
 }
 
-/// Test if an image variant can be decoded
+/// Lower a score by how much of the EC budget the symbol already consumes
+///
+/// A code that only decodes by spending most of its Reed-Solomon capacity is
+/// one smudge away from failing, however well it did under stress tests.
+pub fn apply_ec_penalty(score: u8, ec_used_ratio: f32) -> u8 {
+    let excess = (ec_used_ratio.clamp(0.0, 1.0) - EC_PENALTY_THRESHOLD).max(0.0);
+    let penalty = excess / (1.0 - EC_PENALTY_THRESHOLD) * EC_PENALTY_MAX;
+    (score as f32 - penalty).round().clamp(0.0, 100.0) as u8
+}
+
+/// Lower a score for an inverted or mirrored symbol
+///
+/// Our decoder reads both, but many phone scanners try neither, so such a
+/// code fails for a share of its audience however clean it is.
+pub fn apply_orientation_penalty(score: u8, inverted: bool, mirrored: bool) -> u8 {
+    let penalty = if inverted { INVERTED_PENALTY } else { 0 } + if mirrored { MIRRORED_PENALTY } else { 0 };
+    score.saturating_sub(penalty)
+}
+
+/// Test if the original image can be decoded at all
 #[inline]
-fn test_decode(img: &DynamicImage) -> bool {
-    multi_decode_image(img).is_ok()
+fn test_decode(img: &DynamicImage, options: &DecodeOptions) -> bool {
+    multi_decode_image_with(img, options).is_ok()
+}
+
+/// Test if a degraded variant can still be decoded
+///
+/// Upscaling is skipped: it would restore a downscaled variant's resolution
+/// and hide the degradation being measured.
+#[inline]
+fn test_variant(img: &DynamicImage, options: &DecodeOptions) -> bool {
+    decode_without_upscale(img, options).is_ok()
 }
 
 /// Downscale image by a factor (0.5 = half size)
@@ -276,6 +366,49 @@ mod tests {
         assert!(score < 100);
     }
 
+    #[test]
+    fn micro_symbols_score_without_decoder_bonus() {
+        let stress = StressResults {
+            original: true,
+            downscale_50: true,
+            downscale_25: true,
+            blur_light: true,
+            blur_medium: true,
+            low_contrast: true,
+        };
+        assert_eq!(calculate_score_for(&stress, 1, Symbology::MicroQr), 100);
+        assert_eq!(calculate_fast_score_for(&stress, 1, Symbology::RectangularMicroQr), 100);
+        assert!(calculate_score_for(&stress, 1, Symbology::Qr) < 100);
+    }
+
+    #[test]
+    fn pads_micro_symbols_to_standard_quiet_zone() {
+        let meta = |symbology, version| QrMetadata {
+            symbology,
+            version,
+            error_correction: crate::types::ErrorCorrectionLevel::M,
+            modules: 0,
+            decoders_success: Vec::new(),
+            ec_used_ratio: None,
+            ec_usage: None,
+            eci: None,
+            charset: None,
+            segments: Vec::new(),
+            structured_append: None,
+            fnc1: None,
+            trace: None,
+            inverted: false,
+            mirrored: false,
+        };
+        // M2 is 13 modules; with its 2-module quiet zone, 17 modules of 10px
+        let img = DynamicImage::new_luma8(170, 170);
+        let padded = pad_for_stress(&img, Some(&meta(Symbology::MicroQr, 2))).unwrap();
+        assert_eq!(padded.dimensions(), (210, 210));
+
+        assert!(pad_for_stress(&img, Some(&meta(Symbology::Qr, 2))).is_none());
+        assert!(pad_for_stress(&img, None).is_none());
+    }
+
     #[test]
     fn stress_test_clean_qr_passes_most() {
         let qr_bytes = create_test_qr();
@@ -287,6 +420,21 @@ mod tests {
         assert!(stress.blur_light);
     }
 
+    #[test]
+    fn downscaled_variants_skip_upscaling() {
+        use crate::types::{DecodeStage, MultiDecodeResult};
+        use image::Luma;
+
+        // At a quarter of 5 px modules (1.25 px), the upscale tier reads it first
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let img = DynamicImage::ImageLuma8(code.render::<Luma<u8>>().module_dimensions(5, 5).build());
+        let quarter = downscale(&img, 0.25);
+        let stage = |result: Result<MultiDecodeResult>| result.ok().and_then(|r| r.metadata?.trace).map(|t| t.stage);
+
+        assert_eq!(stage(multi_decode_image_with(&quarter, &DecodeOptions::default())), Some(DecodeStage::Upscale));
+        assert_ne!(stage(decode_without_upscale(&quarter, &DecodeOptions::default())), Some(DecodeStage::Upscale));
+    }
+
     #[test]
     fn fast_stress_test_runs_subset() {
         let qr_bytes = create_test_qr();
@@ -315,6 +463,31 @@ mod tests {
         assert_eq!(new_h, orig_h / 2);
     }
 
+    #[test]
+    fn ec_penalty_spares_light_correction() {
+        assert_eq!(apply_ec_penalty(90, 0.0), 90);
+        assert_eq!(apply_ec_penalty(90, EC_PENALTY_THRESHOLD), 90);
+    }
+
+    #[test]
+    fn ec_penalty_grows_with_budget_used() {
+        let mild = apply_ec_penalty(90, 0.5);
+        let severe = apply_ec_penalty(90, 0.9);
+        assert!(mild < 90);
+        assert!(severe < mild);
+        assert_eq!(apply_ec_penalty(90, 1.0), 90 - EC_PENALTY_MAX as u8);
+        assert_eq!(apply_ec_penalty(10, 1.0), 0);
+    }
+
+    #[test]
+    fn orientation_penalty_applies_per_flag() {
+        assert_eq!(apply_orientation_penalty(90, false, false), 90);
+        assert_eq!(apply_orientation_penalty(90, true, false), 90 - INVERTED_PENALTY);
+        assert_eq!(apply_orientation_penalty(90, false, true), 90 - MIRRORED_PENALTY);
+        assert_eq!(apply_orientation_penalty(90, true, true), 90 - INVERTED_PENALTY - MIRRORED_PENALTY);
+        assert_eq!(apply_orientation_penalty(20, true, true), 0);
+    }
+
     #[test]
     fn parallel_stress_tests_consistent() {
         let qr_bytes = create_test_qr();
@@ -0,0 +1,542 @@
+//! Encoding-mode segments and character sets
+//!
+//! Parses the QR bitstream from the error-corrected data codewords, so the
+//! payload is available as raw bytes alongside the lossy `String` view. Also
+//! reports the ECI designator the symbol declares or, failing that, the
+//! character set the bytes look like.
+
+use rxing::common::{CharacterSet, Eci};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Data encoding mode of a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SegmentMode {
+    /// Digits 0-9, 10 bits per 3 characters
+    Numeric,
+    /// Digits, upper-case letters and ` $%*+-./:`, 11 bits per 2 characters
+    Alphanumeric,
+    /// 8-bit bytes in the declared (or default) character set
+    Byte,
+    /// Shift_JIS double-byte characters, 13 bits each
+    Kanji,
+}
+
+impl fmt::Display for SegmentMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SegmentMode::Numeric => "numeric",
+            SegmentMode::Alphanumeric => "alphanumeric",
+            SegmentMode::Byte => "byte",
+            SegmentMode::Kanji => "kanji",
+        })
+    }
+}
+
+/// One run of data in a single encoding mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    /// Encoding mode
+    pub mode: SegmentMode,
+    /// Character count from the segment header
+    pub char_count: u32,
+    /// Bytes the segment contributes to the raw payload
+    pub byte_len: u32,
+}
+
+/// Character set of the payload bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Charset {
+    /// 7-bit ASCII only (valid in every other set)
+    #[serde(rename = "US-ASCII")]
+    Ascii,
+    /// Latin-1, the QR default when no ECI is declared
+    #[serde(rename = "ISO-8859-1")]
+    Iso8859_1,
+    #[serde(rename = "UTF-8")]
+    Utf8,
+    #[serde(rename = "Shift_JIS")]
+    ShiftJis,
+    /// Declared through an ECI designator not listed above
+    Other,
+}
+
+impl Charset {
+    /// Character set assigned to an ECI designator
+    pub fn from_eci(designator: u32) -> Self {
+        match designator {
+            20 => Charset::ShiftJis,
+            1 | 3 => Charset::Iso8859_1,
+            26 => Charset::Utf8,
+            27 | 170 => Charset::Ascii,
+            _ => Charset::Other,
+        }
+    }
+
+    /// Best guess at the character set of undeclared payload bytes
+    ///
+    /// Kanji segments imply Shift_JIS. Otherwise valid multi-byte UTF-8 wins
+    /// over Shift_JIS, and anything else falls back to the ISO-8859-1 default.
+    pub fn detect(bytes: &[u8], segments: &[Segment]) -> Self {
+        if segments.iter().any(|s| s.mode == SegmentMode::Kanji) {
+            return Charset::ShiftJis;
+        }
+        if bytes.is_ascii() {
+            Charset::Ascii
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Charset::Utf8
+        } else if is_shift_jis(bytes) {
+            Charset::ShiftJis
+        } else {
+            Charset::Iso8859_1
+        }
+    }
+
+    /// Payload bytes as text in this character set
+    ///
+    /// ISO-8859-1 maps byte for byte and Shift_JIS goes through rxing's
+    /// decoder; everything else is read as UTF-8, lossily. `Other` only names
+    /// its table through the ECI designator, so [`PayloadEncoding::text`]
+    /// decodes it properly.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Charset::Iso8859_1 => bytes.iter().map(|&b| b as char).collect(),
+            Charset::ShiftJis => decode_as(CharacterSet::Shift_JIS, bytes),
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+/// Payload bytes as text, in the table the ECI designator names when the
+/// charset is `Other` and in `charset` otherwise
+pub(crate) fn decode_text(bytes: &[u8], eci: Option<u32>, charset: Charset) -> String {
+    match eci {
+        Some(designator) if charset == Charset::Other => decode_as(Eci::from(designator).into(), bytes),
+        _ => charset.decode(bytes),
+    }
+}
+
+/// Bytes through an rxing character set, lossily as UTF-8 if it has no decoder
+fn decode_as(charset: CharacterSet, bytes: &[u8]) -> String {
+    charset
+        .decode_replace(bytes)
+        .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned())
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Charset::Ascii => "US-ASCII",
+            Charset::Iso8859_1 => "ISO-8859-1",
+            Charset::Utf8 => "UTF-8",
+            Charset::ShiftJis => "Shift_JIS",
+            Charset::Other => "other",
+        })
+    }
+}
+
+/// Structured Append header: this symbol's place in a multi-symbol message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructuredAppend {
+    /// Position of this symbol in the message, from 0
+    pub index: u8,
+    /// Number of symbols the message is split across (1-16)
+    pub total: u8,
+    /// XOR of every byte of the complete message
+    pub parity: u8,
+}
+
+/// FNC1 mode declared in the bitstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fnc1Mode {
+    /// FNC1 in first position: the data are GS1 element strings
+    Gs1,
+    /// FNC1 in second position: an industry format named by its AIM application indicator
+    Industry(u8),
+}
+
+/// Payload bytes with their encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadEncoding {
+    /// Payload bytes of every segment, in order
+    pub raw_bytes: Vec<u8>,
+    /// First ECI designator in the bitstream
+    pub eci: Option<u32>,
+    /// Declared (ECI) or detected character set
+    pub charset: Charset,
+    /// Mode breakdown of the payload
+    pub segments: Vec<Segment>,
+    /// Structured Append header, if the symbol is part of a larger message
+    #[serde(default)]
+    pub structured_append: Option<StructuredAppend>,
+    /// FNC1 mode, if the symbol declares one
+    #[serde(default)]
+    pub fnc1: Option<Fnc1Mode>,
+}
+
+impl PayloadEncoding {
+    /// Encoding for bytes whose segment structure is unknown
+    pub fn from_bytes(raw_bytes: Vec<u8>) -> Self {
+        let charset = Charset::detect(&raw_bytes, &[]);
+        Self {
+            raw_bytes,
+            eci: None,
+            charset,
+            segments: Vec::new(),
+            structured_append: None,
+            fnc1: None,
+        }
+    }
+
+    /// Payload bytes as text in the declared or detected character set
+    pub fn text(&self) -> String {
+        decode_text(&self.raw_bytes, self.eci, self.charset)
+    }
+}
+
+/// MSB-first reader over the data codewords
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn available(&self) -> usize {
+        self.bytes.len() * 8 - self.pos
+    }
+
+    fn read(&mut self, bits: usize) -> Option<u32> {
+        if bits > self.available() {
+            return None;
+        }
+        let mut value = 0u32;
+        for _ in 0..bits {
+            let bit = (self.bytes[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+/// Parse the segments of a QR bitstream
+///
+/// `data` are the error-corrected data codewords (padding included) of a
+/// symbol of the given `version`, which sets the character count widths.
+/// Returns `None` if the bitstream is malformed.
+pub fn parse_segments(data: &[u8], version: u8) -> Option<PayloadEncoding> {
+    let group = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        27..=40 => 2,
+        _ => return None,
+    };
+    let mut reader = BitReader { bytes: data, pos: 0 };
+    let mut raw_bytes = Vec::new();
+    let mut segments = Vec::new();
+    let mut eci = None;
+    let mut structured_append = None;
+    let mut fnc1 = None;
+
+    while reader.available() >= 4 {
+        let mode = reader.read(4)?;
+        let start = raw_bytes.len();
+        let (mode, count) = match mode {
+            0b0000 => break,
+            0b0111 => {
+                let designator = read_eci(&mut reader)?;
+                eci.get_or_insert(designator);
+                continue;
+            }
+            // Structured append: 4-bit index, 4-bit total - 1, 8-bit parity
+            0b0011 => {
+                let (index, last, parity) = (reader.read(4)?, reader.read(4)?, reader.read(8)?);
+                structured_append.get_or_insert(StructuredAppend {
+                    index: index as u8,
+                    total: last as u8 + 1,
+                    parity: parity as u8,
+                });
+                continue;
+            }
+            // FNC1 in first position carries no data; second has an app indicator
+            0b0101 => {
+                fnc1.get_or_insert(Fnc1Mode::Gs1);
+                continue;
+            }
+            0b1001 => {
+                let indicator = reader.read(8)?;
+                fnc1.get_or_insert(Fnc1Mode::Industry(indicator as u8));
+                continue;
+            }
+            0b0001 => {
+                let count = reader.read([10, 12, 14][group])?;
+                read_numeric(&mut reader, count, &mut raw_bytes)?;
+                (SegmentMode::Numeric, count)
+            }
+            0b0010 => {
+                let count = reader.read([9, 11, 13][group])?;
+                read_alphanumeric(&mut reader, count, &mut raw_bytes)?;
+                if fnc1.is_some() {
+                    unescape_fnc1(&mut raw_bytes, start);
+                }
+                (SegmentMode::Alphanumeric, count)
+            }
+            0b0100 => {
+                let count = reader.read([8, 16, 16][group])?;
+                for _ in 0..count {
+                    raw_bytes.push(reader.read(8)? as u8);
+                }
+                (SegmentMode::Byte, count)
+            }
+            0b1000 => {
+                let count = reader.read([8, 10, 12][group])?;
+                read_kanji(&mut reader, count, &mut raw_bytes)?;
+                (SegmentMode::Kanji, count)
+            }
+            _ => return None,
+        };
+        segments.push(Segment {
+            mode,
+            char_count: count,
+            byte_len: (raw_bytes.len() - start) as u32,
+        });
+    }
+
+    let charset = match eci {
+        Some(designator) => Charset::from_eci(designator),
+        None => Charset::detect(&raw_bytes, &segments),
+    };
+    Some(PayloadEncoding {
+        raw_bytes,
+        eci,
+        charset,
+        segments,
+        structured_append,
+        fnc1,
+    })
+}
+
+fn read_eci(reader: &mut BitReader) -> Option<u32> {
+    let first = reader.read(8)?;
+    if first & 0x80 == 0 {
+        Some(first & 0x7F)
+    } else if first & 0xC0 == 0x80 {
+        Some(((first & 0x3F) << 8) | reader.read(8)?)
+    } else if first & 0xE0 == 0xC0 {
+        Some(((first & 0x1F) << 16) | reader.read(16)?)
+    } else {
+        None
+    }
+}
+
+fn read_numeric(reader: &mut BitReader, count: u32, out: &mut Vec<u8>) -> Option<()> {
+    let mut remaining = count;
+    while remaining > 0 {
+        let digits = remaining.min(3);
+        let value = reader.read([4, 7, 10][digits as usize - 1])?;
+        if value >= 10u32.pow(digits) {
+            return None;
+        }
+        out.extend(format!("{value:0width$}", width = digits as usize).bytes());
+        remaining -= digits;
+    }
+    Some(())
+}
+
+fn read_alphanumeric(reader: &mut BitReader, count: u32, out: &mut Vec<u8>) -> Option<()> {
+    let char_at = |i: u32| ALPHANUMERIC.get(i as usize).copied();
+    let mut remaining = count;
+    while remaining >= 2 {
+        let value = reader.read(11)?;
+        out.push(char_at(value / 45)?);
+        out.push(char_at(value % 45)?);
+        remaining -= 2;
+    }
+    if remaining == 1 {
+        out.push(char_at(reader.read(6)?)?);
+    }
+    Some(())
+}
+
+/// In FNC1 mode an alphanumeric `%` is a group separator and `%%` a literal `%`
+fn unescape_fnc1(bytes: &mut Vec<u8>, start: usize) {
+    let mut out = Vec::with_capacity(bytes.len() - start);
+    let mut i = start;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'%', Some(b'%')) => {
+                out.push(b'%');
+                i += 2;
+            }
+            (b'%', _) => {
+                out.push(0x1D);
+                i += 1;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    bytes.truncate(start);
+    bytes.extend(out);
+}
+
+fn read_kanji(reader: &mut BitReader, count: u32, out: &mut Vec<u8>) -> Option<()> {
+    for _ in 0..count {
+        let value = reader.read(13)?;
+        let packed = ((value / 0xC0) << 8) | (value % 0xC0);
+        let sjis = if packed < 0x1F00 { packed + 0x8140 } else { packed + 0xC140 };
+        out.extend_from_slice(&(sjis as u16).to_be_bytes());
+    }
+    Some(())
+}
+
+/// Whether every non-ASCII byte forms a valid Shift_JIS double-byte pair
+fn is_shift_jis(bytes: &[u8]) -> bool {
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x00..=0x7F | 0xA1..=0xDF => i += 1,
+            0x81..=0x9F | 0xE0..=0xEF => match bytes.get(i + 1) {
+                Some(0x40..=0x7E | 0x80..=0xFC) => i += 2,
+                _ => return false,
+            },
+            _ => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::bits::Bits;
+    use qrcode::types::{EcLevel, Version};
+
+    /// Data codewords for a hand-built bitstream
+    fn codewords(build: impl FnOnce(&mut Bits)) -> (Vec<u8>, u8) {
+        let mut bits = Bits::new(Version::Normal(2));
+        build(&mut bits);
+        bits.push_terminator(EcLevel::L).unwrap();
+        (bits.into_bytes(), 2)
+    }
+
+    #[test]
+    fn parses_mixed_mode_segments() {
+        let (data, version) = codewords(|bits| {
+            bits.push_alphanumeric_data(b"HELLO ").unwrap();
+            bits.push_numeric_data(b"0123456").unwrap();
+            bits.push_byte_data(b"ok").unwrap();
+        });
+        let encoding = parse_segments(&data, version).unwrap();
+
+        assert_eq!(encoding.raw_bytes, b"HELLO 0123456ok");
+        let modes: Vec<_> = encoding.segments.iter().map(|s| (s.mode, s.char_count)).collect();
+        assert_eq!(
+            modes,
+            vec![(SegmentMode::Alphanumeric, 6), (SegmentMode::Numeric, 7), (SegmentMode::Byte, 2)]
+        );
+        assert_eq!(encoding.eci, None);
+        assert_eq!(encoding.charset, Charset::Ascii);
+    }
+
+    #[test]
+    fn keeps_binary_bytes_and_declared_eci() {
+        let (data, version) = codewords(|bits| {
+            bits.push_eci_designator(26).unwrap();
+            bits.push_byte_data(&[0x00, 0xFF, 0x80]).unwrap();
+        });
+        let encoding = parse_segments(&data, version).unwrap();
+
+        assert_eq!(encoding.raw_bytes, vec![0x00, 0xFF, 0x80]);
+        assert_eq!(encoding.eci, Some(26));
+        assert_eq!(encoding.charset, Charset::Utf8);
+    }
+
+    #[test]
+    fn reads_structured_append_header() {
+        // Index 1, total - 1 = 2, parity 0xA5, then "hi" in byte mode and a terminator
+        let bits: Vec<u8> = "0011 0001 0010 10100101 0100 00000010 01101000 01101001 0000"
+            .bytes()
+            .filter(|b| !b.is_ascii_whitespace())
+            .map(|b| b - b'0')
+            .collect();
+        let data: Vec<u8> = bits
+            .chunks(8)
+            .map(|chunk| chunk.iter().enumerate().fold(0, |byte, (i, &bit)| byte | bit << (7 - i)))
+            .collect();
+
+        let encoding = parse_segments(&data, 1).unwrap();
+        assert_eq!(
+            encoding.structured_append,
+            Some(StructuredAppend { index: 1, total: 3, parity: 0xA5 })
+        );
+        assert_eq!(encoding.raw_bytes, b"hi");
+    }
+
+    #[test]
+    fn reads_fnc1_modes_and_group_separators() {
+        let (data, version) = codewords(|bits| {
+            bits.push_fnc1_first_position().unwrap();
+            bits.push_numeric_data(b"01095060001343521").unwrap();
+            bits.push_alphanumeric_data(b"0AB%21100%%").unwrap();
+        });
+        let encoding = parse_segments(&data, version).unwrap();
+
+        assert_eq!(encoding.fnc1, Some(Fnc1Mode::Gs1));
+        assert_eq!(encoding.raw_bytes, b"010950600013435210AB\x1D21100%");
+        assert_eq!(encoding.segments[1].byte_len, 10);
+
+        let (data, version) = codewords(|bits| {
+            bits.push_fnc1_second_position(37).unwrap();
+            bits.push_byte_data(b"%").unwrap();
+        });
+        let encoding = parse_segments(&data, version).unwrap();
+        assert_eq!(encoding.fnc1, Some(Fnc1Mode::Industry(37)));
+        assert_eq!(encoding.raw_bytes, b"%");
+    }
+
+    #[test]
+    fn decodes_kanji_to_shift_jis() {
+        // 点 and 茗 in Shift_JIS
+        let (data, version) = codewords(|bits| bits.push_kanji_data(&[0x93, 0x5F, 0xE4, 0xAA]).unwrap());
+        let encoding = parse_segments(&data, version).unwrap();
+
+        assert_eq!(encoding.raw_bytes, vec![0x93, 0x5F, 0xE4, 0xAA]);
+        assert_eq!(encoding.segments[0].mode, SegmentMode::Kanji);
+        assert_eq!(encoding.charset, Charset::ShiftJis);
+        assert_eq!(encoding.text(), "点茗");
+    }
+
+    #[test]
+    fn decodes_eci_character_sets() {
+        // ECI 7 is ISO-8859-5 (Cyrillic), which Charset only knows as Other
+        let (data, version) = codewords(|bits| {
+            bits.push_eci_designator(7).unwrap();
+            bits.push_byte_data(&[0xBF, 0xE0, 0xD8, 0xD2, 0xD5, 0xE2]).unwrap();
+        });
+        let encoding = parse_segments(&data, version).unwrap();
+
+        assert_eq!(encoding.charset, Charset::Other);
+        assert_eq!(encoding.text(), "Привет");
+        assert_eq!(Charset::ShiftJis.decode(&[0x82, 0xA0, 0x82, 0xA2]), "あい");
+    }
+
+    #[test]
+    fn detects_undeclared_charsets() {
+        assert_eq!(Charset::detect("café".as_bytes(), &[]), Charset::Utf8);
+        assert_eq!(Charset::detect(&[0x63, 0x61, 0x66, 0xE9], &[]), Charset::Iso8859_1);
+        assert_eq!(Charset::detect(&[0x82, 0xA0, 0x82, 0xA2], &[]), Charset::ShiftJis);
+        assert_eq!(Charset::detect(b"plain", &[]), Charset::Ascii);
+    }
+
+    #[test]
+    fn rejects_unknown_modes() {
+        assert!(parse_segments(&[0b1111_0000], 1).is_none());
+        assert!(parse_segments(&[], 41).is_none());
+    }
+}
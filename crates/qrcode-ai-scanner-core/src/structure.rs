@@ -0,0 +1,384 @@
+//! Function pattern integrity
+//!
+//! Artistic styling tends to eat into the structures scanners lock on to
+//! first. This module samples the original image at the located symbol and
+//! scores each of them:
+//! - Finder patterns: 1:1:3:1:1 run ratio along both axes, plus the 7x7 shape
+//! - Timing patterns: strict dark/light alternation on row and column 6
+//! - Alignment patterns: the 5x5 ring-and-dot shape at every version centre
+
+use crate::locate::{is_qr_size, locate_symbol, otsu_level, sample_luma, sample_modules, SymbolLocation};
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+
+/// Expected finder run lengths (in modules) along a line through its centre
+const FINDER_RATIO: [f32; 5] = [1.0, 1.0, 3.0, 1.0, 1.0];
+
+/// Runs shorter than this fraction of a module are treated as styling noise
+const MIN_RUN_MODULES: f32 = 0.25;
+
+/// Scanline samples per module when measuring finder ratios
+const SAMPLES_PER_MODULE: f32 = 8.0;
+
+/// Which corner a finder pattern sits in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FinderPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+}
+
+/// Health of a single finder pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FinderHealth {
+    pub position: FinderPosition,
+    /// Centre of the pattern in image pixels
+    pub center: (f32, f32),
+    /// How close the horizontal/vertical runs are to 1:1:3:1:1 (0-100)
+    pub ratio_score: u8,
+    /// Share of the 7x7 modules matching the expected pattern (0-100)
+    pub shape_score: u8,
+    /// Combined health (0-100)
+    pub score: u8,
+}
+
+/// Health of a single alignment pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlignmentHealth {
+    /// Centre module column
+    pub x: u8,
+    /// Centre module row
+    pub y: u8,
+    /// Share of the 5x5 modules matching the expected pattern (0-100)
+    pub score: u8,
+}
+
+/// Integrity of the symbol's function patterns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureHealth {
+    /// Modules per side of the analysed symbol
+    pub modules: u8,
+    /// Finder patterns (top-left, top-right, bottom-left)
+    pub finders: Vec<FinderHealth>,
+    /// Alternation of the horizontal timing pattern on row 6 (0-100)
+    pub timing_horizontal: u8,
+    /// Alternation of the vertical timing pattern on column 6 (0-100)
+    pub timing_vertical: u8,
+    /// Alignment patterns (empty for version 1)
+    pub alignment: Vec<AlignmentHealth>,
+    /// Overall structural health (0-100)
+    pub score: u8,
+}
+
+/// Locate the symbol and analyse its function patterns
+///
+/// Returns `None` when no symbol can be located in the image.
+pub fn analyze_structure(img: &DynamicImage) -> Option<StructureHealth> {
+    let location = locate_symbol(img)?;
+    analyze_at(&img.to_luma8(), &location)
+}
+
+/// Analyse the function patterns of a symbol at a known location
+///
+/// Returns `None` when the location's size is not a standard QR symbol.
+pub fn analyze_at(gray: &GrayImage, location: &SymbolLocation) -> Option<StructureHealth> {
+    let size = location.size;
+    if !is_qr_size(size) {
+        return None;
+    }
+    let samples = sample_modules(gray, location);
+    let level = otsu_level(&samples);
+    let dark = |x: usize, y: usize| samples[y * size + x] < level;
+
+    let finders: Vec<FinderHealth> = [
+        (FinderPosition::TopLeft, 0, 0),
+        (FinderPosition::TopRight, size.saturating_sub(7), 0),
+        (FinderPosition::BottomLeft, 0, size.saturating_sub(7)),
+    ]
+    .into_iter()
+    .map(|(position, x, y)| {
+        let shape = pattern_match(&dark, x, y, 7, finder_expected);
+        let horizontal = finder_ratio_score(gray, location, level, (x as f32, y as f32 + 3.5), (1.0, 0.0));
+        let vertical = finder_ratio_score(gray, location, level, (x as f32 + 3.5, y as f32), (0.0, 1.0));
+        let ratio = (horizontal + vertical) / 2.0;
+        FinderHealth {
+            position,
+            center: location.map(x as f32 + 3.5, y as f32 + 3.5),
+            ratio_score: to_score(ratio),
+            shape_score: to_score(shape),
+            score: to_score((ratio + shape) / 2.0),
+        }
+    })
+    .collect();
+
+    let timing_horizontal = to_score(timing_score(size, |i| dark(i, 6)));
+    let timing_vertical = to_score(timing_score(size, |i| dark(6, i)));
+
+    let alignment: Vec<AlignmentHealth> = alignment_centers(size)
+        .into_iter()
+        .map(|(x, y)| AlignmentHealth {
+            x: x as u8,
+            y: y as u8,
+            score: to_score(pattern_match(&dark, x - 2, y - 2, 5, alignment_expected)),
+        })
+        .collect();
+
+    let finder_avg = finders.iter().map(|f| f.score as f32).sum::<f32>() / finders.len() as f32;
+    let timing_avg = (timing_horizontal as f32 + timing_vertical as f32) / 2.0;
+    let score = if alignment.is_empty() {
+        finder_avg * 0.65 + timing_avg * 0.35
+    } else {
+        let alignment_avg = alignment.iter().map(|a| a.score as f32).sum::<f32>() / alignment.len() as f32;
+        finder_avg * 0.5 + timing_avg * 0.25 + alignment_avg * 0.25
+    };
+
+    Some(StructureHealth {
+        modules: size.min(u8::MAX as usize) as u8,
+        finders,
+        timing_horizontal,
+        timing_vertical,
+        alignment,
+        score: score.round().clamp(0.0, 100.0) as u8,
+    })
+}
+
+fn to_score(fraction: f32) -> u8 {
+    (fraction * 100.0).round().clamp(0.0, 100.0) as u8
+}
+
+/// Expected colour of module (`dx`, `dy`) inside a 7x7 finder
+fn finder_expected(dx: usize, dy: usize) -> bool {
+    let ring = dx.min(dy).min(6 - dx).min(6 - dy);
+    ring != 1
+}
+
+/// Expected colour of module (`dx`, `dy`) inside a 5x5 alignment pattern
+fn alignment_expected(dx: usize, dy: usize) -> bool {
+    let ring = dx.min(dy).min(4 - dx).min(4 - dy);
+    ring != 1
+}
+
+/// Fraction of an `n` x `n` block at (`x0`, `y0`) matching `expected`
+fn pattern_match(
+    dark: &impl Fn(usize, usize) -> bool,
+    x0: usize,
+    y0: usize,
+    n: usize,
+    expected: fn(usize, usize) -> bool,
+) -> f32 {
+    let mut matching = 0;
+    for dy in 0..n {
+        for dx in 0..n {
+            if dark(x0 + dx, y0 + dy) == expected(dx, dy) {
+                matching += 1;
+            }
+        }
+    }
+    matching as f32 / (n * n) as f32
+}
+
+/// Fraction of timing modules (between the finder separators) that alternate correctly
+fn timing_score(size: usize, dark: impl Fn(usize) -> bool) -> f32 {
+    if size < 17 {
+        return 0.0;
+    }
+    let range = 8..size - 8;
+    let total = range.len();
+    let matching = range.filter(|&i| dark(i) == (i % 2 == 0)).count();
+    matching as f32 / total.max(1) as f32
+}
+
+/// Alignment pattern centres for a symbol of `size` modules, excluding finder corners
+fn alignment_centers(size: usize) -> Vec<(usize, usize)> {
+    let Ok(version) = rxing::qrcode::decoder::Version::getProvisionalVersionForDimension(size as u32) else {
+        return Vec::new();
+    };
+    let centers = version.getAlignmentPatternCenters();
+    let last = centers.len().saturating_sub(1);
+
+    let mut result = Vec::new();
+    for (i, &y) in centers.iter().enumerate() {
+        for (j, &x) in centers.iter().enumerate() {
+            let in_finder = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+            if !in_finder {
+                result.push((x as usize, y as usize));
+            }
+        }
+    }
+    result
+}
+
+/// Score how closely a scanline through a finder centre follows 1:1:3:1:1
+///
+/// The line starts one module before the pattern (`start`, in module
+/// coordinates) and runs for 9 modules along `dir`, so the quiet zone or
+/// separator bounds the pattern on both sides.
+fn finder_ratio_score(
+    gray: &GrayImage,
+    location: &SymbolLocation,
+    level: f32,
+    start: (f32, f32),
+    dir: (f32, f32),
+) -> f32 {
+    let steps = (9.0 * SAMPLES_PER_MODULE) as usize;
+    let step = 1.0 / SAMPLES_PER_MODULE;
+
+    // (is_dark, length in modules)
+    let mut runs: Vec<(bool, f32)> = Vec::new();
+    for i in 0..steps {
+        let t = -1.0 + (i as f32 + 0.5) * step;
+        let (px, py) = location.map(start.0 + dir.0 * t, start.1 + dir.1 * t);
+        let is_dark = sample_luma(gray, px, py, 0.0) < level;
+        match runs.last_mut() {
+            Some((d, len)) if *d == is_dark => *len += step,
+            _ => runs.push((is_dark, step)),
+        }
+    }
+
+    merge_short_runs(&mut runs);
+
+    let Some(first_dark) = runs.iter().position(|&(d, _)| d) else {
+        return 0.0;
+    };
+    let Some(pattern) = runs.get(first_dark..first_dark + 5) else {
+        return 0.0;
+    };
+
+    let total: f32 = pattern.iter().map(|&(_, len)| len).sum();
+    let unit = total / 7.0;
+    if unit <= 0.0 {
+        return 0.0;
+    }
+    let error: f32 = pattern
+        .iter()
+        .zip(FINDER_RATIO)
+        .map(|(&(_, len), expected)| (len / unit - expected).abs())
+        .sum::<f32>()
+        / 7.0;
+    (1.0 - error).clamp(0.0, 1.0)
+}
+
+/// Fold runs shorter than [`MIN_RUN_MODULES`] into their neighbours
+fn merge_short_runs(runs: &mut Vec<(bool, f32)>) {
+    loop {
+        let shortest = runs
+            .iter()
+            .enumerate()
+            .filter(|(_, &(_, len))| len < MIN_RUN_MODULES)
+            .min_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+            .map(|(i, _)| i);
+        let Some(i) = shortest else { break };
+        if runs.len() == 1 {
+            break;
+        }
+
+        let (_, len) = runs.remove(i);
+        if i > 0 && i < runs.len() {
+            // Neighbours share a colour: join them around the removed run
+            let (_, next) = runs.remove(i);
+            runs[i - 1].1 += len + next;
+        } else if i > 0 {
+            runs[i - 1].1 += len;
+        } else {
+            runs[0].1 += len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    const MODULE_PX: u32 = 6;
+    const QUIET_PX: u32 = 4 * MODULE_PX;
+
+    fn render(code: &qrcode::QrCode) -> GrayImage {
+        code.render::<Luma<u8>>()
+            .module_dimensions(MODULE_PX, MODULE_PX)
+            .build()
+    }
+
+    /// Paint module (`x`, `y`) with the given luma
+    fn paint(img: &mut GrayImage, x: u32, y: u32, value: u8) {
+        for py in 0..MODULE_PX {
+            for px in 0..MODULE_PX {
+                img.put_pixel(QUIET_PX + x * MODULE_PX + px, QUIET_PX + y * MODULE_PX + py, Luma([value]));
+            }
+        }
+    }
+
+    #[test]
+    fn clean_symbol_is_fully_healthy() {
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let img = DynamicImage::ImageLuma8(render(&code));
+        let health = analyze_structure(&img).expect("symbol located");
+
+        assert_eq!(health.modules as usize, code.width());
+        assert_eq!(health.finders.len(), 3);
+        for finder in &health.finders {
+            assert!(finder.ratio_score >= 90, "{finder:?}");
+            assert_eq!(finder.shape_score, 100, "{finder:?}");
+        }
+        assert_eq!(health.timing_horizontal, 100);
+        assert_eq!(health.timing_vertical, 100);
+        assert!(health.score >= 95);
+    }
+
+    #[test]
+    fn broken_timing_row_is_reported() {
+        let code = qrcode::QrCode::new(b"https://example.com").unwrap();
+        let mut gray = render(&code);
+        let size = code.width() as u32;
+        for x in 8..size - 8 {
+            paint(&mut gray, x, 6, 255);
+        }
+
+        let img = DynamicImage::ImageLuma8(gray);
+        let health = analyze_structure(&img).unwrap();
+        assert!(health.timing_horizontal <= 60, "{}", health.timing_horizontal);
+        assert_eq!(health.timing_vertical, 100);
+    }
+
+    #[test]
+    fn alignment_patterns_follow_version_table() {
+        let code = qrcode::QrCode::with_version(b"alignment", qrcode::Version::Normal(7), qrcode::EcLevel::M)
+            .unwrap();
+        let mut gray = render(&code);
+
+        // Version 7 has centres at 6, 22, 38: six patterns outside the finders
+        let clean = analyze_at(&gray, &locate_in(&gray)).unwrap();
+        assert_eq!(clean.alignment.len(), 6);
+        assert!(clean.alignment.iter().all(|a| a.score == 100));
+
+        // Fill the light ring of the centre pattern
+        for (dx, dy) in [(-1i32, -1i32), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+            paint(&mut gray, (22 + dx) as u32, (22 + dy) as u32, 0);
+        }
+        let damaged = analyze_at(&gray, &locate_in(&gray)).unwrap();
+        let centre = damaged.alignment.iter().find(|a| a.x == 22 && a.y == 22).unwrap();
+        assert!(centre.score <= 70, "{centre:?}");
+        assert!(damaged.score < clean.score);
+    }
+
+    #[test]
+    fn sizes_that_are_not_qr_symbols_are_rejected() {
+        let gray = GrayImage::new(40, 40);
+        for size in [0, 5, 19, 22] {
+            let location = SymbolLocation::new(size, [(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)]).unwrap();
+            assert_eq!(analyze_at(&gray, &location), None);
+        }
+    }
+
+    #[test]
+    fn merges_noise_runs() {
+        let mut runs = vec![(false, 1.0), (true, 1.0), (false, 0.1), (true, 0.5), (false, 1.0)];
+        merge_short_runs(&mut runs);
+        assert_eq!(runs.len(), 3);
+        assert!((runs[1].1 - 1.6).abs() < 1e-5);
+    }
+
+    fn locate_in(gray: &GrayImage) -> SymbolLocation {
+        crate::locate::locate_in_luma(gray).expect("symbol located")
+    }
+}
@@ -0,0 +1,243 @@
+//! Tiled scanning for small symbols in large scenes
+//!
+//! The preprocessing tiers resize whole images to a few hundred pixels, which
+//! shrinks a symbol covering a few percent of a photo below decodable size.
+//! A tiled scan cuts the image into overlapping tiles, reads every symbol in
+//! each tile at native resolution, and merges what the tiles read into
+//! detections positioned in the whole image.
+
+use crate::decoder::{decode_without_brute_force, try_decode_with_both, DecodeOptions};
+use crate::locate::locate_symbol;
+use crate::roi::may_hold_symbol;
+use crate::types::{Detection, MultiDecodeResult, Region, Symbology};
+use image::{DynamicImage, GenericImageView};
+use rayon::prelude::*;
+
+/// Side of a tile, in pixels
+pub const TILE_SIZE: u32 = 1024;
+
+/// Overlap between neighbouring tiles; any symbol up to this size lies
+/// wholly inside at least one tile
+pub const TILE_OVERLAP: u32 = 512;
+
+/// Margin around the points rxing reports for a symbol, as a fraction of
+/// their span; QR finder centres sit 3.5 modules inside the symbol
+const SYMBOL_MARGIN: f32 = 0.6;
+
+/// Symbol corners clockwise from top-left, as in [`Detection::corners`]
+type Corners = [(f32, f32); 4];
+
+/// Tiles covering a `width` x `height` image, row by row
+pub fn tile_grid(width: u32, height: u32) -> Vec<Region> {
+    let xs = tile_starts(width);
+    let ys = tile_starts(height);
+    ys.iter()
+        .flat_map(|&y| {
+            xs.iter().map(move |&x| Region {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            })
+        })
+        .collect()
+}
+
+/// Tile offsets along one axis; the last tile is flush with the far edge
+fn tile_starts(len: u32) -> Vec<u32> {
+    if len <= TILE_SIZE {
+        return vec![0];
+    }
+    let stride = TILE_SIZE - TILE_OVERLAP;
+    let last = len - TILE_SIZE;
+    let mut starts: Vec<u32> = (0..last).step_by(stride as usize).collect();
+    starts.push(last);
+    starts
+}
+
+/// Decode every tile and merge the reads, in tile order
+pub fn scan_tiles(img: &DynamicImage, options: &DecodeOptions) -> Vec<Detection> {
+    let (width, height) = img.dimensions();
+    let reads: Vec<Detection> = tile_grid(width, height)
+        .par_iter()
+        .flat_map_iter(|&tile| scan_tile(img, tile, options))
+        .collect();
+    merge(reads)
+}
+
+/// Every symbol in one tile, in image coordinates
+///
+/// One multi-symbol rxing pass reads every clean symbol in the tile. Only
+/// when it reads nothing, and a quick screen finds something symbol-like,
+/// do the decode tiers run, without brute force: most tiles of a scene are
+/// background, and the pool or random search on each would dominate the scan.
+fn scan_tile(img: &DynamicImage, tile: Region, options: &DecodeOptions) -> Vec<Detection> {
+    let crop = img.crop_imm(tile.x, tile.y, tile.width, tile.height);
+    let reads = read_every_symbol(&crop, options);
+    if !reads.is_empty() {
+        return reads.into_iter().map(|(result, corners)| detection(result, corners, tile)).collect();
+    }
+    if !may_hold_symbol(&crop) {
+        return Vec::new();
+    }
+    decode_without_brute_force(&crop, options)
+        .map(|result| {
+            let corners = symbol_corners(&crop, &result);
+            detection(result, corners, tile)
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Each symbol rxing finds in a tile, re-read from a crop around it for its
+/// metadata, with corners in tile pixels
+fn read_every_symbol(tile: &DynamicImage, options: &DecodeOptions) -> Vec<(MultiDecodeResult, Option<Corners>)> {
+    let luma = tile.to_luma8();
+    let (width, height) = luma.dimensions();
+    let Ok(found) = rxing::helpers::detect_multiple_in_luma_with_hints(
+        luma.into_raw(),
+        width,
+        height,
+        &mut options.rxing_hints(),
+    ) else {
+        return Vec::new();
+    };
+
+    found
+        .iter()
+        .filter_map(|read| {
+            let bounds = read.getPoints().iter().fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |[x0, y0, x1, y1], p| {
+                [x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)]
+            });
+            let region = Region::around(bounds, SYMBOL_MARGIN, (width, height))?;
+            let symbol = tile.crop_imm(region.x, region.y, region.width, region.height);
+            let result = try_decode_with_both(&symbol, options).ok().filter(|r| r.content == read.getText())?;
+            let corners = symbol_corners(&symbol, &result)
+                .map(|corners| corners.map(|(x, y)| (x + region.x as f32, y + region.y as f32)));
+            Some((result, corners))
+        })
+        .collect()
+}
+
+/// Corners of a QR-family symbol in `img`, when it can be located
+fn symbol_corners(img: &DynamicImage, result: &MultiDecodeResult) -> Option<Corners> {
+    let symbology = result.metadata.as_ref().map(|m| m.symbology).unwrap_or_default();
+    Symbology::QR_FAMILY
+        .contains(&symbology)
+        .then(|| locate_symbol(img))
+        .flatten()
+        .map(|location| location.corners)
+}
+
+/// A tile read, moved from tile to image coordinates
+fn detection(mut result: MultiDecodeResult, corners: Option<Corners>, tile: Region) -> Detection {
+    // Regions of interest are found per tile; move them to image coordinates
+    if let Some(roi) = result.metadata.as_mut().and_then(|m| m.trace.as_mut()).and_then(|t| t.roi.as_mut()) {
+        roi.region.x += tile.x;
+        roi.region.y += tile.y;
+    }
+    Detection {
+        content: result.content,
+        raw_bytes: result.raw_bytes,
+        metadata: result.metadata,
+        corners: corners.map(|corners| corners.map(|(x, y)| (x + tile.x as f32, y + tile.y as f32))),
+        tile,
+    }
+}
+
+/// Collapse reads of the same symbol from overlapping tiles
+///
+/// Two reads are the same symbol when their content matches and one lies
+/// over the other. Located reads are preferred over unlocated ones.
+fn merge(reads: Vec<Detection>) -> Vec<Detection> {
+    let mut merged: Vec<Detection> = Vec::new();
+    for read in reads {
+        let duplicate = merged.iter_mut().find(|kept| {
+            kept.content == read.content
+                && (footprint(kept).contains(footprint(&read).center())
+                    || footprint(&read).contains(footprint(kept).center()))
+        });
+        match duplicate {
+            Some(kept) if kept.corners.is_none() && read.corners.is_some() => *kept = read,
+            Some(_) => {}
+            None => merged.push(read),
+        }
+    }
+    merged
+}
+
+/// Where a detection sits: the symbol's bounding box, or its tile if unlocated
+fn footprint(detection: &Detection) -> Region {
+    let Some(corners) = detection.corners else {
+        return detection.tile;
+    };
+    let (min_x, max_x) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), p| (lo.min(p.0), hi.max(p.0)));
+    let (min_y, max_y) = corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+    Region {
+        x: min_x.max(0.0) as u32,
+        y: min_y.max(0.0) as u32,
+        width: (max_x - min_x).ceil().max(1.0) as u32,
+        height: (max_y - min_y).ceil().max(1.0) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{imageops, GrayImage, Luma};
+
+    #[test]
+    fn tiles_overlap_and_cover_the_image() {
+        let tiles = tile_grid(2500, 900);
+        let xs: Vec<_> = tiles.iter().map(|t| t.x).collect();
+        assert_eq!(xs, [0, 512, 1024, 1476]);
+        assert!(tiles.iter().all(|t| t.y == 0 && t.height == 900));
+        assert_eq!(tile_grid(640, 480), [Region { x: 0, y: 0, width: 640, height: 480 }]);
+    }
+
+    #[test]
+    fn finds_small_symbols_in_global_coordinates() {
+        let symbol = |text: &str| {
+            let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+            code.render::<Luma<u8>>().module_dimensions(4, 4).build()
+        };
+        let mut scene = GrayImage::from_pixel(2000, 1000, Luma([255]));
+        imageops::overlay(&mut scene, &symbol("left"), 200, 300);
+        imageops::overlay(&mut scene, &symbol("right"), 1500, 700);
+
+        let found = scan_tiles(&DynamicImage::ImageLuma8(scene), &DecodeOptions::default());
+        let mut contents: Vec<_> = found.iter().map(|d| d.content.as_str()).collect();
+        contents.sort_unstable();
+        assert_eq!(contents, ["left", "right"]);
+
+        let right = found.iter().find(|d| d.content == "right").unwrap();
+        let (x, y) = right.corners.expect("symbol is located")[0];
+        // Quiet zone is 4 modules of 4 px
+        assert!((x - 1516.0).abs() < 4.0 && (y - 716.0).abs() < 4.0, "top-left at ({x}, {y})");
+    }
+
+    #[test]
+    fn reads_every_symbol_in_one_tile() {
+        let symbol = |text: &str| {
+            let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+            code.render::<Luma<u8>>().module_dimensions(4, 4).build()
+        };
+        let mut scene = GrayImage::from_pixel(900, 700, Luma([255]));
+        imageops::overlay(&mut scene, &symbol("first"), 50, 60);
+        imageops::overlay(&mut scene, &symbol("second"), 550, 400);
+        assert_eq!(tile_grid(900, 700).len(), 1);
+
+        let found = scan_tiles(&DynamicImage::ImageLuma8(scene), &DecodeOptions::default());
+        let mut contents: Vec<_> = found.iter().map(|d| d.content.as_str()).collect();
+        contents.sort_unstable();
+        assert_eq!(contents, ["first", "second"]);
+        assert!(found.iter().all(|d| d.corners.is_some()));
+    }
+
+    #[test]
+    fn blank_tiles_are_screened_out() {
+        let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(1024, 1024, Luma([255])));
+        assert!(!may_hold_symbol(&blank));
+        assert!(scan_tiles(&blank, &DecodeOptions::default()).is_empty());
+    }
+}
@@ -1,3 +1,10 @@
+use crate::fidelity::ModuleFidelity;
+use crate::grid::ModuleGrid;
+use crate::payload::ParsedPayload;
+use crate::print::PrintRecommendation;
+use crate::segments::{Charset, Fnc1Mode, Segment, StructuredAppend};
+use crate::structure::StructureHealth;
+use crate::url_risk::UrlRisk;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -10,23 +17,188 @@ pub struct ValidationResult {
     pub decodable: bool,
     /// Decoded content of the QR code
     pub content: Option<String>,
+    /// Payload bytes exactly as encoded, if the bitstream could be read
+    pub raw_bytes: Option<Vec<u8>>,
     /// QR code technical metadata
     pub metadata: Option<QrMetadata>,
     /// Results of stress tests used for scoring
     pub stress_results: StressResults,
+    /// Print size and scan distance recommendation (default camera model)
+    pub print: Option<PrintRecommendation>,
+    /// Finder, timing and alignment pattern integrity
+    pub structure: Option<StructureHealth>,
+    /// Flipped modules and EC budget used, against the rebuilt reference symbol
+    pub fidelity: Option<ModuleFidelity>,
+    /// Typed content (URL, Wi-Fi, contact, ...) with field validation errors
+    pub payload: Option<ParsedPayload>,
+    /// Phishing heuristics for link content
+    pub url_risk: Option<UrlRisk>,
 }
 
 /// Technical metadata about the QR code
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QrMetadata {
-    /// QR code version (1-40, determines size)
+    /// Symbol family: standard QR, Micro QR or rMQR
+    #[serde(default)]
+    pub symbology: Symbology,
+    /// Version within the symbology (QR 1-40, Micro QR M1-M4, rMQR 1-32), determines size
     pub version: u8,
     /// Error correction level
     pub error_correction: ErrorCorrectionLevel,
-    /// Number of modules (21, 25, 29, etc.)
+    /// Number of modules per side (21, 25, 29, etc.; columns for rMQR)
     pub modules: u8,
     /// List of decoders that successfully decoded this QR
     pub decoders_success: Vec<String>,
+    /// Share of the EC budget consumed by the worst Reed-Solomon block (0.0-1.0)
+    pub ec_used_ratio: Option<f32>,
+    /// Codewords and blocks Reed-Solomon had to correct
+    pub ec_usage: Option<EcUsage>,
+    /// ECI designator declared in the bitstream
+    pub eci: Option<u32>,
+    /// Declared (ECI) or detected character set of the payload
+    pub charset: Option<Charset>,
+    /// Encoding-mode segments of the payload, in order
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+    /// Structured Append header, if this symbol is one part of a larger message
+    #[serde(default)]
+    pub structured_append: Option<StructuredAppend>,
+    /// FNC1 mode (GS1 or industry format) declared by the symbol
+    #[serde(default)]
+    pub fnc1: Option<Fnc1Mode>,
+    /// Decode stage that read the symbol and the region it was cropped to
+    #[serde(default)]
+    pub trace: Option<DecodeTrace>,
+    /// Light modules on a dark background; many phone scanners only read dark on light
+    #[serde(default)]
+    pub inverted: bool,
+    /// Symbol drawn left to right reversed; many phone scanners never try a mirror image
+    #[serde(default)]
+    pub mirrored: bool,
+}
+
+/// Decode stage that read a symbol, cheapest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeStage {
+    /// The image as given
+    #[default]
+    Original,
+    /// Otsu, inverted Otsu or high-contrast threshold of the whole image, or
+    /// an inverted or mirrored copy of it
+    QuickThreshold,
+    /// Upscaled so modules span several pixels, then re-binarised
+    Upscale,
+    /// Known-good preprocessing, colour channels and HSV in one parallel pool
+    ParallelPool,
+    /// Majority votes over module centres of a located symbol
+    ModuleCentres,
+    /// Random preprocessing search
+    BruteForce,
+}
+
+impl fmt::Display for DecodeStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Original => write!(f, "original"),
+            Self::QuickThreshold => write!(f, "quick threshold"),
+            Self::Upscale => write!(f, "upscale"),
+            Self::ParallelPool => write!(f, "parallel pool"),
+            Self::ModuleCentres => write!(f, "module centres"),
+            Self::BruteForce => write!(f, "brute force"),
+        }
+    }
+}
+
+/// How a candidate symbol region was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoiSource {
+    /// rqrr grid detection
+    Grid,
+    /// 1:1:3:1:1 finder pattern search
+    FinderPatterns,
+    /// Dense high-frequency texture
+    Texture,
+    /// Candidate from a coarse image pyramid level
+    Pyramid,
+}
+
+impl fmt::Display for RoiSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Grid => write!(f, "grid"),
+            Self::FinderPatterns => write!(f, "finder patterns"),
+            Self::Texture => write!(f, "texture"),
+            Self::Pyramid => write!(f, "pyramid"),
+        }
+    }
+}
+
+/// Likely symbol region, with margin, that decoding was cropped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionOfInterest {
+    /// Crop in image pixels
+    pub region: Region,
+    pub source: RoiSource,
+}
+
+/// Resampling filter used to enlarge a low-resolution image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpscaleFilter {
+    /// Pixel replication; keeps module edges hard
+    Nearest,
+    /// Catmull-Rom bicubic interpolation
+    Bicubic,
+    /// Bicubic, then each pixel snapped to the nearer of its local extremes
+    EdgePreserving,
+}
+
+impl fmt::Display for UpscaleFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nearest => write!(f, "nearest"),
+            Self::Bicubic => write!(f, "bicubic"),
+            Self::EdgePreserving => write!(f, "edge-preserving"),
+        }
+    }
+}
+
+/// How a low-resolution image was enlarged before it decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Upscaling {
+    pub filter: UpscaleFilter,
+    /// Integer scale factor applied to both sides
+    pub factor: u32,
+}
+
+/// How a symbol was read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DecodeTrace {
+    pub stage: DecodeStage,
+    /// Region the stage ran on; `None` when it ran on the whole image
+    pub roi: Option<RegionOfInterest>,
+    /// Set when the image had to be upscaled to read
+    #[serde(default)]
+    pub upscale: Option<Upscaling>,
+    /// Whether the format (and version) information was unreadable and the
+    /// EC level and mask were found by trying every combination
+    #[serde(default)]
+    pub format_recovered: bool,
+}
+
+/// Reed-Solomon correction work needed to read the symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EcUsage {
+    /// Codewords corrected, across all blocks
+    pub codewords_corrected: u32,
+    /// Codewords that can be corrected, across all blocks
+    pub codewords_capacity: u32,
+    /// Blocks with at least one corrected codeword
+    pub blocks_corrected: u32,
+    /// Total Reed-Solomon blocks for the version and EC level
+    pub blocks: u32,
 }
 
 /// Results of stress tests for scannability scoring
@@ -46,6 +218,89 @@ pub struct StressResults {
     pub low_contrast: bool,
 }
 
+/// Symbol family of a decoded code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Symbology {
+    /// Standard QR code (ISO/IEC 18004, versions 1-40)
+    #[default]
+    Qr,
+    /// Micro QR code (M1-M4): one finder pattern, 11-17 modules
+    MicroQr,
+    /// Rectangular Micro QR code (ISO/IEC 23941): 7-17 rows by 27-139 columns
+    RectangularMicroQr,
+    /// Data Matrix (ISO/IEC 16022), opt-in
+    DataMatrix,
+    /// Aztec code (ISO/IEC 24778), opt-in
+    Aztec,
+    /// PDF417 stacked barcode (ISO/IEC 15438), opt-in
+    Pdf417,
+}
+
+impl Symbology {
+    /// The QR family, decoded unless other formats are requested
+    pub const QR_FAMILY: [Symbology; 3] = [Self::Qr, Self::MicroQr, Self::RectangularMicroQr];
+
+    /// Every symbology the decoder can read
+    pub const ALL: [Symbology; 6] = [
+        Self::Qr,
+        Self::MicroQr,
+        Self::RectangularMicroQr,
+        Self::DataMatrix,
+        Self::Aztec,
+        Self::Pdf417,
+    ];
+
+    /// Quiet zone the symbology requires on each side (modules)
+    pub fn quiet_zone_modules(self) -> u8 {
+        match self {
+            Self::Qr => 4,
+            Self::MicroQr | Self::RectangularMicroQr | Self::Pdf417 => 2,
+            Self::DataMatrix => 1,
+            // The bullseye finder needs no margin
+            Self::Aztec => 0,
+        }
+    }
+
+    /// Whether this is one of the Micro QR families
+    pub fn is_micro(self) -> bool {
+        matches!(self, Self::MicroQr | Self::RectangularMicroQr)
+    }
+
+    /// Parse a format name as given on the command line or from Node
+    ///
+    /// Case-insensitive; spaces, dashes and underscores are ignored, so
+    /// `"Data Matrix"`, `"data-matrix"` and `"datamatrix"` all match.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let key: String = name
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match key.as_str() {
+            "qr" | "qrcode" => Some(Self::Qr),
+            "microqr" => Some(Self::MicroQr),
+            "rmqr" | "rectangularmicroqr" => Some(Self::RectangularMicroQr),
+            "datamatrix" => Some(Self::DataMatrix),
+            "aztec" => Some(Self::Aztec),
+            "pdf417" => Some(Self::Pdf417),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Symbology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Qr => write!(f, "QR"),
+            Self::MicroQr => write!(f, "Micro QR"),
+            Self::RectangularMicroQr => write!(f, "rMQR"),
+            Self::DataMatrix => write!(f, "Data Matrix"),
+            Self::Aztec => write!(f, "Aztec"),
+            Self::Pdf417 => write!(f, "PDF417"),
+        }
+    }
+}
+
 /// QR code error correction level
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum ErrorCorrectionLevel {
@@ -74,23 +329,151 @@ impl fmt::Display for ErrorCorrectionLevel {
 /// Simple decode result (without stress tests)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecodeResult {
-    /// Decoded content
+    /// Decoded content (lossy text view of `raw_bytes`)
     pub content: String,
+    /// Payload bytes exactly as encoded, if the bitstream could be read
+    pub raw_bytes: Option<Vec<u8>>,
     /// Metadata if available
     pub metadata: Option<QrMetadata>,
 }
 
+/// Axis-aligned rectangle of an image, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Whether the point (`x`, `y`) lies inside the rectangle
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        x >= self.x as f32
+            && y >= self.y as f32
+            && x < (self.x + self.width) as f32
+            && y < (self.y + self.height) as f32
+    }
+
+    /// Centre of the rectangle
+    pub fn center(&self) -> (f32, f32) {
+        (
+            self.x as f32 + self.width as f32 / 2.0,
+            self.y as f32 + self.height as f32 / 2.0,
+        )
+    }
+
+    /// Box `[min_x, min_y, max_x, max_y]` grown by `margin` times its longer
+    /// side, clipped to a `width` x `height` image; `None` if nothing is left
+    pub fn around(bounds: [f32; 4], margin: f32, (width, height): (u32, u32)) -> Option<Self> {
+        let [min_x, min_y, max_x, max_y] = bounds;
+        let pad = (max_x - min_x).max(max_y - min_y) * margin;
+        let x0 = (min_x - pad).max(0.0) as u32;
+        let y0 = (min_y - pad).max(0.0) as u32;
+        let x1 = ((max_x + pad).ceil().max(0.0) as u32).min(width);
+        let y1 = ((max_y + pad).ceil().max(0.0) as u32).min(height);
+        (x1 > x0 && y1 > y0).then_some(Self {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        })
+    }
+
+    /// Share of a `width` x `height` image the rectangle covers
+    pub fn coverage(&self, (width, height): (u32, u32)) -> f32 {
+        (self.width as f32 * self.height as f32) / (width.max(1) as f32 * height.max(1) as f32)
+    }
+}
+
+/// A symbol found by a tiled scan, positioned in the whole image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detection {
+    /// Decoded content (lossy text view of `raw_bytes`)
+    pub content: String,
+    /// Payload bytes exactly as encoded, if the bitstream could be read
+    pub raw_bytes: Option<Vec<u8>>,
+    /// Metadata if available
+    pub metadata: Option<QrMetadata>,
+    /// Symbol corners in image pixels (top-left, top-right, bottom-right,
+    /// bottom-left), when the symbol could be located
+    pub corners: Option<[(f32, f32); 4]>,
+    /// Tile the symbol was read from, in image pixels
+    pub tile: Region,
+}
+
+/// Channel layout of a raw pixel buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PixelFormat {
+    /// 8-bit grayscale
+    Luma8,
+    Rgb8,
+    Rgba8,
+    /// Blue, green, red, alpha (Windows bitmaps, many camera frames)
+    Bgra8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Luma8 => 1,
+            Self::Rgb8 => 3,
+            Self::Rgba8 | Self::Bgra8 => 4,
+        }
+    }
+
+    /// Format for a name like `rgba8` or `RGBA`, case-insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().trim_end_matches('8') {
+            "luma" | "gray" | "grey" => Some(Self::Luma8),
+            "rgb" => Some(Self::Rgb8),
+            "rgba" => Some(Self::Rgba8),
+            "bgra" => Some(Self::Bgra8),
+            _ => None,
+        }
+    }
+}
+
+/// Already-decoded pixels, e.g. a camera frame or canvas `ImageData`
+#[derive(Debug, Clone, Copy)]
+pub struct PixelBuffer<'a> {
+    pub data: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+    /// Bytes from the start of one row to the next; may include padding
+    pub stride: usize,
+    pub format: PixelFormat,
+}
+
+impl<'a> PixelBuffer<'a> {
+    /// Tightly packed rows (stride = width × bytes per pixel)
+    pub fn new(data: &'a [u8], width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            data,
+            width,
+            height,
+            stride: width as usize * format.bytes_per_pixel(),
+            format,
+        }
+    }
+}
+
 /// Internal result from multi-decoder
 #[derive(Debug, Clone)]
 pub struct MultiDecodeResult {
     pub content: String,
+    pub raw_bytes: Option<Vec<u8>>,
     pub metadata: Option<QrMetadata>,
     pub decoders_success: Vec<String>,
+    /// Module grid as sampled by the read that succeeded, when the decoder
+    /// exposes it; it may come from a preprocessed variant of the image
+    pub grid: Option<ModuleGrid>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::segments::SegmentMode;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -99,13 +482,30 @@ mod tests {
             score: 85,
             decodable: true,
             content: Some("https://example.com".to_string()),
+            raw_bytes: None,
             metadata: Some(QrMetadata {
+                symbology: Symbology::Qr,
                 version: 3,
                 error_correction: ErrorCorrectionLevel::H,
                 modules: 29,
                 decoders_success: vec!["rxing".to_string()],
+                ec_used_ratio: None,
+                ec_usage: None,
+                eci: None,
+                charset: None,
+                segments: Vec::new(),
+                structured_append: None,
+                fnc1: None,
+                trace: None,
+                inverted: false,
+                mirrored: false,
             }),
             stress_results: StressResults::default(),
+            print: None,
+            structure: None,
+            fidelity: None,
+            payload: None,
+            url_risk: None,
         };
 
         let json = serde_json::to_string(&result).unwrap();
@@ -114,6 +514,15 @@ mod tests {
         assert!(json.contains("\"content\":\"https://example.com\""));
     }
 
+    #[test]
+    fn metadata_from_before_segments_deserializes() {
+        let json = r#"{"version":1,"error_correction":"M","modules":21,"decoders_success":["rxing"]}"#;
+        let meta: QrMetadata = serde_json::from_str(json).unwrap();
+        assert!(meta.segments.is_empty());
+        assert_eq!(meta.symbology, Symbology::Qr);
+        assert!(!meta.inverted && !meta.mirrored);
+    }
+
     #[test]
     fn stress_results_default_all_false() {
         let sr = StressResults::default();
@@ -133,6 +542,26 @@ mod tests {
         assert_eq!(format!("{}", ErrorCorrectionLevel::H), "H");
     }
 
+    #[test]
+    fn symbology_quiet_zones() {
+        assert_eq!(Symbology::default(), Symbology::Qr);
+        assert_eq!(Symbology::Qr.quiet_zone_modules(), 4);
+        assert_eq!(Symbology::MicroQr.quiet_zone_modules(), 2);
+        assert_eq!(Symbology::RectangularMicroQr.to_string(), "rMQR");
+    }
+
+    #[test]
+    fn symbology_from_name() {
+        assert_eq!(Symbology::from_name("Data Matrix"), Some(Symbology::DataMatrix));
+        assert_eq!(Symbology::from_name("data-matrix"), Some(Symbology::DataMatrix));
+        assert_eq!(Symbology::from_name("PDF417"), Some(Symbology::Pdf417));
+        assert_eq!(Symbology::from_name("micro_qr"), Some(Symbology::MicroQr));
+        assert_eq!(Symbology::from_name("maxicode"), None);
+        for symbology in Symbology::ALL {
+            assert_eq!(Symbology::from_name(&symbology.to_string()), Some(symbology));
+        }
+    }
+
     #[test]
     fn error_correction_level_default_is_m() {
         assert_eq!(ErrorCorrectionLevel::default(), ErrorCorrectionLevel::M);
@@ -141,14 +570,37 @@ mod tests {
     #[test]
     fn qr_metadata_serializes() {
         let meta = QrMetadata {
+            symbology: Symbology::Qr,
             version: 5,
             error_correction: ErrorCorrectionLevel::Q,
             modules: 37,
             decoders_success: vec!["rxing".to_string(), "rqrr".to_string()],
+            ec_used_ratio: Some(0.25),
+            ec_usage: Some(EcUsage {
+                codewords_corrected: 2,
+                codewords_capacity: 8,
+                blocks_corrected: 1,
+                blocks: 1,
+            }),
+            eci: Some(26),
+            charset: Some(Charset::Utf8),
+            segments: vec![Segment {
+                mode: SegmentMode::Byte,
+                char_count: 4,
+                byte_len: 4,
+            }],
+            structured_append: None,
+            fnc1: None,
+            trace: None,
+            inverted: false,
+            mirrored: false,
         };
 
         let json = serde_json::to_string(&meta).unwrap();
         assert!(json.contains("\"version\":5"));
         assert!(json.contains("\"modules\":37"));
+        assert!(json.contains("\"ec_used_ratio\":0.25"));
+        assert!(json.contains("\"charset\":\"UTF-8\""));
+        assert!(json.contains("\"mode\":\"Byte\""));
     }
 }
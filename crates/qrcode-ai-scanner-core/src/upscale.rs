@@ -0,0 +1,221 @@
+//! Upscaling for tiny and low-resolution symbols
+//!
+//! The preprocessing tiers only ever shrink images, while both decoders need
+//! a few pixels per module to find the finder patterns. Favicons, thumbnails
+//! and screenshots of small codes have modules one or two pixels wide. This
+//! tier estimates the module size from binarised run lengths and, when it is
+//! too small, enlarges the image so modules span about six pixels. Each
+//! filter's output is tried as is and re-binarised with Otsu.
+
+use crate::decoder::{apply_otsu_threshold, try_decode_with_both, DecodeOptions};
+use crate::error::{QraiError, Result};
+use crate::locate::otsu_level;
+use crate::types::{DecodeStage, DecodeTrace, MultiDecodeResult, UpscaleFilter, Upscaling};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Luma};
+use rayon::prelude::*;
+
+/// Modules narrower than this, in pixels, are upscaled
+pub const MIN_MODULE_PX: u32 = 3;
+
+/// Modules are enlarged to about this many pixels
+const TARGET_MODULE_PX: u32 = 6;
+
+/// Only thumbnail-sized images are treated as low resolution; small symbols
+/// in larger images are left to the region-of-interest and tiled scans, and
+/// texture in larger artwork can pass for one-pixel modules
+const MAX_SOURCE_SIDE: u32 = 256;
+
+/// Upscaled images are kept within this many pixels per side
+const MAX_UPSCALED_SIDE: u32 = 1024;
+
+/// Fewer runs than this means there is no pattern to measure
+const MIN_RUNS: u32 = 16;
+
+/// Filters tried, cheapest first
+const FILTERS: [UpscaleFilter; 3] = [UpscaleFilter::Nearest, UpscaleFilter::Bicubic, UpscaleFilter::EdgePreserving];
+
+/// Most common run length between dark/light transitions, in pixels
+///
+/// Runs touching the image border are background and are skipped. In a
+/// symbol most runs are a single module, so the mode tracks the module size.
+pub fn estimate_module_size(gray: &GrayImage) -> Option<u32> {
+    let (width, height) = gray.dimensions();
+    let samples: Vec<f32> = gray.as_raw().iter().map(|&v| v as f32).collect();
+    let level = otsu_level(&samples);
+    let dark = |x: u32, y: u32| (gray.get_pixel(x, y).0[0] as f32) < level;
+
+    let mut lengths = vec![0u32; width.max(height) as usize + 1];
+    let mut count = |len: u32, line: u32, run_start: u32, run_end: u32| {
+        if run_start > 0 && run_end < line {
+            lengths[len as usize] += 1;
+        }
+    };
+    for y in 0..height {
+        let mut start = 0;
+        for x in 1..=width {
+            if x == width || dark(x, y) != dark(start, y) {
+                count(x - start, width, start, x);
+                start = x;
+            }
+        }
+    }
+    for x in 0..width {
+        let mut start = 0;
+        for y in 1..=height {
+            if y == height || dark(x, y) != dark(x, start) {
+                count(y - start, height, start, y);
+                start = y;
+            }
+        }
+    }
+
+    if lengths.iter().sum::<u32>() < MIN_RUNS {
+        return None;
+    }
+    (1..lengths.len() as u32).max_by_key(|&len| (lengths[len as usize], std::cmp::Reverse(len)))
+}
+
+/// Integer factor that brings `module` px modules to about six pixels
+///
+/// `None` when modules are already wide enough or the image is too large
+/// to enlarge at least twofold.
+pub fn upscale_factor(module: u32, dimensions: (u32, u32)) -> Option<u32> {
+    let longest = dimensions.0.max(dimensions.1).max(1);
+    if module >= MIN_MODULE_PX || longest > MAX_SOURCE_SIDE {
+        return None;
+    }
+    let factor = TARGET_MODULE_PX.div_ceil(module.max(1)).min(MAX_UPSCALED_SIDE / longest);
+    (factor >= 2).then_some(factor)
+}
+
+/// Enlarge a grayscale image `factor` times with `filter`
+pub fn upscale(gray: &GrayImage, filter: UpscaleFilter, factor: u32) -> GrayImage {
+    let (width, height) = (gray.width() * factor, gray.height() * factor);
+    match filter {
+        UpscaleFilter::Nearest => imageops::resize(gray, width, height, FilterType::Nearest),
+        UpscaleFilter::Bicubic => imageops::resize(gray, width, height, FilterType::CatmullRom),
+        UpscaleFilter::EdgePreserving => {
+            let smooth = imageops::resize(gray, width, height, FilterType::CatmullRom);
+            toggle(&smooth, (factor / 2).max(1))
+        }
+    }
+}
+
+/// Toggle (shock) filter: each pixel moves to the nearer of the minimum and
+/// maximum in its window, turning interpolated ramps back into hard edges
+fn toggle(gray: &GrayImage, radius: u32) -> GrayImage {
+    let low = window_extreme(gray, radius, u8::min);
+    let high = window_extreme(gray, radius, u8::max);
+    GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        let v = gray.get_pixel(x, y).0[0];
+        let (lo, hi) = (low.get_pixel(x, y).0[0], high.get_pixel(x, y).0[0]);
+        Luma([if v - lo <= hi - v { lo } else { hi }])
+    })
+}
+
+/// Minimum or maximum over a square window, computed as two 1-D passes
+pub(crate) fn window_extreme(gray: &GrayImage, radius: u32, pick: fn(u8, u8) -> u8) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let pass = |src: &GrayImage, horizontal: bool| {
+        GrayImage::from_fn(width, height, |x, y| {
+            let (pos, len) = if horizontal { (x, width) } else { (y, height) };
+            let range = pos.saturating_sub(radius)..(pos + radius + 1).min(len);
+            let value = range
+                .map(|p| if horizontal { src.get_pixel(p, y).0[0] } else { src.get_pixel(x, p).0[0] })
+                .reduce(pick)
+                .unwrap_or(0);
+            Luma([value])
+        })
+    };
+    pass(&pass(gray, true), false)
+}
+
+/// Decode a low-resolution image by upscaling it
+///
+/// Every filter is tried in parallel, each on the upscaled image and its
+/// Otsu binarisation. The result's trace records the filter and factor.
+///
+/// # Errors
+/// `QraiError::DecodeFailed` if modules are already large enough, the image
+/// is too big to be low resolution, or no upscaled variant decodes.
+pub(crate) fn decode_upscaled(img: &DynamicImage, options: &DecodeOptions) -> Result<MultiDecodeResult> {
+    if img.width().max(img.height()) > MAX_SOURCE_SIDE {
+        return Err(QraiError::DecodeFailed);
+    }
+    let gray = img.to_luma8();
+    let factor = estimate_module_size(&gray)
+        .and_then(|module| upscale_factor(module, gray.dimensions()))
+        .ok_or(QraiError::DecodeFailed)?;
+
+    let (mut result, filter) = FILTERS
+        .par_iter()
+        .find_map_any(|&filter| {
+            let enlarged = DynamicImage::ImageLuma8(upscale(&gray, filter, factor));
+            let result = try_decode_with_both(&enlarged, options)
+                .ok()
+                .or_else(|| apply_otsu_threshold(&enlarged).and_then(|otsu| try_decode_with_both(&otsu, options).ok()))?;
+            Some((result, filter))
+        })
+        .ok_or(QraiError::DecodeFailed)?;
+
+    if let Some(meta) = result.metadata.as_mut() {
+        meta.trace = Some(DecodeTrace {
+            stage: DecodeStage::Upscale,
+            upscale: Some(Upscaling { filter, factor }),
+            ..Default::default()
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::multi_decode_image;
+
+    fn render(text: &str, module: u32) -> GrayImage {
+        let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+        code.render::<Luma<u8>>().module_dimensions(module, module).build()
+    }
+
+    #[test]
+    fn estimates_module_size_from_runs() {
+        assert_eq!(estimate_module_size(&render("https://example.com", 1)), Some(1));
+        assert_eq!(estimate_module_size(&render("https://example.com", 2)), Some(2));
+        assert_eq!(estimate_module_size(&render("https://example.com", 5)), Some(5));
+        assert_eq!(estimate_module_size(&GrayImage::from_pixel(32, 32, Luma([255]))), None);
+    }
+
+    #[test]
+    fn factors_target_six_pixel_modules() {
+        assert_eq!(upscale_factor(1, (29, 29)), Some(6));
+        assert_eq!(upscale_factor(2, (58, 58)), Some(3));
+        assert_eq!(upscale_factor(3, (87, 87)), None);
+        // Capped by the output size, and skipped for larger images
+        assert_eq!(upscale_factor(1, (200, 150)), Some(5));
+        assert_eq!(upscale_factor(1, (512, 512)), None);
+    }
+
+    #[test]
+    fn edge_preserving_upscale_keeps_edges_hard() {
+        let step = GrayImage::from_fn(8, 4, |x, _| Luma([if x < 4 { 0 } else { 255 }]));
+        let sharp = upscale(&step, UpscaleFilter::EdgePreserving, 4);
+        assert_eq!(sharp.dimensions(), (32, 16));
+        assert!(sharp.pixels().all(|p| p.0[0] < 20 || p.0[0] > 235));
+    }
+
+    #[test]
+    fn decodes_blurred_favicons_by_upscaling() {
+        // 33 modules squeezed into 48 px: about 1.5 px per module, smoothed
+        let code = qrcode::QrCode::new(b"https://example.com/favicon").unwrap();
+        let sharp = code.render::<Luma<u8>>().module_dimensions(8, 8).build();
+        let tiny = DynamicImage::ImageLuma8(imageops::resize(&sharp, 48, 48, FilterType::Gaussian));
+
+        let result = multi_decode_image(&tiny).unwrap();
+        assert_eq!(result.content, "https://example.com/favicon");
+        let trace = result.metadata.unwrap().trace.unwrap();
+        assert_eq!(trace.stage, DecodeStage::Upscale);
+        assert_eq!(trace.upscale.map(|u| u.factor), Some(6));
+    }
+}
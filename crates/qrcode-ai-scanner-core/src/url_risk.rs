@@ -0,0 +1,431 @@
+//! Offline URL risk heuristics
+//!
+//! Flags the tricks common in QR phishing ("quishing") without any network
+//! lookups: look-alike international domains, raw IP hosts, link shorteners
+//! that hide the destination, plain HTTP, embedded credentials, open-redirect
+//! parameters and script/data URIs.
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Link shorteners and redirectors that hide the real destination
+const SHORTENERS: &[&str] = &[
+    "bit.ly", "bitly.com", "tinyurl.com", "t.co", "goo.gl", "ow.ly", "is.gd", "buff.ly", "rebrand.ly",
+    "cutt.ly", "shorturl.at", "rb.gy", "t.ly", "tiny.cc", "bl.ink", "s.id", "v.gd", "qrco.de", "qr.io",
+    "lnkd.in", "trib.al", "dlvr.it", "soo.gd", "shorte.st", "adf.ly", "bc.vc", "mcaf.ee", "su.pr",
+    "x.co", "po.st", "u.to", "clck.ru", "surl.li", "tny.im", "1url.com", "rotf.lol", "kutt.it",
+    "short.io", "shrtco.de", "urlz.fr", "linktr.ee", "tr.ee", "l.ead.me", "qrs.ly", "me-qr.com",
+];
+
+/// Query parameter names used to bounce visitors to another site
+const REDIRECT_PARAMS: &[&str] = &[
+    "url", "uri", "u", "r", "redirect", "redirect_uri", "redirect_url", "redir", "next", "return",
+    "returnto", "return_to", "returnurl", "return_url", "goto", "go", "dest", "destination",
+    "continue", "target", "to", "out", "link", "forward", "callback", "q",
+];
+
+/// Cyrillic and Greek letters that render like Latin ones
+const CONFUSABLES: &[char] = &[
+    'а', 'в', 'е', 'к', 'м', 'н', 'о', 'р', 'с', 'т', 'у', 'х', 'ѕ', 'і', 'ј', 'ԁ', 'ԛ', 'ԝ', 'ӏ',
+    'һ', 'α', 'ο', 'ρ', 'ν', 'τ', 'ι', 'κ', 'χ', 'ε', 'ϲ',
+];
+
+/// How risky it is to open a link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum RiskLevel {
+    /// Nothing suspicious found
+    #[default]
+    Safe,
+    /// Worth a second look (plain HTTP, shortener)
+    Low,
+    /// Commonly abused pattern (raw IP, IDN, redirect parameter)
+    Medium,
+    /// Strong phishing indicator (homograph, credentials, script URI)
+    High,
+}
+
+impl std::fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RiskLevel::Safe => "safe",
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        })
+    }
+}
+
+/// Individual heuristic that fired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RiskFlag {
+    /// Internationalised (punycode / non-ASCII) host
+    InternationalDomain,
+    /// Host mixes scripts or spells Latin words with look-alike letters
+    Homograph,
+    /// Host is an IP address, possibly in decimal/hex/octal disguise
+    IpHost,
+    /// Known link shortener
+    Shortener,
+    /// Scheme other than HTTPS
+    InsecureScheme,
+    /// `user:password@` before the host
+    Credentials,
+    /// Query parameters carrying another URL
+    RedirectParams,
+    /// `javascript:`, `vbscript:` or `data:` URI
+    ScriptUri,
+}
+
+impl RiskFlag {
+    fn level(self) -> RiskLevel {
+        match self {
+            RiskFlag::Shortener | RiskFlag::InsecureScheme => RiskLevel::Low,
+            RiskFlag::InternationalDomain | RiskFlag::IpHost | RiskFlag::RedirectParams => RiskLevel::Medium,
+            RiskFlag::Homograph | RiskFlag::Credentials | RiskFlag::ScriptUri => RiskLevel::High,
+        }
+    }
+}
+
+/// Why a URL was flagged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RiskReason {
+    pub flag: RiskFlag,
+    /// Human-readable explanation
+    pub detail: String,
+}
+
+/// Risk assessment of a decoded link
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UrlRisk {
+    /// Overall level: the worst flag, raised one step when three or more fire
+    pub level: RiskLevel,
+    pub reasons: Vec<RiskReason>,
+    /// Host the link points to, as written
+    pub host: Option<String>,
+}
+
+impl UrlRisk {
+    /// Whether a particular heuristic fired
+    pub fn has(&self, flag: RiskFlag) -> bool {
+        self.reasons.iter().any(|r| r.flag == flag)
+    }
+}
+
+/// Assess decoded content that is a link
+///
+/// Returns `None` when the content is not a URI with an authority
+/// (`scheme://...`) or a `javascript:` / `vbscript:` / `data:` URI.
+pub fn analyze_url(content: &str) -> Option<UrlRisk> {
+    let text = content.trim();
+    let (scheme, rest) = text.split_once(':')?;
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid_scheme {
+        return None;
+    }
+    let scheme = scheme.to_ascii_lowercase();
+    let mut reasons = Vec::new();
+    let mut flag = |flag: RiskFlag, detail: String| reasons.push(RiskReason { flag, detail });
+
+    if matches!(scheme.as_str(), "javascript" | "vbscript" | "data") {
+        flag(RiskFlag::ScriptUri, format!("{scheme}: URI runs or embeds content instead of opening a page"));
+        return Some(finish(reasons, None));
+    }
+    let rest = rest.strip_prefix("//")?;
+
+    if scheme != "https" {
+        flag(RiskFlag::InsecureScheme, format!("{scheme}:// is not encrypted"));
+    }
+
+    let authority_end = rest.find(['/', '?', '#', '\\']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(authority_end);
+    let host_port = match authority.rsplit_once('@') {
+        Some((userinfo, host)) => {
+            let detail = if userinfo.contains(':') {
+                format!("embeds a username and password before the real host '{host}'")
+            } else {
+                format!("'{userinfo}@' disguises the real host '{host}'")
+            };
+            flag(RiskFlag::Credentials, detail);
+            host
+        }
+        None => authority,
+    };
+    let host = split_host(host_port).trim_end_matches('.').to_lowercase();
+
+    if let Some(kind) = ip_kind(&host) {
+        flag(RiskFlag::IpHost, format!("host is a {kind} instead of a domain name"));
+    } else {
+        check_idn(&host, &mut flag);
+        if let Some(shortener) = SHORTENERS.iter().find(|s| host == **s || host.ends_with(&format!(".{s}"))) {
+            flag(RiskFlag::Shortener, format!("{shortener} hides the final destination"));
+        }
+    }
+
+    let query = tail.split_once('?').map(|(_, q)| q.split('#').next().unwrap_or(q)).unwrap_or("");
+    let redirects: Vec<&str> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, value)| {
+            let value = value.to_ascii_lowercase();
+            let embeds_url = value.starts_with("http") || value.starts_with("//") || value.starts_with("%2f%2f");
+            REDIRECT_PARAMS.contains(&key.to_ascii_lowercase().as_str()) && embeds_url
+        })
+        .map(|(key, _)| key)
+        .collect();
+    if !redirects.is_empty() {
+        flag(RiskFlag::RedirectParams, format!("forwards to another URL via '{}'", redirects.join("', '")));
+    }
+
+    Some(finish(reasons, Some(host).filter(|h| !h.is_empty())))
+}
+
+fn finish(reasons: Vec<RiskReason>, host: Option<String>) -> UrlRisk {
+    let mut level = reasons.iter().map(|r| r.flag.level()).max().unwrap_or_default();
+    if reasons.len() >= 3 {
+        level = match level {
+            RiskLevel::Safe => RiskLevel::Low,
+            RiskLevel::Low => RiskLevel::Medium,
+            _ => RiskLevel::High,
+        };
+    }
+    UrlRisk { level, reasons, host }
+}
+
+/// Host without port (IPv6 brackets kept off)
+fn split_host(host_port: &str) -> &str {
+    if let Some(v6) = host_port.strip_prefix('[') {
+        return v6.split(']').next().unwrap_or(v6);
+    }
+    match host_port.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host_port,
+    }
+}
+
+/// Describe an IP host, including the integer forms browsers still accept
+fn ip_kind(host: &str) -> Option<&'static str> {
+    if host.parse::<Ipv6Addr>().is_ok() {
+        return Some("raw IPv6 address");
+    }
+    if host.parse::<Ipv4Addr>().is_ok() {
+        return Some("raw IPv4 address");
+    }
+    let is_number = |part: &str| {
+        let hex = part.strip_prefix("0x").or_else(|| part.strip_prefix("0X"));
+        match hex {
+            Some(h) => !h.is_empty() && h.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()),
+        }
+    };
+    let parts: Vec<&str> = host.split('.').collect();
+    (parts.len() <= 4 && parts.iter().all(|p| is_number(p))).then_some("disguised (decimal, hex or octal) IP address")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Other,
+}
+
+fn script(c: char) -> Option<Script> {
+    match c {
+        'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => Some(Script::Latin),
+        '\u{0370}'..='\u{03FF}' => Some(Script::Greek),
+        '\u{0400}'..='\u{052F}' => Some(Script::Cyrillic),
+        c if c.is_alphabetic() => Some(Script::Other),
+        _ => None,
+    }
+}
+
+fn check_idn(host: &str, flag: &mut impl FnMut(RiskFlag, String)) {
+    let mut international = false;
+    for label in host.split('.') {
+        let decoded = match label.strip_prefix("xn--") {
+            Some(encoded) => match punycode_decode(encoded) {
+                Some(decoded) => decoded,
+                None => {
+                    flag(RiskFlag::InternationalDomain, format!("'{label}' is malformed punycode"));
+                    continue;
+                }
+            },
+            None if label.is_ascii() => continue,
+            None => label.to_string(),
+        };
+        international = true;
+
+        let scripts: Vec<Script> = decoded.chars().filter_map(script).fold(Vec::new(), |mut acc, s| {
+            if !acc.contains(&s) {
+                acc.push(s);
+            }
+            acc
+        });
+        let lookalike = !decoded.is_empty()
+            && decoded
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || CONFUSABLES.contains(&c))
+            && decoded.chars().any(|c| CONFUSABLES.contains(&c));
+        if scripts.len() > 1 && scripts.contains(&Script::Latin) {
+            flag(RiskFlag::Homograph, format!("'{decoded}' mixes Latin with other scripts"));
+        } else if lookalike {
+            flag(RiskFlag::Homograph, format!("'{decoded}' is spelled with letters that look Latin"));
+        }
+    }
+    if international {
+        flag(RiskFlag::InternationalDomain, format!("'{host}' is an internationalised domain name"));
+    }
+}
+
+/// RFC 3492 punycode decoding of a single label (without the `xn--` prefix)
+fn punycode_decode(input: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+
+    let adapt = |mut delta: u32, points: u32, first: bool| {
+        delta /= if first { DAMP } else { 2 };
+        delta += delta / points;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+    };
+
+    let (basic, encoded) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+    let (mut n, mut i, mut bias) = (128u32, 0u32, 72u32);
+    let mut digits = encoded.chars();
+
+    while !digits.as_str().is_empty() {
+        let (old_i, mut w, mut k) = (i, 1u32, BASE);
+        loop {
+            let digit = match digits.next()? {
+                c @ 'a'..='z' => c as u32 - 'a' as u32,
+                c @ 'A'..='Z' => c as u32 - 'A' as u32,
+                c @ '0'..='9' => c as u32 - '0' as u32 + 26,
+                _ => return None,
+            };
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias { T_MIN } else if k >= bias + T_MAX { T_MAX } else { k - bias };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(url: &str) -> Vec<RiskFlag> {
+        analyze_url(url).unwrap().reasons.iter().map(|r| r.flag).collect()
+    }
+
+    #[test]
+    fn plain_https_link_is_safe() {
+        let risk = analyze_url("https://www.example.com/menu?table=4").unwrap();
+        assert_eq!(risk.level, RiskLevel::Safe);
+        assert!(risk.reasons.is_empty());
+        assert_eq!(risk.host.as_deref(), Some("www.example.com"));
+    }
+
+    #[test]
+    fn non_links_are_not_analysed() {
+        assert!(analyze_url("hello world").is_none());
+        assert!(analyze_url("tel:+15551234567").is_none());
+        assert!(analyze_url("WIFI:T:WPA;S:home;P:password;;").is_none());
+    }
+
+    #[test]
+    fn flags_script_and_data_uris() {
+        let risk = analyze_url("javascript:alert(document.cookie)").unwrap();
+        assert_eq!(risk.level, RiskLevel::High);
+        assert!(risk.has(RiskFlag::ScriptUri));
+        assert!(analyze_url("data:text/html;base64,PHNjcmlwdD4=").unwrap().has(RiskFlag::ScriptUri));
+    }
+
+    #[test]
+    fn flags_insecure_schemes_and_shorteners() {
+        assert_eq!(flags("http://example.com"), vec![RiskFlag::InsecureScheme]);
+        let risk = analyze_url("https://bit.ly/3xYz").unwrap();
+        assert_eq!(risk.level, RiskLevel::Low);
+        assert!(risk.has(RiskFlag::Shortener));
+    }
+
+    #[test]
+    fn flags_ip_hosts_including_disguised_forms() {
+        assert_eq!(flags("https://192.168.1.10/login"), vec![RiskFlag::IpHost]);
+        assert_eq!(flags("https://[2001:db8::1]:8443/"), vec![RiskFlag::IpHost]);
+        assert_eq!(flags("https://3232235786/"), vec![RiskFlag::IpHost]);
+        assert_eq!(flags("https://0xC0.0xA8.1.10/"), vec![RiskFlag::IpHost]);
+    }
+
+    #[test]
+    fn flags_credentials_before_host() {
+        let risk = analyze_url("https://paypal.com@evil.example/login").unwrap();
+        assert_eq!(risk.level, RiskLevel::High);
+        assert!(risk.has(RiskFlag::Credentials));
+        assert_eq!(risk.host.as_deref(), Some("evil.example"));
+    }
+
+    #[test]
+    fn flags_redirect_parameters() {
+        let risk = analyze_url("https://example.com/out?url=https%3A%2F%2Fevil.example&id=3").unwrap();
+        assert_eq!(risk.level, RiskLevel::Medium);
+        assert!(risk.has(RiskFlag::RedirectParams));
+        assert!(flags("https://example.com/search?q=shoes").is_empty());
+    }
+
+    #[test]
+    fn flags_punycode_homographs() {
+        // "аррӏе" in Cyrillic
+        let risk = analyze_url("https://xn--80ak6aa92e.com/").unwrap();
+        assert!(risk.has(RiskFlag::InternationalDomain));
+        assert!(risk.has(RiskFlag::Homograph), "{:?}", risk.reasons);
+        assert_eq!(risk.level, RiskLevel::High);
+
+        // "münchen" is international but not deceptive
+        let risk = analyze_url("https://xn--mnchen-3ya.de/").unwrap();
+        assert_eq!(risk.reasons.iter().map(|r| r.flag).collect::<Vec<_>>(), vec![RiskFlag::InternationalDomain]);
+        assert_eq!(risk.level, RiskLevel::Medium);
+
+        // Latin with one Cyrillic "о"
+        assert!(analyze_url("https://gооgle.com").unwrap().has(RiskFlag::Homograph));
+    }
+
+    #[test]
+    fn decodes_punycode() {
+        assert_eq!(punycode_decode("mnchen-3ya").as_deref(), Some("münchen"));
+        assert_eq!(punycode_decode("80ak6aa92e").as_deref(), Some("аррӏе"));
+        assert!(punycode_decode("mnchen-!").is_none());
+    }
+
+    #[test]
+    fn many_flags_raise_the_level() {
+        let risk = analyze_url("http://bit.ly/x?next=http://evil.example").unwrap();
+        assert_eq!(risk.reasons.len(), 3);
+        assert_eq!(risk.level, RiskLevel::High);
+    }
+}
@@ -11,7 +11,9 @@ export interface ValidationResult {
   decodable: boolean
   /** Decoded content of the QR code */
   content?: string
-  /** QR code version (1-40) */
+  /** Symbol family (QR, Micro QR, rMQR, Data Matrix, Aztec, PDF417) */
+  symbology?: string
+  /** Version within the symbology (QR 1-40, Micro QR 1-4, rMQR 1-32) */
   version?: number
   /** Error correction level (L, M, Q, H) */
   errorCorrection?: string
@@ -19,6 +21,14 @@ export interface ValidationResult {
   modules?: number
   /** List of decoders that successfully decoded the QR */
   decodersSuccess: Array<string>
+  /** Share of the error correction budget consumed by the worst block (0-1) */
+  ecUsedRatio?: number
+  /** Light modules on a dark background, which many phone scanners miss */
+  inverted: boolean
+  /** Mirror-image symbol, which many phone scanners miss */
+  mirrored: boolean
+  /** Decode stage that read the symbol, and the crop it ran on */
+  trace?: DecodeTrace
   /** Whether original image was decodable */
   stressOriginal: boolean
   /** Whether 50% downscaled image was decodable */
@@ -31,41 +41,253 @@ export interface ValidationResult {
   stressBlurMedium: boolean
   /** Whether low contrast image was decodable */
   stressLowContrast: boolean
+  /** Typed content with field validation errors */
+  payload?: Payload
+  /** Phishing risk of link content (safe, low, medium, high) */
+  urlRiskLevel?: string
+  /** Why the link was flagged */
+  urlRiskReasons: Array<string>
+}
+/** Typed QR content */
+export interface Payload {
+  /** Content type (url, wifi, contact, email, phone, sms, geo, event, payment, gs1, text) */
+  kind: string
+  /** Parsed fields by name (lists are comma-joined) */
+  fields: Record<string, string>
+  /** Missing or malformed fields */
+  errors: Array<PayloadFieldError>
+}
+/** Payload field that failed validation */
+export interface PayloadFieldError {
+  /** Field name, as in `fields` */
+  field: string
+  /** What is wrong with it */
+  message: string
 }
 /** Simple decode result (without stress tests) */
 export interface DecodeResult {
   /** Decoded content of the QR code */
   content: string
-  /** QR code version (1-40) */
+  /** Symbol family (QR, Micro QR, rMQR, Data Matrix, Aztec, PDF417) */
+  symbology?: string
+  /** Version within the symbology (QR 1-40, Micro QR 1-4, rMQR 1-32) */
   version?: number
   /** Error correction level (L, M, Q, H) */
   errorCorrection?: string
   /** Number of modules in the QR code */
   modules?: number
+  /** Share of the error correction budget consumed by the worst block (0-1) */
+  ecUsedRatio?: number
+  /** Light modules on a dark background, which many phone scanners miss */
+  inverted: boolean
+  /** Mirror-image symbol, which many phone scanners miss */
+  mirrored: boolean
+  /** Payload bytes exactly as encoded (content is a lossy text view) */
+  rawBytes?: Buffer
+  /** ECI designator declared in the QR code */
+  eci?: number
+  /** Declared or detected character set (UTF-8, ISO-8859-1, Shift_JIS, US-ASCII) */
+  charset?: string
+  /** Encoding-mode segments of the payload, in order */
+  segments: Array<Segment>
+  /** Decode stage that read the symbol, and the crop it ran on */
+  trace?: DecodeTrace
+}
+/** How a symbol was read */
+export interface DecodeTrace {
+  /** Decode stage (original, quick_threshold, upscale, parallel_pool, module_centres, brute_force) */
+  stage: string
+  /** Region of interest the stage was cropped to; absent for the whole image */
+  region?: Rect
+  /** How the region was found (grid, finder_patterns, texture, pyramid) */
+  regionSource?: string
+  /** Filter used when the image had to be upscaled (nearest, bicubic, edge_preserving) */
+  upscaleFilter?: string
+  /** Integer factor the image was upscaled by */
+  upscaleFactor?: number
+  /** Whether the EC level and mask were recovered by trying every combination */
+  formatRecovered: boolean
+}
+/** Encoding-mode segment of a decoded payload */
+export interface Segment {
+  /** Encoding mode (numeric, alphanumeric, byte, kanji) */
+  mode: string
+  /** Character count from the segment header */
+  charCount: number
+  /** Bytes the segment contributes to the raw payload */
+  byteLen: number
+}
+/** Every content read by every decoder across preprocessing variants */
+export interface ConsensusResult {
+  /** Distinct contents, most-read first */
+  readings: Array<ContentReading>
+  /** More than one distinct content was read (treat as suspicious) */
+  ambiguous: boolean
+  /** Preprocessing variants decoded (each by every decoder) */
+  variantsTried: number
+}
+/** Message reassembled from Structured Append parts */
+export interface AppendedMessageResult {
+  /** Full message, parts joined in order */
+  content: string
+  /** Number of symbols the message was split across */
+  total: number
+  /** Parity byte from the headers, verified against the message */
+  parity: number
+  /** ECI designator declared by the first part */
+  eci?: number
+  /** Declared (ECI) or detected character set of the message */
+  charset: string
+}
+/** A symbol found by `decodeTiled`, positioned in the whole image */
+export interface Detection {
+  /** Decoded content and metadata */
+  result: DecodeResult
+  /**
+   * Symbol corners in image pixels (top-left, top-right, bottom-right,
+   * bottom-left), when the symbol could be located
+   */
+  corners?: Array<Point>
+  /** Tile the symbol was read from, in image pixels */
+  tile: Rect
+}
+/** Image position in pixels */
+export interface Point {
+  x: number
+  y: number
+}
+/** Image rectangle in pixels */
+export interface Rect {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+/** One distinct content read from the image */
+export interface ContentReading {
+  /** Decoded content */
+  content: string
+  /** Decoders that read it */
+  decoders: Array<string>
+  /** Preprocessing variants it was read from */
+  variants: Array<string>
+  /** Number of (decoder, variant) reads that produced it */
+  reads: number
+}
+/** Options for decode and validate calls */
+export interface DecodeOptions {
+  /**
+   * Symbologies to accept: qr, microqr, rmqr, datamatrix, aztec, pdf417
+   * (default: the QR family)
+   */
+  formats?: Array<string>
+  /** Widest image accepted, in pixels (default 10000) */
+  maxWidth?: number
+  /** Tallest image accepted, in pixels (default 10000) */
+  maxHeight?: number
+  /** Largest width x height accepted (default 100000000) */
+  maxPixels?: number
+  /** Largest input buffer accepted, in bytes (default 64 MiB) */
+  maxInputBytes?: number
+  /** Most memory the image decoder may allocate, in bytes (default 512 MiB) */
+  maxDecodeMemory?: number
+}
+/** Already-decoded pixels; a canvas `ImageData` can be passed as is */
+export interface PixelData {
+  /** Pixel bytes, e.g. `ImageData.data` */
+  data: Uint8ClampedArray
+  width: number
+  height: number
+  /** Bytes per row including padding (default: tightly packed) */
+  stride?: number
+  /** Channel layout: luma8, rgb8, rgba8 or bgra8 (default rgba8, as in ImageData) */
+  format?: string
 }
 /**
  * Validate a QR code image and compute scannability score
  *
  * @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+ * @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
  * @returns ValidationResult with score, content, and metadata
  */
-export declare function validate(imageBuffer: Buffer): ValidationResult
+export declare function validate(imageBuffer: Buffer, options?: DecodeOptions | undefined | null): ValidationResult
 /**
  * Fast decode without stress tests (for when you only need content)
  *
  * @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+ * @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
  * @returns DecodeResult with content and basic metadata
  */
-export declare function decode(imageBuffer: Buffer): DecodeResult
+export declare function decode(imageBuffer: Buffer, options?: DecodeOptions | undefined | null): DecodeResult
+/**
+ * Decode with every decoder over several preprocessing variants
+ *
+ * Slower than `decode`; use it to screen untrusted uploads for codes that
+ * read differently on different scanners.
+ *
+ * @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+ * @param options - Optional decode options; only the size limits apply
+ * @returns ConsensusResult with every distinct content and an ambiguous flag
+ */
+export declare function decodeConsensus(imageBuffer: Buffer, options?: DecodeOptions | undefined | null): ConsensusResult
+/**
+ * Find every symbol in a large scene by scanning overlapping tiles
+ *
+ * Reads codes that cover only a few percent of a photo, which `decode`
+ * shrinks below decodable size. Throws if no tile decodes.
+ *
+ * @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+ * @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
+ * @returns Detection per symbol, with corners in image coordinates
+ */
+export declare function decodeTiled(imageBuffer: Buffer, options?: DecodeOptions | undefined | null): Array<Detection>
+/**
+ * Reassemble a Structured Append message from one or more images
+ *
+ * Parts may be in any order and several may share one image. Throws
+ * listing the missing parts, or if the parity check fails.
+ *
+ * @param imageBuffers - Raw image bytes (PNG, JPEG, etc.), one per image
+ * @param options - Optional decode options; only the size limits apply, to each image
+ * @returns AppendedMessageResult with the full message
+ */
+export declare function decodeStructuredAppend(imageBuffers: Array<Buffer>, options?: DecodeOptions | undefined | null): AppendedMessageResult
 /**
  * Fast validation with reduced stress tests (~2x faster)
  *
  * Good for real-time feedback during QR editing.
  *
  * @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+ * @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
+ * @returns ValidationResult with score, content, and metadata
+ */
+export declare function validateFast(imageBuffer: Buffer, options?: DecodeOptions | undefined | null): ValidationResult
+/**
+ * Validate already-decoded pixels (camera frames, canvas `ImageData`)
+ *
+ * Skips the PNG/JPEG encode and decode round trip of `validate`.
+ *
+ * @param pixels - `{ data, width, height, stride?, format? }`, e.g. `ImageData`
+ * @param options - Optional decode options
+ * @returns ValidationResult with score, content, and metadata
+ */
+export declare function validatePixels(pixels: PixelData, options?: DecodeOptions | undefined | null): ValidationResult
+/**
+ * Fast validation of already-decoded pixels (~2x faster)
+ *
+ * @param pixels - `{ data, width, height, stride?, format? }`, e.g. `ImageData`
+ * @param options - Optional decode options
  * @returns ValidationResult with score, content, and metadata
  */
-export declare function validateFast(imageBuffer: Buffer): ValidationResult
+export declare function validateFastPixels(pixels: PixelData, options?: DecodeOptions | undefined | null): ValidationResult
+/**
+ * Decode already-decoded pixels without stress tests
+ *
+ * @param pixels - `{ data, width, height, stride?, format? }`, e.g. `ImageData`
+ * @param options - Optional decode options
+ * @returns DecodeResult with content and basic metadata
+ */
+export declare function decodePixels(pixels: PixelData, options?: DecodeOptions | undefined | null): DecodeResult
 /**
  * Get only the scannability score (0-100)
  *
@@ -320,11 +320,17 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { validate, decode, validateFast, validateScoreOnly, validateScoreFast, isValid, score, passesThreshold, isProductionReady, summarize, getRating } = nativeBinding
+const { validate, decode, decodeConsensus, decodeTiled, decodeStructuredAppend, validateFast, validatePixels, validateFastPixels, decodePixels, validateScoreOnly, validateScoreFast, isValid, score, passesThreshold, isProductionReady, summarize, getRating } = nativeBinding
 
 module.exports.validate = validate
 module.exports.decode = decode
+module.exports.decodeConsensus = decodeConsensus
+module.exports.decodeTiled = decodeTiled
+module.exports.decodeStructuredAppend = decodeStructuredAppend
 module.exports.validateFast = validateFast
+module.exports.validatePixels = validatePixels
+module.exports.validateFastPixels = validateFastPixels
+module.exports.decodePixels = decodePixels
 module.exports.validateScoreOnly = validateScoreOnly
 module.exports.validateScoreFast = validateScoreFast
 module.exports.isValid = isValid
@@ -1,9 +1,17 @@
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use qrcode_ai_scanner_core::{
-    decode_only as core_decode_only, validate as core_validate,
-    validate_fast as core_validate_fast, ErrorCorrectionLevel,
+    decode_consensus_with as core_decode_consensus_with, decode_only as core_decode_only,
+    decode_only_pixels as core_decode_only_pixels, decode_only_with as core_decode_only_with,
+    decode_structured_append_with as core_decode_structured_append_with, decode_tiled as core_decode_tiled,
+    validate as core_validate,
+    validate_fast as core_validate_fast, validate_fast_pixels as core_validate_fast_pixels,
+    validate_fast_with as core_validate_fast_with, validate_pixels as core_validate_pixels,
+    validate_with as core_validate_with, DecodeOptions as CoreDecodeOptions,
+    DecodeResult as CoreDecodeResult, DecodeStage, DecodeTrace as CoreDecodeTrace, ErrorCorrectionLevel, ParsedPayload, PixelBuffer, PixelFormat,
+    QrMetadata, ResourceLimits, RoiSource, Symbology, UpscaleFilter, UrlRisk, ValidationResult as CoreValidationResult,
 };
+use std::collections::HashMap;
 
 /// QR code validation result
 #[napi(object)]
@@ -14,7 +22,9 @@ pub struct ValidationResult {
     pub decodable: bool,
     /// Decoded content of the QR code
     pub content: Option<String>,
-    /// QR code version (1-40)
+    /// Symbol family (QR, Micro QR, rMQR, Data Matrix, Aztec, PDF417)
+    pub symbology: Option<String>,
+    /// Version within the symbology (QR 1-40, Micro QR 1-4, rMQR 1-32)
     pub version: Option<u8>,
     /// Error correction level (L, M, Q, H)
     pub error_correction: Option<String>,
@@ -22,6 +32,14 @@ pub struct ValidationResult {
     pub modules: Option<u8>,
     /// List of decoders that successfully decoded the QR
     pub decoders_success: Vec<String>,
+    /// Share of the error correction budget consumed by the worst block (0-1)
+    pub ec_used_ratio: Option<f64>,
+    /// Light modules on a dark background, which many phone scanners miss
+    pub inverted: bool,
+    /// Mirror-image symbol, which many phone scanners miss
+    pub mirrored: bool,
+    /// Decode stage that read the symbol, and the crop it ran on
+    pub trace: Option<DecodeTrace>,
     /// Whether original image was decodable
     pub stress_original: bool,
     /// Whether 50% downscaled image was decodable
@@ -34,6 +52,32 @@ pub struct ValidationResult {
     pub stress_blur_medium: bool,
     /// Whether low contrast image was decodable
     pub stress_low_contrast: bool,
+    /// Typed content with field validation errors
+    pub payload: Option<Payload>,
+    /// Phishing risk of link content (safe, low, medium, high)
+    pub url_risk_level: Option<String>,
+    /// Why the link was flagged
+    pub url_risk_reasons: Vec<String>,
+}
+
+/// Typed QR content
+#[napi(object)]
+pub struct Payload {
+    /// Content type (url, wifi, contact, email, phone, sms, geo, event, payment, gs1, text)
+    pub kind: String,
+    /// Parsed fields by name (lists are comma-joined)
+    pub fields: HashMap<String, String>,
+    /// Missing or malformed fields
+    pub errors: Vec<PayloadFieldError>,
+}
+
+/// Payload field that failed validation
+#[napi(object)]
+pub struct PayloadFieldError {
+    /// Field name, as in `fields`
+    pub field: String,
+    /// What is wrong with it
+    pub message: String,
 }
 
 /// Simple decode result (without stress tests)
@@ -41,76 +85,270 @@ pub struct ValidationResult {
 pub struct DecodeResult {
     /// Decoded content of the QR code
     pub content: String,
-    /// QR code version (1-40)
+    /// Symbol family (QR, Micro QR, rMQR, Data Matrix, Aztec, PDF417)
+    pub symbology: Option<String>,
+    /// Version within the symbology (QR 1-40, Micro QR 1-4, rMQR 1-32)
     pub version: Option<u8>,
     /// Error correction level (L, M, Q, H)
     pub error_correction: Option<String>,
     /// Number of modules in the QR code
     pub modules: Option<u8>,
+    /// Share of the error correction budget consumed by the worst block (0-1)
+    pub ec_used_ratio: Option<f64>,
+    /// Light modules on a dark background, which many phone scanners miss
+    pub inverted: bool,
+    /// Mirror-image symbol, which many phone scanners miss
+    pub mirrored: bool,
+    /// Payload bytes exactly as encoded (content is a lossy text view)
+    pub raw_bytes: Option<Buffer>,
+    /// ECI designator declared in the QR code
+    pub eci: Option<u32>,
+    /// Declared or detected character set (UTF-8, ISO-8859-1, Shift_JIS, US-ASCII)
+    pub charset: Option<String>,
+    /// Encoding-mode segments of the payload, in order
+    pub segments: Vec<Segment>,
+    /// Decode stage that read the symbol, and the crop it ran on
+    pub trace: Option<DecodeTrace>,
+}
+
+/// How a symbol was read
+#[napi(object)]
+pub struct DecodeTrace {
+    /// Decode stage (original, quick_threshold, upscale, parallel_pool, module_centres, brute_force)
+    pub stage: String,
+    /// Region of interest the stage was cropped to; absent for the whole image
+    pub region: Option<Rect>,
+    /// How the region was found (grid, finder_patterns, texture, pyramid)
+    pub region_source: Option<String>,
+    /// Filter used when the image had to be upscaled (nearest, bicubic, edge_preserving)
+    pub upscale_filter: Option<String>,
+    /// Integer factor the image was upscaled by
+    pub upscale_factor: Option<u32>,
+    /// Whether the EC level and mask were recovered by trying every combination
+    pub format_recovered: bool,
+}
+
+/// Encoding-mode segment of a decoded payload
+#[napi(object)]
+pub struct Segment {
+    /// Encoding mode (numeric, alphanumeric, byte, kanji)
+    pub mode: String,
+    /// Character count from the segment header
+    pub char_count: u32,
+    /// Bytes the segment contributes to the raw payload
+    pub byte_len: u32,
+}
+
+/// Every content read by every decoder across preprocessing variants
+#[napi(object)]
+pub struct ConsensusResult {
+    /// Distinct contents, most-read first
+    pub readings: Vec<ContentReading>,
+    /// More than one distinct content was read (treat as suspicious)
+    pub ambiguous: bool,
+    /// Preprocessing variants decoded (each by every decoder)
+    pub variants_tried: u32,
+}
+
+/// Message reassembled from Structured Append parts
+#[napi(object)]
+pub struct AppendedMessageResult {
+    /// Full message, parts joined in order
+    pub content: String,
+    /// Number of symbols the message was split across
+    pub total: u32,
+    /// Parity byte from the headers, verified against the message
+    pub parity: u32,
+    /// ECI designator declared by the first part
+    pub eci: Option<u32>,
+    /// Declared (ECI) or detected character set of the message
+    pub charset: String,
+}
+
+/// A symbol found by `decodeTiled`, positioned in the whole image
+#[napi(object)]
+pub struct Detection {
+    /// Decoded content and metadata
+    pub result: DecodeResult,
+    /// Symbol corners in image pixels (top-left, top-right, bottom-right,
+    /// bottom-left), when the symbol could be located
+    pub corners: Option<Vec<Point>>,
+    /// Tile the symbol was read from, in image pixels
+    pub tile: Rect,
+}
+
+/// Image position in pixels
+#[napi(object)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Image rectangle in pixels
+#[napi(object)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One distinct content read from the image
+#[napi(object)]
+pub struct ContentReading {
+    /// Decoded content
+    pub content: String,
+    /// Decoders that read it
+    pub decoders: Vec<String>,
+    /// Preprocessing variants it was read from
+    pub variants: Vec<String>,
+    /// Number of (decoder, variant) reads that produced it
+    pub reads: u32,
+}
+
+/// Options for decode and validate calls
+#[napi(object)]
+pub struct DecodeOptions {
+    /// Symbologies to accept: qr, microqr, rmqr, datamatrix, aztec, pdf417
+    /// (default: the QR family)
+    pub formats: Option<Vec<String>>,
+    /// Widest image accepted, in pixels (default 10000)
+    pub max_width: Option<u32>,
+    /// Tallest image accepted, in pixels (default 10000)
+    pub max_height: Option<u32>,
+    /// Largest width x height accepted (default 100000000)
+    pub max_pixels: Option<f64>,
+    /// Largest input buffer accepted, in bytes (default 64 MiB)
+    pub max_input_bytes: Option<f64>,
+    /// Most memory the image decoder may allocate, in bytes (default 512 MiB)
+    pub max_decode_memory: Option<f64>,
+}
+
+/// Already-decoded pixels; a canvas `ImageData` can be passed as is
+#[napi(object, object_to_js = false)]
+pub struct PixelData {
+    /// Pixel bytes, e.g. `ImageData.data`
+    pub data: Uint8ClampedArray,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes per row including padding (default: tightly packed)
+    pub stride: Option<u32>,
+    /// Channel layout: luma8, rgb8, rgba8 or bgra8 (default rgba8, as in ImageData)
+    pub format: Option<String>,
 }
 
 /// Validate a QR code image and compute scannability score
 ///
 /// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+/// @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
 /// @returns ValidationResult with score, content, and metadata
 #[napi]
-pub fn validate(image_buffer: Buffer) -> Result<ValidationResult> {
-    let result = core_validate(&image_buffer)
+pub fn validate(image_buffer: Buffer, options: Option<DecodeOptions>) -> Result<ValidationResult> {
+    let result = core_validate_with(&image_buffer, &decode_options(options)?)
         .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(convert_validation(result))
+}
 
-    let (version, error_correction, modules, decoders_success) =
-        if let Some(ref meta) = result.metadata {
-            (
-                Some(meta.version),
-                Some(ec_to_string(meta.error_correction)),
-                Some(meta.modules),
-                meta.decoders_success.clone(),
-            )
-        } else {
-            (None, None, None, vec![])
-        };
+/// Fast decode without stress tests (for when you only need content)
+///
+/// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+/// @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
+/// @returns DecodeResult with content and basic metadata
+#[napi]
+pub fn decode(image_buffer: Buffer, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+    let result = core_decode_only_with(&image_buffer, &decode_options(options)?)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(convert_decode(result))
+}
 
-    Ok(ValidationResult {
-        score: result.score,
-        decodable: result.decodable,
-        content: result.content,
-        version,
-        error_correction,
-        modules,
-        decoders_success,
-        stress_original: result.stress_results.original,
-        stress_downscale_50: result.stress_results.downscale_50,
-        stress_downscale_25: result.stress_results.downscale_25,
-        stress_blur_light: result.stress_results.blur_light,
-        stress_blur_medium: result.stress_results.blur_medium,
-        stress_low_contrast: result.stress_results.low_contrast,
+/// Decode with every decoder over several preprocessing variants
+///
+/// Slower than `decode`; use it to screen untrusted uploads for codes that
+/// read differently on different scanners.
+///
+/// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+/// @param options - Optional decode options; only the size limits apply
+/// @returns ConsensusResult with every distinct content and an ambiguous flag
+#[napi]
+pub fn decode_consensus(image_buffer: Buffer, options: Option<DecodeOptions>) -> Result<ConsensusResult> {
+    let limits = decode_options(options)?.limits;
+    let consensus = core_decode_consensus_with(&image_buffer, &limits)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    Ok(ConsensusResult {
+        readings: consensus
+            .readings
+            .into_iter()
+            .map(|r| ContentReading {
+                content: r.content,
+                decoders: r.decoders,
+                variants: r.variants,
+                reads: r.reads,
+            })
+            .collect(),
+        ambiguous: consensus.ambiguous,
+        variants_tried: consensus.variants_tried,
     })
 }
 
-/// Fast decode without stress tests (for when you only need content)
+/// Find every symbol in a large scene by scanning overlapping tiles
+///
+/// Reads codes that cover only a few percent of a photo, which `decode`
+/// shrinks below decodable size. Throws if no tile decodes.
 ///
 /// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
-/// @returns DecodeResult with content and basic metadata
+/// @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
+/// @returns Detection per symbol, with corners in image coordinates
 #[napi]
-pub fn decode(image_buffer: Buffer) -> Result<DecodeResult> {
-    let result = core_decode_only(&image_buffer)
+pub fn decode_tiled(image_buffer: Buffer, options: Option<DecodeOptions>) -> Result<Vec<Detection>> {
+    let detections = core_decode_tiled(&image_buffer, &decode_options(options)?)
         .map_err(|e| Error::from_reason(e.to_string()))?;
 
-    let (version, error_correction, modules) = if let Some(ref meta) = result.metadata {
-        (
-            Some(meta.version),
-            Some(ec_to_string(meta.error_correction)),
-            Some(meta.modules),
-        )
-    } else {
-        (None, None, None)
-    };
+    Ok(detections
+        .into_iter()
+        .map(|d| Detection {
+            corners: d
+                .corners
+                .map(|corners| corners.iter().map(|&(x, y)| Point { x: x as f64, y: y as f64 }).collect()),
+            tile: Rect {
+                x: d.tile.x,
+                y: d.tile.y,
+                width: d.tile.width,
+                height: d.tile.height,
+            },
+            result: convert_decode(CoreDecodeResult {
+                content: d.content,
+                raw_bytes: d.raw_bytes,
+                metadata: d.metadata,
+            }),
+        })
+        .collect())
+}
 
-    Ok(DecodeResult {
-        content: result.content,
-        version,
-        error_correction,
-        modules,
+/// Reassemble a Structured Append message from one or more images
+///
+/// Parts may be in any order and several may share one image. Throws
+/// listing the missing parts, or if the parity check fails.
+///
+/// @param imageBuffers - Raw image bytes (PNG, JPEG, etc.), one per image
+/// @param options - Optional decode options; only the size limits apply, to each image
+/// @returns AppendedMessageResult with the full message
+#[napi]
+pub fn decode_structured_append(
+    image_buffers: Vec<Buffer>,
+    options: Option<DecodeOptions>,
+) -> Result<AppendedMessageResult> {
+    let limits = decode_options(options)?.limits;
+    let images: Vec<&[u8]> = image_buffers.iter().map(|b| b.as_ref()).collect();
+    let message = core_decode_structured_append_with(&images, &limits)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    Ok(AppendedMessageResult {
+        content: message.content,
+        total: message.total as u32,
+        parity: message.parity as u32,
+        eci: message.eci,
+        charset: message.charset.to_string(),
     })
 }
 
@@ -119,12 +357,76 @@ pub fn decode(image_buffer: Buffer) -> Result<DecodeResult> {
 /// Good for real-time feedback during QR editing.
 ///
 /// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+/// @param options - Optional decode options (e.g. `{ formats: ['qr', 'datamatrix'] }`)
+/// @returns ValidationResult with score, content, and metadata
+#[napi]
+pub fn validate_fast(image_buffer: Buffer, options: Option<DecodeOptions>) -> Result<ValidationResult> {
+    let result = core_validate_fast_with(&image_buffer, &decode_options(options)?)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(convert_validation(result))
+}
+
+/// Validate already-decoded pixels (camera frames, canvas `ImageData`)
+///
+/// Skips the PNG/JPEG encode and decode round trip of `validate`.
+///
+/// @param pixels - `{ data, width, height, stride?, format? }`, e.g. `ImageData`
+/// @param options - Optional decode options
 /// @returns ValidationResult with score, content, and metadata
 #[napi]
-pub fn validate_fast(image_buffer: Buffer) -> Result<ValidationResult> {
+pub fn validate_pixels(pixels: PixelData, options: Option<DecodeOptions>) -> Result<ValidationResult> {
+    let options = decode_options(options)?;
+    let result = with_pixel_buffer(&pixels, |buffer| core_validate_pixels(buffer, &options))?;
+    Ok(convert_validation(result))
+}
+
+/// Fast validation of already-decoded pixels (~2x faster)
+///
+/// @param pixels - `{ data, width, height, stride?, format? }`, e.g. `ImageData`
+/// @param options - Optional decode options
+/// @returns ValidationResult with score, content, and metadata
+#[napi]
+pub fn validate_fast_pixels(pixels: PixelData, options: Option<DecodeOptions>) -> Result<ValidationResult> {
+    let options = decode_options(options)?;
+    let result = with_pixel_buffer(&pixels, |buffer| core_validate_fast_pixels(buffer, &options))?;
+    Ok(convert_validation(result))
+}
+
+/// Decode already-decoded pixels without stress tests
+///
+/// @param pixels - `{ data, width, height, stride?, format? }`, e.g. `ImageData`
+/// @param options - Optional decode options
+/// @returns DecodeResult with content and basic metadata
+#[napi]
+pub fn decode_pixels(pixels: PixelData, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+    let options = decode_options(options)?;
+    let result = with_pixel_buffer(&pixels, |buffer| core_decode_only_pixels(buffer, &options))?;
+    Ok(convert_decode(result))
+}
+
+/// Get only the scannability score (0-100)
+///
+/// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+/// @returns Score from 0 (unreadable) to 100 (highly scannable)
+#[napi]
+pub fn validate_score_only(image_buffer: Buffer) -> Result<u8> {
+    let result = core_validate(&image_buffer)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(result.score)
+}
+
+/// Get score using fast validation (~2x faster)
+///
+/// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
+/// @returns Score from 0 (unreadable) to 100 (highly scannable)
+#[napi]
+pub fn validate_score_fast(image_buffer: Buffer) -> Result<u8> {
     let result = core_validate_fast(&image_buffer)
         .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(result.score)
+}
 
+fn convert_validation(result: CoreValidationResult) -> ValidationResult {
     let (version, error_correction, modules, decoders_success) =
         if let Some(ref meta) = result.metadata {
             (
@@ -136,44 +438,173 @@ pub fn validate_fast(image_buffer: Buffer) -> Result<ValidationResult> {
         } else {
             (None, None, None, vec![])
         };
+    let ec_used_ratio = ec_used_ratio(&result.metadata);
+    let (inverted, mirrored) = orientation(&result.metadata);
+    let (url_risk_level, url_risk_reasons) = url_risk(result.url_risk);
 
-    Ok(ValidationResult {
+    ValidationResult {
         score: result.score,
         decodable: result.decodable,
         content: result.content,
+        symbology: result.metadata.as_ref().map(|m| m.symbology.to_string()),
         version,
         error_correction,
         modules,
         decoders_success,
+        ec_used_ratio,
+        inverted,
+        mirrored,
+        trace: trace(&result.metadata),
         stress_original: result.stress_results.original,
         stress_downscale_50: result.stress_results.downscale_50,
         stress_downscale_25: result.stress_results.downscale_25,
         stress_blur_light: result.stress_results.blur_light,
         stress_blur_medium: result.stress_results.blur_medium,
         stress_low_contrast: result.stress_results.low_contrast,
-    })
+        payload: result.payload.map(convert_payload),
+        url_risk_level,
+        url_risk_reasons,
+    }
 }
 
-/// Get only the scannability score (0-100)
-///
-/// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
-/// @returns Score from 0 (unreadable) to 100 (highly scannable)
-#[napi]
-pub fn validate_score_only(image_buffer: Buffer) -> Result<u8> {
-    let result = core_validate(&image_buffer)
-        .map_err(|e| Error::from_reason(e.to_string()))?;
-    Ok(result.score)
+fn convert_decode(result: CoreDecodeResult) -> DecodeResult {
+    let (version, error_correction, modules) = if let Some(ref meta) = result.metadata {
+        (
+            Some(meta.version),
+            Some(ec_to_string(meta.error_correction)),
+            Some(meta.modules),
+        )
+    } else {
+        (None, None, None)
+    };
+    let ec_used_ratio = ec_used_ratio(&result.metadata);
+    let (inverted, mirrored) = orientation(&result.metadata);
+    let symbology = result.metadata.as_ref().map(|m| m.symbology.to_string());
+    let trace = trace(&result.metadata);
+    let (eci, charset, segments) = match result.metadata {
+        Some(meta) => (
+            meta.eci,
+            meta.charset.map(|c| c.to_string()),
+            meta.segments
+                .iter()
+                .map(|s| Segment {
+                    mode: s.mode.to_string(),
+                    char_count: s.char_count,
+                    byte_len: s.byte_len,
+                })
+                .collect(),
+        ),
+        None => (None, None, Vec::new()),
+    };
+
+    DecodeResult {
+        content: result.content,
+        symbology,
+        version,
+        error_correction,
+        modules,
+        ec_used_ratio,
+        inverted,
+        mirrored,
+        raw_bytes: result.raw_bytes.map(Buffer::from),
+        eci,
+        charset,
+        segments,
+        trace,
+    }
 }
 
-/// Get score using fast validation (~2x faster)
-///
-/// @param imageBuffer - Raw image bytes (PNG, JPEG, etc.)
-/// @returns Score from 0 (unreadable) to 100 (highly scannable)
-#[napi]
-pub fn validate_score_fast(image_buffer: Buffer) -> Result<u8> {
-    let result = core_validate_fast(&image_buffer)
-        .map_err(|e| Error::from_reason(e.to_string()))?;
-    Ok(result.score)
+fn convert_payload(parsed: ParsedPayload) -> Payload {
+    Payload {
+        kind: parsed.payload.kind().to_string(),
+        fields: parsed
+            .payload
+            .fields()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+        errors: parsed
+            .errors
+            .into_iter()
+            .map(|e| PayloadFieldError {
+                field: e.field,
+                message: e.message,
+            })
+            .collect(),
+    }
+}
+
+/// Run a core call on the pixel buffer described by `pixels`
+fn with_pixel_buffer<T>(
+    pixels: &PixelData,
+    run: impl FnOnce(&PixelBuffer) -> qrcode_ai_scanner_core::Result<T>,
+) -> Result<T> {
+    let format = match pixels.format.as_deref() {
+        None => PixelFormat::Rgba8,
+        Some(name) => PixelFormat::from_name(name)
+            .ok_or_else(|| Error::from_reason(format!("Unknown pixel format: {}", name)))?,
+    };
+    let mut buffer = PixelBuffer::new(&pixels.data, pixels.width, pixels.height, format);
+    if let Some(stride) = pixels.stride {
+        buffer.stride = stride as usize;
+    }
+    run(&buffer).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+fn decode_options(options: Option<DecodeOptions>) -> Result<CoreDecodeOptions> {
+    let mut core = CoreDecodeOptions::default();
+    let Some(options) = options else {
+        return Ok(core);
+    };
+    if let Some(names) = &options.formats {
+        core.formats = names
+            .iter()
+            .map(|name| {
+                Symbology::from_name(name)
+                    .ok_or_else(|| Error::from_reason(format!("Unknown format: {}", name)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+    }
+    core.limits = resource_limits(&options, core.limits)?;
+    Ok(core)
+}
+
+/// Apply the `max*` options over `limits`
+fn resource_limits(options: &DecodeOptions, mut limits: ResourceLimits) -> Result<ResourceLimits> {
+    // JS numbers arrive as f64; reject anything that is not a non-negative integer
+    let whole = |name: &str, value: f64| {
+        if value >= 0.0 && value.fract() == 0.0 {
+            Ok(value as u64)
+        } else {
+            Err(Error::from_reason(format!("{} must be a non-negative integer, got {}", name, value)))
+        }
+    };
+    if let Some(width) = options.max_width {
+        limits.max_width = width;
+    }
+    if let Some(height) = options.max_height {
+        limits.max_height = height;
+    }
+    if let Some(pixels) = options.max_pixels {
+        limits.max_pixels = whole("maxPixels", pixels)?;
+    }
+    if let Some(bytes) = options.max_input_bytes {
+        limits.max_input_bytes = usize::try_from(whole("maxInputBytes", bytes)?).unwrap_or(usize::MAX);
+    }
+    if let Some(memory) = options.max_decode_memory {
+        limits.max_decode_memory = whole("maxDecodeMemory", memory)?;
+    }
+    Ok(limits)
+}
+
+fn url_risk(risk: Option<UrlRisk>) -> (Option<String>, Vec<String>) {
+    match risk {
+        Some(risk) => (
+            Some(risk.level.to_string()),
+            risk.reasons.into_iter().map(|r| r.detail).collect(),
+        ),
+        None => (None, Vec::new()),
+    }
 }
 
 fn ec_to_string(ec: ErrorCorrectionLevel) -> String {
@@ -185,6 +616,52 @@ fn ec_to_string(ec: ErrorCorrectionLevel) -> String {
     }
 }
 
+fn ec_used_ratio(metadata: &Option<QrMetadata>) -> Option<f64> {
+    metadata.as_ref().and_then(|m| m.ec_used_ratio).map(f64::from)
+}
+
+fn orientation(metadata: &Option<QrMetadata>) -> (bool, bool) {
+    metadata.as_ref().map_or((false, false), |m| (m.inverted, m.mirrored))
+}
+
+fn trace(metadata: &Option<QrMetadata>) -> Option<DecodeTrace> {
+    let CoreDecodeTrace { stage, roi, upscale, format_recovered } = metadata.as_ref()?.trace?;
+    let stage = match stage {
+        DecodeStage::Original => "original",
+        DecodeStage::QuickThreshold => "quick_threshold",
+        DecodeStage::Upscale => "upscale",
+        DecodeStage::ParallelPool => "parallel_pool",
+        DecodeStage::ModuleCentres => "module_centres",
+        DecodeStage::BruteForce => "brute_force",
+    };
+    let source = |source: RoiSource| match source {
+        RoiSource::Grid => "grid",
+        RoiSource::FinderPatterns => "finder_patterns",
+        RoiSource::Texture => "texture",
+        RoiSource::Pyramid => "pyramid",
+    };
+    Some(DecodeTrace {
+        stage: stage.to_string(),
+        region: roi.map(|r| Rect {
+            x: r.region.x,
+            y: r.region.y,
+            width: r.region.width,
+            height: r.region.height,
+        }),
+        region_source: roi.map(|r| source(r.source).to_string()),
+        upscale_filter: upscale.map(|u| {
+            match u.filter {
+                UpscaleFilter::Nearest => "nearest",
+                UpscaleFilter::Bicubic => "bicubic",
+                UpscaleFilter::EdgePreserving => "edge_preserving",
+            }
+            .to_string()
+        }),
+        upscale_factor: upscale.map(|u| u.factor),
+        format_recovered,
+    })
+}
+
 // ============================================================================
 // CONVENIENCE HELPERS - Simple one-liners for common tasks
 // ============================================================================